@@ -0,0 +1,73 @@
+//! Allocation bitmaps for filesystem images
+//!
+//! Most of a mostly-empty filesystem image is free space. With an
+//! [`AllocationBitmap`](struct.AllocationBitmap.html) attached, a
+//! [`ChunkedHasher`](../struct.ChunkedHasher.html) skips every chunk that
+//! doesn't contain a single allocated block. The bitmap is supplied by the
+//! caller, e.g. read from the ext4 block group descriptors or the NTFS
+//! `$Bitmap` file.
+
+use anyhow::{ensure, Result};
+
+/// Block allocation bitmap, one bit per block with the least significant bit
+/// of the first byte describing the first block
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocationBitmap {
+    /// Bitmap bytes
+    bitmap: Vec<u8>,
+    /// Size of the blocks described by each bit
+    block_size: u64,
+}
+
+impl AllocationBitmap {
+    /// Instantiate an allocation bitmap
+    /// # Arguments
+    /// * `bitmap` - bitmap bytes, blocks past the end of the bitmap are
+    ///   considered allocated
+    /// * `block_size` - size of the blocks described by each bit
+    pub fn new(bitmap: Vec<u8>, block_size: u64) -> Result<Self> {
+        ensure!(block_size > 0, "Block size must be greater than zero");
+        Ok(Self { bitmap, block_size })
+    }
+
+    /// Whether any block overlapping the byte range is allocated
+    /// # Arguments
+    /// * `offset` - start of the byte range
+    /// * `length` - length of the byte range
+    pub fn is_allocated(&self, offset: u64, length: u64) -> bool {
+        if length == 0 {
+            return false;
+        }
+        let first_block = offset / self.block_size;
+        let last_block = (offset + (length - 1)) / self.block_size;
+        (first_block..=last_block).any(|block| self.block_allocated(block))
+    }
+
+    fn block_allocated(&self, block: u64) -> bool {
+        match self.bitmap.get((block / 8) as usize) {
+            Some(byte) => byte & (1 << (block % 8)) != 0,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_ranges() -> Result<()> {
+        // Blocks 1 and 9 allocated
+        let bitmap = AllocationBitmap::new(vec![0b0000_0010, 0b0000_0010], 4)?;
+        assert!(!bitmap.is_allocated(0, 4));
+        assert!(bitmap.is_allocated(0, 5));
+        assert!(bitmap.is_allocated(7, 1));
+        assert!(!bitmap.is_allocated(8, 28));
+        assert!(bitmap.is_allocated(8, 29));
+        assert!(!bitmap.is_allocated(4, 0));
+        // Past the end of the bitmap
+        assert!(bitmap.is_allocated(64, 4));
+        assert!(AllocationBitmap::new(vec![], 0).is_err());
+        Ok(())
+    }
+}
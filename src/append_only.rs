@@ -0,0 +1,234 @@
+//! Chained commitments for append-only streams
+//!
+//! A [`ChainState`](struct.ChainState.html) folds every chunk hash into a
+//! running digest, `digest = H(previous digest || index || size || hash)`.
+//! Persisting the state after each chunk allows
+//! [`verify_append_only`](fn.verify_append_only.html) to later prove that
+//! everything covered by the state is still present, unmodified, at the start
+//! of the stream.
+
+use crate::{hashers::Hasher, Chunk, ChunkedHasher, ReadAndSeek};
+use anyhow::{anyhow, ensure, Context, Result};
+use std::str::FromStr;
+
+/// Running commitment over the chunks of an append-only stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainState {
+    /// Fixed chunk size the stream is chunked with
+    pub chunk_size: u64,
+    /// Amount of chunks folded into the commitment
+    pub chunk_count: u64,
+    /// Amount of bytes covered by the commitment
+    pub covered: u64,
+    /// Chained digest, empty until the first chunk is added
+    pub digest: Vec<u8>,
+}
+
+impl ChainState {
+    /// Instantiate an empty commitment
+    /// # Arguments
+    /// * `chunk_size` - fixed chunk size the stream will be chunked with
+    pub fn new(chunk_size: u64) -> Self {
+        Self {
+            chunk_size,
+            chunk_count: 0,
+            covered: 0,
+            digest: Vec::new(),
+        }
+    }
+
+    /// Fold the next chunk into the commitment
+    /// # Arguments
+    /// * `chunk` - the next chunk of the stream
+    pub fn update<H: Hasher>(&mut self, chunk: &Chunk) {
+        let mut input = Vec::with_capacity(self.digest.len() + 16 + chunk.hash.len());
+        input.extend_from_slice(&self.digest);
+        input.extend_from_slice(&chunk.index.to_le_bytes());
+        input.extend_from_slice(&chunk.size.to_le_bytes());
+        input.extend_from_slice(&chunk.hash);
+        self.digest = H::hash_bytes(&input);
+        self.chunk_count += 1;
+        self.covered += chunk.size;
+    }
+
+    /// Compute the commitment over a whole stream
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size of the stream to commit to
+    /// * `chunk_size` - fixed chunk size
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{append_only::{verify_append_only, ChainState}, hashers::sha2::Sha256Hasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// let mut log: Cursor<&[u8]> = Cursor::new(b"first entry\n");
+    /// let state = ChainState::from_stream::<Sha256Hasher>(&mut log, 12, 4)?;
+    /// let mut grown: Cursor<&[u8]> = Cursor::new(b"first entry\nsecond entry\n");
+    /// assert!(verify_append_only::<Sha256Hasher>(&state, &mut grown, 25)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_stream<H: Hasher>(
+        buffer: &mut dyn ReadAndSeek,
+        stream_size: u64,
+        chunk_size: u64,
+    ) -> Result<Self> {
+        let mut state = Self::new(chunk_size);
+        for chunk in ChunkedHasher::<H>::fixed_chunks(buffer, stream_size, chunk_size)? {
+            state.update::<H>(&chunk);
+        }
+        ensure!(
+            state.covered == stream_size,
+            "Stream ended after {} of {} bytes",
+            state.covered,
+            stream_size
+        );
+        Ok(state)
+    }
+}
+
+impl std::fmt::Display for ChainState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use hex::encode;
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.chunk_size,
+            self.chunk_count,
+            self.covered,
+            encode(&self.digest)
+        )
+    }
+}
+
+impl FromStr for ChainState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut fields = s.trim().split('/');
+        let mut next_field = |name: &str| {
+            fields
+                .next()
+                .ok_or_else(|| anyhow!("Missing {} in chain state", name))
+        };
+        let chunk_size = next_field("chunk size")?
+            .parse()
+            .context("Invalid chunk size in chain state")?;
+        let chunk_count = next_field("chunk count")?
+            .parse()
+            .context("Invalid chunk count in chain state")?;
+        let covered = next_field("covered size")?
+            .parse()
+            .context("Invalid covered size in chain state")?;
+        let digest = hex::decode(next_field("digest")?).context("Invalid digest in chain state")?;
+        ensure!(fields.next().is_none(), "Trailing data in chain state");
+        Ok(Self {
+            chunk_size,
+            chunk_count,
+            covered,
+            digest,
+        })
+    }
+}
+
+/// Verify that the stream still starts with the content committed to by an
+/// earlier state, i.e. that it was only appended to since
+///
+/// Returns `false` if the stream was truncated or any committed byte changed.
+/// # Arguments
+/// * `old_state` - previously persisted commitment
+/// * `buffer` - the buffer to verify
+/// * `stream_size` - current size of the stream
+pub fn verify_append_only<H: Hasher>(
+    old_state: &ChainState,
+    buffer: &mut dyn ReadAndSeek,
+    stream_size: u64,
+) -> Result<bool> {
+    if old_state.covered == 0 {
+        return Ok(old_state.chunk_count == 0 && old_state.digest.is_empty());
+    }
+    if stream_size < old_state.covered {
+        return Ok(false);
+    }
+    let mut state = ChainState::new(old_state.chunk_size);
+    for chunk in ChunkedHasher::<H>::fixed_chunks(buffer, old_state.covered, old_state.chunk_size)?
+    {
+        state.update::<H>(&chunk);
+    }
+    Ok(&state == old_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::Sha256Hasher;
+    use std::io::Cursor;
+
+    const LOG: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    #[test]
+    fn appended_stream_verifies() -> Result<()> {
+        let mut old: Cursor<&[u8]> = Cursor::new(&LOG[..35]);
+        let state = ChainState::from_stream::<Sha256Hasher>(&mut old, 35, 10)?;
+        assert_eq!(state.chunk_count, 4);
+        assert_eq!(state.covered, 35);
+        let persisted: ChainState = state.to_string().parse()?;
+        assert_eq!(persisted, state);
+
+        let mut grown: Cursor<&[u8]> = Cursor::new(LOG);
+        assert!(verify_append_only::<Sha256Hasher>(
+            &persisted,
+            &mut grown,
+            LOG.len() as u64
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn rewritten_or_truncated_stream_fails() -> Result<()> {
+        let mut old: Cursor<&[u8]> = Cursor::new(&LOG[..35]);
+        let state = ChainState::from_stream::<Sha256Hasher>(&mut old, 35, 10)?;
+
+        let mut rewritten = LOG.to_vec();
+        rewritten[12] = b'x';
+        let mut rewritten: Cursor<&[u8]> = Cursor::new(&rewritten);
+        assert!(!verify_append_only::<Sha256Hasher>(
+            &state,
+            &mut rewritten,
+            LOG.len() as u64
+        )?);
+
+        let mut truncated: Cursor<&[u8]> = Cursor::new(&LOG[..30]);
+        assert!(!verify_append_only::<Sha256Hasher>(
+            &state,
+            &mut truncated,
+            30
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_state_is_rejected() {
+        assert!("10/1/10".parse::<ChainState>().is_err());
+        assert!("10/1/10/zz".parse::<ChainState>().is_err());
+        assert!("10/1/10/00/00".parse::<ChainState>().is_err());
+    }
+
+    #[test]
+    fn empty_stream_commitment() -> Result<()> {
+        let mut empty: Cursor<&[u8]> = Cursor::new(b"");
+        let state = ChainState::from_stream::<Sha256Hasher>(&mut empty, 0, 10)?;
+        assert_eq!(state, ChainState::new(10));
+        assert_eq!(state.to_string().parse::<ChainState>()?, state);
+        let mut grown: Cursor<&[u8]> = Cursor::new(LOG);
+        assert!(verify_append_only::<Sha256Hasher>(
+            &state,
+            &mut grown,
+            LOG.len() as u64
+        )?);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Content-defined chunking with FastCDC
+//!
+//! Chunk boundaries are placed where a rolling gear hash of the content
+//! matches a mask, so inserting or deleting bytes only moves the boundaries
+//! near the edit instead of every boundary after it. Normalized chunking is
+//! used: a stricter mask before the average chunk size and a looser one after
+//! it keep chunk sizes close to the average.
+//!
+//! The gear table is generated with splitmix64 from a fixed seed, so
+//! boundaries are stable across versions of this crate, but don't match
+//! other FastCDC implementations.
+
+use crate::strategy::ChunkingStrategy;
+use anyhow::{ensure, Result};
+
+/// Gear table, one random value per byte value
+pub(crate) const GEAR: [u64; 256] = random_table(0x6368_756e_6b65_6421);
+
+/// Table of 256 values generated with splitmix64
+pub(crate) const fn random_table(seed: u64) -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut state = seed;
+    let mut index = 0;
+    while index < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+/// FastCDC chunk boundary finder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FastCdc {
+    min_size: u64,
+    avg_size: u64,
+    max_size: u64,
+    /// Mask used before the average size, one bit more than the average
+    /// size calls for
+    mask_small: u64,
+    /// Mask used after the average size, one bit less than the average size
+    /// calls for
+    mask_large: u64,
+}
+
+impl FastCdc {
+    /// Instantiate a boundary finder
+    /// # Arguments
+    /// * `min_size` - minimum chunk size, except for the last chunk
+    /// * `avg_size` - desired average chunk size
+    /// * `max_size` - maximum chunk size
+    pub fn new(min_size: u64, avg_size: u64, max_size: u64) -> Result<Self> {
+        ensure!(min_size > 0, "Minimum size must be greater than zero");
+        ensure!(
+            min_size <= avg_size && avg_size <= max_size,
+            "Chunk sizes must satisfy minimum <= average <= maximum"
+        );
+        ensure!(avg_size >= 4, "Average size must be at least 4");
+        let bits = 63 - avg_size.leading_zeros();
+        // The gear hash shifts left, so the top bits depend on the most bytes
+        let mask = |bits: u32| !0u64 << (64 - bits);
+        Ok(Self {
+            min_size,
+            avg_size,
+            max_size,
+            mask_small: mask(bits + 1),
+            mask_large: mask(bits - 1),
+        })
+    }
+}
+
+impl ChunkingStrategy for FastCdc {
+    fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let length = data.len() as u64;
+        if length <= self.min_size {
+            return data.len();
+        }
+        let end = length.min(self.max_size) as usize;
+        let normal = (self.avg_size as usize).min(end);
+        let mut hash = 0u64;
+        for (index, byte) in data
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(self.min_size as usize)
+        {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = if index < normal {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if hash & mask == 0 {
+                return index + 1;
+            }
+        }
+        end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo random test data
+    fn data(length: usize) -> Vec<u8> {
+        let mut state = 1u32;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn boundaries(cdc: &FastCdc, data: &[u8]) -> Vec<usize> {
+        let mut boundaries = Vec::new();
+        let mut start = 0;
+        while start < data.len() {
+            start += cdc.cut_point(&data[start..]);
+            boundaries.push(start);
+        }
+        boundaries
+    }
+
+    #[test]
+    fn sizes_stay_within_bounds() -> Result<()> {
+        let cdc = FastCdc::new(256, 1024, 4096)?;
+        let data = data(256 * 1024);
+        let boundaries = boundaries(&cdc, &data);
+        let mut previous = 0;
+        for boundary in &boundaries[..boundaries.len() - 1] {
+            assert!((256..=4096).contains(&(boundary - previous)));
+            previous = *boundary;
+        }
+        let average = data.len() / boundaries.len();
+        assert!((512..=2048).contains(&average), "average {}", average);
+        assert!(FastCdc::new(0, 1024, 4096).is_err());
+        assert!(FastCdc::new(256, 8192, 4096).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn insertion_only_moves_nearby_boundaries() -> Result<()> {
+        let cdc = FastCdc::new(256, 1024, 4096)?;
+        let original = data(64 * 1024);
+        let mut edited = original.clone();
+        edited.splice(100..100, b"inserted".iter().cloned());
+        let before = boundaries(&cdc, &original);
+        let after: Vec<usize> = boundaries(&cdc, &edited)
+            .into_iter()
+            .map(|boundary| boundary - 8)
+            .collect();
+        let shared = before.iter().filter(|b| after.contains(b)).count();
+        assert!(shared >= before.len() - 2);
+        Ok(())
+    }
+}
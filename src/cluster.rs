@@ -0,0 +1,197 @@
+//! Grouping of files by the chunk hashes they share
+//!
+//! [`cluster_by_shared_chunks`](fn.cluster_by_shared_chunks.html) links two
+//! manifests when they share enough distinct chunk hashes, and reports every
+//! connected group of linked manifests as a cluster, e.g. to find near
+//! duplicate build artifacts.
+
+use crate::Chunk;
+use anyhow::{ensure, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Group of manifests sharing chunks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cluster {
+    /// Indexes of the manifests in the cluster, in ascending order
+    pub members: Vec<usize>,
+    /// Estimated amount of bytes shared, the size of every distinct chunk
+    /// found in more than one member
+    pub shared_bytes: u64,
+}
+
+/// Cluster manifests sharing more than `threshold` of their chunk hashes
+///
+/// Two manifests are linked when the distinct chunk hashes they have in common
+/// make up more than `threshold` of the distinct chunk hashes of each of
+/// them. Clusters are the connected groups of linked manifests, manifests
+/// that aren't linked to any other aren't reported.
+/// # Arguments
+/// * `manifests` - chunks of every file
+/// * `threshold` - fraction of shared chunk hashes, between 0 and 1
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{cluster::cluster_by_shared_chunks, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # let files = [
+/// #     "brainstormremuneratedisability",
+/// #     "brainstormremuneratedisabilitY",
+/// #     "goalkeepervegetarian",
+/// # ];
+/// let mut manifests: Vec<Vec<Chunk>> = Vec::new();
+/// for data in &files {
+///     let mut buffer: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+///     manifests.push(
+///         ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, data.len() as u64, 10)?
+///             .collect(),
+///     );
+/// }
+/// let clusters = cluster_by_shared_chunks(&manifests, 0.5)?;
+/// assert_eq!(clusters.len(), 1);
+/// assert_eq!(clusters[0].members, vec![0, 1]);
+/// assert_eq!(clusters[0].shared_bytes, 20);
+/// # Ok(())
+/// # }
+/// ```
+pub fn cluster_by_shared_chunks<M: AsRef<[Chunk]>>(
+    manifests: &[M],
+    threshold: f64,
+) -> Result<Vec<Cluster>> {
+    ensure!(
+        (0.0..=1.0).contains(&threshold),
+        "Threshold must be between 0 and 1"
+    );
+    // Distinct chunk hashes of every manifest, with their size
+    let distinct: Vec<HashMap<&[u8], u64>> = manifests
+        .iter()
+        .map(|manifest| {
+            manifest
+                .as_ref()
+                .iter()
+                .map(|chunk| (chunk.hash.as_slice(), chunk.size))
+                .collect()
+        })
+        .collect();
+    let mut owners: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for (member, hashes) in distinct.iter().enumerate() {
+        for hash in hashes.keys() {
+            owners.entry(hash).or_default().push(member);
+        }
+    }
+
+    // Count the shared hashes of every pair of manifests sharing any
+    let mut shared: HashMap<(usize, usize), u64> = HashMap::new();
+    for members in owners.values() {
+        for (position, first) in members.iter().enumerate() {
+            for second in &members[position + 1..] {
+                *shared.entry((*first, *second)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut parents: Vec<usize> = (0..manifests.len()).collect();
+    let mut linked = HashSet::new();
+    for ((first, second), count) in shared {
+        let largest = distinct[first].len().max(distinct[second].len());
+        if count as f64 > threshold * largest as f64 {
+            let (first_root, second_root) = (root(&mut parents, first), root(&mut parents, second));
+            parents[first_root.max(second_root)] = first_root.min(second_root);
+            linked.insert(first);
+            linked.insert(second);
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for member in 0..manifests.len() {
+        if linked.contains(&member) {
+            let cluster = root(&mut parents, member);
+            clusters.entry(cluster).or_default().push(member);
+        }
+    }
+    Ok(clusters
+        .into_values()
+        .map(|members| {
+            let mut seen: HashMap<&[u8], (u64, usize)> = HashMap::new();
+            for member in &members {
+                for (hash, size) in &distinct[*member] {
+                    seen.entry(hash).or_insert((*size, 0)).1 += 1;
+                }
+            }
+            let shared_bytes = seen
+                .values()
+                .filter(|(_, count)| *count > 1)
+                .map(|(size, _)| size)
+                .sum();
+            Cluster {
+                members,
+                shared_bytes,
+            }
+        })
+        .collect())
+}
+
+/// Root of the set `member` belongs to, compressing the path on the way
+fn root(parents: &mut [usize], member: usize) -> usize {
+    let mut root = member;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = member;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(hashes: &[u8]) -> Vec<Chunk> {
+        hashes
+            .iter()
+            .enumerate()
+            .map(|(index, hash)| Chunk {
+                index: index as u64,
+                offset: index as u64 * 10,
+                size: 10,
+                hash: vec![*hash],
+                similarity: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clusters_are_connected_groups() -> Result<()> {
+        let manifests = vec![
+            manifest(&[1, 2, 3, 4]),
+            manifest(&[9, 9, 9]),
+            manifest(&[1, 2, 3, 5]),
+            manifest(&[2, 3, 5, 6]),
+            manifest(&[7, 8]),
+            manifest(&[7, 8, 10]),
+        ];
+        let clusters = cluster_by_shared_chunks(&manifests, 0.5)?;
+        assert_eq!(
+            clusters,
+            vec![
+                Cluster {
+                    members: vec![0, 2, 3],
+                    shared_bytes: 40,
+                },
+                Cluster {
+                    members: vec![4, 5],
+                    shared_bytes: 20,
+                },
+            ]
+        );
+        assert!(cluster_by_shared_chunks(&manifests, 0.9)?.is_empty());
+        assert!(cluster_by_shared_chunks(&manifests, 1.5).is_err());
+        Ok(())
+    }
+}
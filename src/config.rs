@@ -0,0 +1,226 @@
+//! Options of a chunked hasher, grouped by what they decide
+//!
+//! [`ChunkLayout`] decides where the chunks of a stream start and end,
+//! [`HashConfig`] how the data of a chunk is turned into its hash. Observing
+//! the chunks is left to [`hooks`](../hooks/index.html).
+
+use crate::{
+    allocation::AllocationBitmap,
+    domain,
+    hashers::{algorithm::DynHasher, Hasher, KeyedHasher},
+    layout, similarity, ChunkHash, Hashed, StreamSize,
+};
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+/// Where the chunks of a stream start and end
+pub(crate) struct ChunkLayout {
+    /// Size of the chunks to use per read cycle
+    pub(crate) chunk_size: u64,
+    /// Distance between the start of two consecutive chunks
+    pub(crate) stride: u64,
+    /// Hint pertaining to the total stream size, `u64::MAX` if unknown
+    pub(crate) stream_size: u64,
+    /// Whether the stream size is unknown and the stream is read
+    /// sequentially instead of seeking to every chunk
+    pub(crate) sequential: bool,
+    /// Start offset of every chunk, when chunk boundaries are explicit
+    pub(crate) boundaries: Option<Vec<u64>>,
+    /// End of the byte range to hash, when only part of the stream is hashed
+    pub(crate) range_end: Option<u64>,
+    /// Allocation bitmap of the stream, chunks without allocated blocks
+    /// are skipped
+    pub(crate) allocation: Option<AllocationBitmap>,
+    /// Data regions of a sparse file, chunks entirely within the holes
+    /// between them are hashed as zeros without being read
+    pub(crate) data_regions: Option<Vec<Range<u64>>>,
+    /// Whether a short last chunk is hashed zero-padded to the chunk size
+    pub(crate) pad_last_chunk: bool,
+}
+
+impl ChunkLayout {
+    pub(crate) fn new(stream_size: StreamSize, chunk_size: u64, stride: u64) -> Self {
+        Self {
+            chunk_size,
+            stride,
+            stream_size: match stream_size {
+                StreamSize::Known(size) => size,
+                StreamSize::Unknown => u64::MAX,
+            },
+            sequential: stream_size == StreamSize::Unknown,
+            boundaries: None,
+            range_end: None,
+            allocation: None,
+            data_regions: None,
+            pad_last_chunk: true,
+        }
+    }
+
+    /// Amount of chunks of the whole stream, `None` if the stream size is
+    /// unknown
+    pub(crate) fn chunk_count(&self) -> Option<u64> {
+        if self.sequential {
+            return None;
+        }
+        if let Some(boundaries) = &self.boundaries {
+            return Some(boundaries.len() as u64);
+        }
+        Some(layout::chunk_count(
+            self.stream_size,
+            self.chunk_size,
+            self.stride,
+        ))
+    }
+
+    /// Offset and length of a chunk, `None` past the last chunk or the end
+    /// of the range
+    /// # Arguments
+    /// * `index` - index of the chunk
+    pub(crate) fn chunk_range(&self, index: u64) -> Option<(u64, u64)> {
+        let (offset, length) = match &self.boundaries {
+            Some(boundaries) => {
+                let position = index as usize;
+                let offset = *boundaries.get(position)?;
+                let end = boundaries.get(position + 1).unwrap_or(&self.stream_size);
+                (offset, layout::distance(offset, *end))
+            }
+            None => {
+                if layout::is_exhausted(index, self.chunk_size, self.stride, self.stream_size) {
+                    return None;
+                }
+                let offset = layout::chunk_offset(index, self.stride)?;
+                // Never read past the stream size hint, the buffer may be
+                // longer than what we were asked to hash
+                (
+                    offset,
+                    layout::read_length(offset, self.chunk_size, self.stream_size),
+                )
+            }
+        };
+        match self.range_end {
+            Some(end) if offset >= end => None,
+            _ => Some((offset, length)),
+        }
+    }
+
+    /// Whether a chunk holds allocated blocks, chunks that don't are skipped
+    pub(crate) fn is_allocated(&self, offset: u64, length: u64) -> bool {
+        match &self.allocation {
+            Some(allocation) => allocation.is_allocated(offset, length),
+            None => true,
+        }
+    }
+
+    /// Whether a chunk lies entirely within a hole of a sparse file
+    pub(crate) fn is_hole(&self, offset: u64, length: u64) -> bool {
+        let regions = match &self.data_regions {
+            Some(regions) => regions,
+            None => return false,
+        };
+        let next = regions.partition_point(|region| region.end <= offset);
+        regions.get(next).map_or(true, |region| {
+            layout::chunk_end(offset, length).map_or(false, |end| region.start >= end)
+        })
+    }
+
+    /// Size a short last chunk is hashed zero-padded to, zero if it isn't
+    pub(crate) fn padded_size(&self) -> u64 {
+        if self.pad_last_chunk {
+            self.chunk_size
+        } else {
+            0
+        }
+    }
+
+    /// Whether every chunk starts where the previous one ended
+    #[cfg(feature = "paranoid")]
+    pub(crate) fn is_contiguous(&self) -> bool {
+        self.allocation.is_none() && self.stride == self.chunk_size
+    }
+}
+
+/// How the data of a chunk is turned into its hash
+#[derive(Default)]
+pub(crate) struct HashConfig<'a> {
+    /// Whether to compute a similarity digest for every chunk
+    pub(crate) similarity: bool,
+    /// Hasher carrying state, used instead of `H` when set
+    pub(crate) keyed_hasher: Option<Box<dyn KeyedHasher + 'a>>,
+    /// Algorithm chosen at runtime, used instead of `H` when set and no
+    /// keyed hasher is
+    pub(crate) algorithm: Option<DynHasher>,
+    /// Salt to bind every chunk hash to, along with the chunk position
+    pub(crate) domain_salt: Option<Vec<u8>>,
+    /// Amount of leading digest bytes to keep
+    pub(crate) truncation: Option<usize>,
+}
+
+impl HashConfig<'_> {
+    /// Whether chunk data can be fed to `H` in increments, similarity
+    /// digests and keyed hashers need the whole chunk at once
+    pub(crate) fn is_incremental(&self) -> bool {
+        !self.similarity && self.keyed_hasher.is_none()
+    }
+
+    /// Whether nothing but the chunk data goes into the hash
+    pub(crate) fn is_positionless(&self) -> bool {
+        self.domain_salt.is_none()
+    }
+
+    /// Bytes hashed before the data of a chunk
+    /// # Arguments
+    /// * `index` - index of the chunk
+    /// * `offset` - offset of the chunk
+    pub(crate) fn prefix(&self, index: u64, offset: u64) -> Vec<u8> {
+        match &self.domain_salt {
+            Some(salt) => domain::prefix(salt, index, offset),
+            None => Vec::new(),
+        }
+    }
+
+    /// Hash a chunk read into memory, `buf` holding the prefix followed by
+    /// the chunk data and its zero padding
+    pub(crate) fn hash<H: Hasher>(
+        &self,
+        buf: &[u8],
+        prefix_length: usize,
+        data_length: usize,
+    ) -> Hashed<H::Output> {
+        let hash_start = Instant::now();
+        let hash = match &self.keyed_hasher {
+            Some(hasher) => ChunkHash::Keyed(hasher.hash_keyed(buf)),
+            None => match self.algorithm {
+                Some(DynHasher(algorithm)) => ChunkHash::Keyed(algorithm.hash_bytes(buf)),
+                None => ChunkHash::Static(H::hash(buf)),
+            },
+        };
+        let hash_time = hash_start.elapsed();
+        Hashed {
+            size: data_length as u64,
+            hash,
+            similarity: if self.similarity {
+                Some(similarity::simhash(
+                    &buf[prefix_length..prefix_length + data_length],
+                ))
+            } else {
+                None
+            },
+            read_time: Duration::default(),
+            hash_time,
+        }
+    }
+
+    /// Hash of a chunk as produced, truncated if requested
+    pub(crate) fn finish<O: AsRef<[u8]>>(&self, hash: ChunkHash<O>) -> Vec<u8> {
+        let mut hash = match hash {
+            ChunkHash::Static(hash) => hash.as_ref().to_vec(),
+            ChunkHash::Keyed(hash) => hash,
+        };
+        if let Some(length) = self.truncation {
+            hash.truncate(length);
+        }
+        hash
+    }
+}
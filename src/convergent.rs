@@ -0,0 +1,82 @@
+//! Convergent encryption key derivation
+//!
+//! Convergent encryption derives the key of every chunk from its own
+//! content, `key = H(salt || chunk hash)`, so identical plaintext chunks
+//! encrypt to identical ciphertext and can still be deduplicated by a store
+//! that never sees the plaintext.
+//!
+//! # Trade-offs
+//!
+//! * Anyone who can guess a chunk's plaintext can derive its key and confirm
+//!   that the chunk is stored ("confirmation of file" attack). Low entropy
+//!   content such as templated documents is therefore not protected.
+//! * Equal chunks are visible as equal ciphertext, leaking which chunks are
+//!   shared between files.
+//! * The salt bounds both of the above: only parties sharing a salt
+//!   deduplicate against each other. Use a per tenant secret salt unless
+//!   global deduplication is explicitly wanted.
+//! * The chunk hash doubles as key material, so it must come from a
+//!   cryptographic hasher and the key must never be stored next to it in
+//!   the clear.
+
+use crate::{hashers::Hasher, Chunk};
+
+/// Derive the encryption key of a chunk
+/// # Arguments
+/// * `salt` - dedup domain secret, see the module documentation
+/// * `chunk` - the plaintext chunk
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{convergent::derive_key, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let keys: Vec<Vec<u8>> = chunks
+///     .iter()
+///     .map(|chunk| derive_key::<Sha256Hasher>(b"tenant secret", chunk))
+///     .collect();
+/// assert_eq!(keys[0].len(), 32);
+/// # Ok(())
+/// # }
+/// ```
+pub fn derive_key<H: Hasher>(salt: &[u8], chunk: &Chunk) -> Vec<u8> {
+    derive_key_from_hash::<H>(salt, &chunk.hash)
+}
+
+/// Derive the encryption key of a chunk from its plaintext hash
+/// # Arguments
+/// * `salt` - dedup domain secret, see the module documentation
+/// * `chunk_hash` - hash of the plaintext chunk
+pub fn derive_key_from_hash<H: Hasher>(salt: &[u8], chunk_hash: &[u8]) -> Vec<u8> {
+    // Length prefix the salt so salt and hash boundaries can't be shifted
+    let mut input = Vec::with_capacity(8 + salt.len() + chunk_hash.len());
+    input.extend_from_slice(&(salt.len() as u64).to_le_bytes());
+    input.extend_from_slice(salt);
+    input.extend_from_slice(chunk_hash);
+    H::hash_bytes(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::Sha256Hasher;
+
+    #[test]
+    fn keys_converge_per_salt() {
+        let hash = Sha256Hasher::hash_bytes(b"brainstorm");
+        let key = derive_key_from_hash::<Sha256Hasher>(b"salt", &hash);
+        assert_eq!(key, derive_key_from_hash::<Sha256Hasher>(b"salt", &hash));
+        assert_ne!(key, derive_key_from_hash::<Sha256Hasher>(b"other", &hash));
+        assert_ne!(
+            derive_key_from_hash::<Sha256Hasher>(b"ab", b"c"),
+            derive_key_from_hash::<Sha256Hasher>(b"a", b"bc")
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Chunk samples to train a compression dictionary on
+//!
+//! Compressing every chunk on its own loses the redundancy between chunks,
+//! which a dictionary shared by all of them, such as a zstd dictionary,
+//! recovers. [`ChunkedHasher::with_dictionary_samples`](../struct.ChunkedHasher.html#method.with_dictionary_samples)
+//! collects the samples to train it on while hashing, so the data isn't read
+//! a second time. Training is left to the compression library, with the
+//! `zstd` crate for example
+//! `zstd::dict::from_continuous(samples.data(), &samples.sizes(), size)`.
+//!
+//! Samples are the leading bytes of evenly spread chunks. When the sample
+//! budget runs out, every other sample is dropped and only every other chunk
+//! is sampled from then on, so the samples cover the whole stream without
+//! knowing its length up front.
+
+use anyhow::{ensure, Result};
+
+/// Samples of chunk contents within a byte budget
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DictionarySamples {
+    /// Maximum amount of sampled bytes
+    budget: usize,
+    /// Maximum size of a single sample
+    sample_size: usize,
+    /// Amount of chunks between two sampled chunks
+    interval: u64,
+    /// Amount of chunks offered so far
+    offered: u64,
+    /// Concatenated samples
+    data: Vec<u8>,
+    /// Chunk index and size of every sample
+    samples: Vec<(u64, usize)>,
+}
+
+impl DictionarySamples {
+    /// Instantiate an empty sample set
+    /// # Arguments
+    /// * `budget` - maximum amount of sampled bytes, zstd recommends about
+    ///   100 times the dictionary size
+    /// * `sample_size` - maximum amount of leading bytes sampled per chunk
+    pub fn new(budget: usize, sample_size: usize) -> Result<Self> {
+        ensure!(sample_size > 0, "Sample size must be greater than zero");
+        ensure!(
+            budget >= sample_size,
+            "Sample budget must hold at least one sample"
+        );
+        Ok(Self {
+            budget,
+            sample_size,
+            interval: 1,
+            ..Self::default()
+        })
+    }
+
+    /// Concatenated samples
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Size of every sample in [`data`](#method.data), in order
+    pub fn sizes(&self) -> Vec<usize> {
+        self.samples.iter().map(|(_, size)| *size).collect()
+    }
+
+    /// Index of every sampled chunk, in order
+    pub fn chunk_indexes(&self) -> Vec<u64> {
+        self.samples.iter().map(|(index, _)| *index).collect()
+    }
+
+    /// Offer the data of the next chunk, sampling it if it's due
+    /// # Arguments
+    /// * `chunk` - data of the chunk
+    pub(crate) fn offer(&mut self, chunk: &[u8]) {
+        let index = self.offered;
+        self.offered += 1;
+        let sample = &chunk[..chunk.len().min(self.sample_size)];
+        if sample.is_empty() {
+            return;
+        }
+        while index % self.interval == 0 && self.data.len() + sample.len() > self.budget {
+            self.thin();
+        }
+        if index % self.interval == 0 {
+            self.data.extend_from_slice(sample);
+            self.samples.push((index, sample.len()));
+        }
+    }
+
+    /// Halve the sampling rate, dropping the samples that are no longer due
+    fn thin(&mut self) {
+        self.interval *= 2;
+        let sampled = std::mem::take(&mut self.data);
+        let mut start = 0;
+        let interval = self.interval;
+        let data = &mut self.data;
+        self.samples.retain(|(index, size)| {
+            let end = start + size;
+            let keep = index % interval == 0;
+            if keep {
+                data.extend_from_slice(&sampled[start..end]);
+            }
+            start = end;
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_stay_within_budget_and_spread() -> Result<()> {
+        let mut samples = DictionarySamples::new(100, 10)?;
+        for index in 0..100u8 {
+            samples.offer(&[index; 25]);
+        }
+        assert!(samples.data().len() <= 100);
+        assert_eq!(samples.chunk_indexes(), vec![0, 16, 32, 48, 64, 80, 96]);
+        assert!(samples.sizes().iter().all(|size| *size == 10));
+        assert_eq!(&samples.data()[10..20], &[16; 10]);
+        Ok(())
+    }
+
+    #[test]
+    fn short_chunks_are_sampled_whole() -> Result<()> {
+        let mut samples = DictionarySamples::new(100, 10)?;
+        samples.offer(b"abc");
+        samples.offer(b"");
+        samples.offer(b"defghijklmnop");
+        assert_eq!(samples.data(), b"abcdefghijklm");
+        assert_eq!(samples.sizes(), vec![3, 10]);
+        assert_eq!(samples.chunk_indexes(), vec![0, 2]);
+        assert!(DictionarySamples::new(5, 10).is_err());
+        assert!(DictionarySamples::new(5, 0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Per chunk domain separation
+//!
+//! With [`ChunkedHasher::with_domain_separation`](../struct.ChunkedHasher.html#method.with_domain_separation)
+//! every chunk hash is bound to a caller supplied salt and to the position of
+//! the chunk, so chunks with equal content can't be swapped between
+//! positions, or between streams hashed with different salts, without
+//! changing their hashes.
+//!
+//! Version 1 of the scheme hashes `prefix || chunk data`, with the prefix
+//! made up of, in order:
+//! * the version byte `0x01`
+//! * the salt length as big endian `u32`
+//! * the salt
+//! * the chunk index as big endian `u64`
+//! * the chunk offset as big endian `u64`
+
+use crate::hashers::Hasher;
+
+/// Version of the domain separation scheme, the first byte of every prefix
+pub const VERSION: u8 = 1;
+
+/// Prefix hashed before the data of a chunk
+/// # Arguments
+/// * `salt` - caller supplied salt
+/// * `index` - index of the chunk
+/// * `offset` - byte offset of the chunk
+pub fn prefix(salt: &[u8], index: u64, offset: u64) -> Vec<u8> {
+    let mut prefix = Vec::with_capacity(21 + salt.len());
+    prefix.push(VERSION);
+    prefix.extend_from_slice(&(salt.len() as u32).to_be_bytes());
+    prefix.extend_from_slice(salt);
+    prefix.extend_from_slice(&index.to_be_bytes());
+    prefix.extend_from_slice(&offset.to_be_bytes());
+    prefix
+}
+
+/// Domain separated hash of a chunk, as produced by a chunked hasher with
+/// domain separation enabled
+/// # Arguments
+/// * `salt` - caller supplied salt
+/// * `index` - index of the chunk
+/// * `offset` - byte offset of the chunk
+/// * `data` - chunk data
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{domain::hash_chunk, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .with_domain_separation(b"volume-7")
+///         .collect();
+/// assert_eq!(chunks[1].hash, hash_chunk::<Sha256Hasher>(b"volume-7", 1, 10, b"remunerate"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn hash_chunk<H: Hasher>(salt: &[u8], index: u64, offset: u64, data: &[u8]) -> Vec<u8> {
+    let mut input = prefix(salt, index, offset);
+    input.extend_from_slice(data);
+    H::hash_bytes(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_layout() {
+        assert_eq!(
+            prefix(b"ab", 2, 0x0102),
+            vec![1, 0, 0, 0, 2, b'a', b'b', 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 1, 2]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Verified download of a stream described by a manifest
+//!
+//! [`verified_download`](fn.verified_download.html) packages range fetching,
+//! per chunk verification, retries of bad chunks and a final check of the
+//! written data into a single call. Fetching itself is left to the caller, so
+//! any transport supporting byte ranges can be used.
+
+use crate::{
+    hashers::Hasher,
+    layout,
+    ranges::{coalesce, ByteRange},
+    Chunk, ChunkRef, ReadAndSeek,
+};
+use anyhow::{anyhow, bail, ensure, Result};
+use std::{
+    fs::File,
+    io::{self, Cursor, Read, SeekFrom, Write},
+};
+
+/// Amount of times chunks failing verification are fetched again
+pub const RETRIES: usize = 3;
+
+/// Amount of chunk bytes fetched at once, chunks are requested in batches
+/// of at most this size, unless a single chunk is larger
+pub const BATCH_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Output whose length can be set, so data past the end of the stream is cut
+/// off
+pub trait SetLen {
+    /// Truncate or extend the output to the given length
+    /// # Arguments
+    /// * `length` - new length of the output
+    fn set_len(&mut self, length: u64) -> io::Result<()>;
+}
+
+impl SetLen for File {
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        File::set_len(self, length)
+    }
+}
+
+impl SetLen for Cursor<Vec<u8>> {
+    fn set_len(&mut self, length: u64) -> io::Result<()> {
+        self.get_mut().resize(length as usize, 0);
+        Ok(())
+    }
+}
+
+/// Download a stream, verifying every chunk against the manifest
+///
+/// Chunks are fetched in offset order, in batches of up to
+/// [`BATCH_SIZE`](constant.BATCH_SIZE.html) bytes, every batch being written
+/// out before the next one is fetched. Chunks that fail verification are
+/// fetched again, up to [`RETRIES`](constant.RETRIES.html) times. Once every
+/// chunk was written, the output is cut off at the end of the stream, read
+/// back and every chunk checked against the manifest again. The last chunk
+/// may have been hashed zero-padded.
+/// # Arguments
+/// * `fetch_ranges` - fetches sorted byte ranges of the stream, returning the
+///   bytes of every range in order
+/// * `manifest` - chunks of the stream, hashed with `H`
+/// * `output` - where the stream is written to, at the offsets of the chunks
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{download::verified_download, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let manifest: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let mut output = Cursor::new(Vec::new());
+/// verified_download::<Sha256Hasher, _, _>(
+///     |ranges| {
+///         Ok(ranges
+///             .iter()
+///             .map(|range| WORDSTRING.as_bytes()[range.start as usize..range.end as usize].to_vec())
+///             .collect())
+///     },
+///     &manifest,
+///     &mut output,
+/// )?;
+/// assert_eq!(output.into_inner(), WORDSTRING.as_bytes());
+/// # Ok(())
+/// # }
+/// ```
+pub fn verified_download<H, F, W>(fetch_ranges: F, manifest: &[Chunk], output: &mut W) -> Result<()>
+where
+    H: Hasher,
+    F: FnMut(&[ByteRange]) -> Result<Vec<Vec<u8>>>,
+    W: ReadAndSeek + Write + SetLen,
+{
+    download_in_batches::<H, F, W>(fetch_ranges, manifest, output, BATCH_SIZE)
+}
+
+fn download_in_batches<H, F, W>(
+    mut fetch_ranges: F,
+    manifest: &[Chunk],
+    output: &mut W,
+    batch_size: u64,
+) -> Result<()>
+where
+    H: Hasher,
+    F: FnMut(&[ByteRange]) -> Result<Vec<Vec<u8>>>,
+    W: ReadAndSeek + Write + SetLen,
+{
+    let mut stream_size = 0;
+    for chunk in manifest {
+        let end = layout::chunk_end(chunk.offset, chunk.size)
+            .ok_or_else(|| anyhow!("Chunk {} ends past the largest offset", chunk.index))?;
+        stream_size = stream_size.max(end);
+    }
+    let mut pending: Vec<&Chunk> = manifest.iter().filter(|chunk| chunk.size > 0).collect();
+    pending.sort_by_key(|chunk| chunk.offset);
+    for _ in 0..=RETRIES {
+        if pending.is_empty() {
+            break;
+        }
+        let mut failed = Vec::new();
+        for batch in batches(&pending, batch_size) {
+            failed.extend(fetch_batch::<H, _, _>(
+                &mut fetch_ranges,
+                batch,
+                manifest,
+                output,
+            )?);
+        }
+        pending = failed;
+    }
+    if !pending.is_empty() {
+        bail!(
+            "{} chunks failed verification after {} retries",
+            pending.len(),
+            RETRIES
+        );
+    }
+    output.set_len(stream_size)?;
+    output.flush()?;
+    for chunk in manifest {
+        output.seek(SeekFrom::Start(chunk.offset))?;
+        let mut written = Vec::new();
+        (&mut *output).take(chunk.size).read_to_end(&mut written)?;
+        ensure!(
+            chunk.matches_data::<H>(&written, chunk.padding_in(manifest)),
+            "Written chunk {} doesn't match the manifest",
+            chunk.index
+        );
+    }
+    Ok(())
+}
+
+/// Split chunks sorted by offset into consecutive batches of up to
+/// `batch_size` bytes, a larger chunk being a batch of its own
+fn batches<'s, 'm>(chunks: &'s [&'m Chunk], batch_size: u64) -> Vec<&'s [&'m Chunk]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut size = 0u64;
+    for (index, chunk) in chunks.iter().enumerate() {
+        if index > start && size.saturating_add(chunk.size) > batch_size {
+            batches.push(&chunks[start..index]);
+            start = index;
+            size = 0;
+        }
+        size = size.saturating_add(chunk.size);
+    }
+    if start < chunks.len() {
+        batches.push(&chunks[start..]);
+    }
+    batches
+}
+
+/// Fetch a batch of chunks sorted by offset and write the ones passing
+/// verification, returning the ones that don't
+fn fetch_batch<'c, H, F, W>(
+    fetch_ranges: &mut F,
+    batch: &[&'c Chunk],
+    manifest: &[Chunk],
+    output: &mut W,
+) -> Result<Vec<&'c Chunk>>
+where
+    H: Hasher,
+    F: FnMut(&[ByteRange]) -> Result<Vec<Vec<u8>>>,
+    W: ReadAndSeek + Write,
+{
+    let ranges = coalesce(batch.iter().map(|chunk| ChunkRef::from(*chunk)));
+    let fetched = fetch_ranges(&ranges)?;
+    ensure!(
+        fetched.len() == ranges.len(),
+        "Fetched {} ranges, {} were requested",
+        fetched.len(),
+        ranges.len()
+    );
+    // Both the chunks and the ranges are sorted, so the range holding a chunk
+    // is found by walking the ranges alongside the chunks
+    let mut covering = ranges.iter().zip(fetched.iter()).peekable();
+    let mut failed = Vec::new();
+    for chunk in batch {
+        while let Some((range, _)) = covering.peek() {
+            if range.end > chunk.offset {
+                break;
+            }
+            covering.next();
+        }
+        let (range, data) = covering
+            .peek()
+            .filter(|(range, _)| {
+                range.start <= chunk.offset && ChunkRef::from(*chunk).end() <= range.end
+            })
+            .ok_or_else(|| anyhow!("Chunk {} isn't covered by a range", chunk.index))?;
+        let start = (chunk.offset - range.start) as usize;
+        let bytes = data.get(start..start + chunk.size as usize);
+        match bytes {
+            Some(bytes) if chunk.matches_data::<H>(bytes, chunk.padding_in(manifest)) => {
+                output.seek(SeekFrom::Start(chunk.offset))?;
+                output.write_all(bytes)?;
+            }
+            _ => failed.push(*chunk),
+        }
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    fn manifest() -> Result<Vec<Chunk>> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 10)?
+                .collect();
+        Ok(chunks)
+    }
+
+    fn fetch(ranges: &[ByteRange]) -> Vec<Vec<u8>> {
+        ranges
+            .iter()
+            .map(|range| DATA[range.start as usize..range.end as usize].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn bad_chunks_are_fetched_again() -> Result<()> {
+        let manifest = manifest()?;
+        let mut requests = Vec::new();
+        let mut output = Cursor::new(Vec::new());
+        verified_download::<Sha256Hasher, _, _>(
+            |ranges| {
+                requests.push(ranges.to_vec());
+                let mut fetched = fetch(ranges);
+                // Corrupt the second chunk on the first attempt
+                if requests.len() == 1 {
+                    fetched[0][15] ^= 0xff;
+                }
+                Ok(fetched)
+            },
+            &manifest,
+            &mut output,
+        )?;
+        assert_eq!(output.into_inner(), DATA);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1], vec![ByteRange { start: 10, end: 20 }]);
+        Ok(())
+    }
+
+    #[test]
+    fn chunks_are_fetched_in_bounded_batches() -> Result<()> {
+        let mut manifest = manifest()?;
+        manifest.reverse();
+        let mut requests = Vec::new();
+        let mut output = Cursor::new(Vec::new());
+        download_in_batches::<Sha256Hasher, _, _>(
+            |ranges| {
+                requests.push(ranges.to_vec());
+                Ok(fetch(ranges))
+            },
+            &manifest,
+            &mut output,
+            25,
+        )?;
+        assert_eq!(output.into_inner(), DATA);
+        assert_eq!(
+            requests,
+            vec![
+                vec![ByteRange { start: 0, end: 20 }],
+                vec![ByteRange { start: 20, end: 40 }],
+                vec![ByteRange { start: 40, end: 50 }]
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn padded_last_chunks_are_downloaded() -> Result<()> {
+        let data = &DATA[..45];
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, data.len() as u64, 10)?
+                .collect();
+        let mut output = Cursor::new(Vec::new());
+        verified_download::<Sha256Hasher, _, _>(
+            |ranges| Ok(fetch(ranges)),
+            &manifest,
+            &mut output,
+        )?;
+        assert_eq!(output.into_inner(), data);
+        Ok(())
+    }
+
+    #[test]
+    fn output_is_cut_off_at_the_end_of_the_stream() -> Result<()> {
+        let manifest = manifest()?;
+        let mut output = Cursor::new(vec![b'x'; 80]);
+        verified_download::<Sha256Hasher, _, _>(
+            |ranges| Ok(fetch(ranges)),
+            &manifest,
+            &mut output,
+        )?;
+        assert_eq!(output.into_inner(), DATA);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_manifests_fail() -> Result<()> {
+        let mut manifest = manifest()?;
+        manifest[4].offset = u64::MAX;
+        let mut output = Cursor::new(Vec::new());
+        let result = verified_download::<Sha256Hasher, _, _>(
+            |ranges| Ok(fetch(ranges)),
+            &manifest,
+            &mut output,
+        );
+        assert!(result.is_err());
+        // Ranges not covering the requested chunks
+        let manifest = self::manifest()?;
+        let result = verified_download::<Sha256Hasher, _, _>(
+            |ranges| Ok(vec![Vec::new(); ranges.len()]),
+            &manifest,
+            &mut output,
+        );
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn persistent_corruption_fails() -> Result<()> {
+        let manifest = manifest()?;
+        let mut output = Cursor::new(Vec::new());
+        let result = verified_download::<Sha256Hasher, _, _>(
+            |ranges| {
+                let mut fetched = fetch(ranges);
+                fetched[0][0] ^= 0xff;
+                Ok(fetched)
+            },
+            &manifest,
+            &mut output,
+        );
+        assert!(result.is_err());
+        Ok(())
+    }
+}
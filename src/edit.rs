@@ -0,0 +1,348 @@
+//! Incremental manifest maintenance under byte level edits
+//!
+//! Given the fixed size chunks of a stream and a script of edits applied to
+//! it, [`remap`](fn.remap.html) predicts the chunk layout of the edited
+//! stream. Chunks whose bytes are untouched, and still line up with an old
+//! chunk, keep their hash; only the remaining chunks have to be re-hashed.
+
+use crate::{layout, Chunk, ChunkRef};
+use anyhow::{ensure, Result};
+use std::collections::HashMap;
+
+/// Byte level edit of a stream, offsets refer to the stream as it is after
+/// all previous edits of the script were applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// `length` new bytes inserted at `offset`
+    Insert { offset: u64, length: u64 },
+    /// `length` bytes removed at `offset`
+    Delete { offset: u64, length: u64 },
+    /// `length` bytes replaced in place at `offset`
+    Overwrite { offset: u64, length: u64 },
+}
+
+/// Chunk of the edited stream
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemappedChunk {
+    /// Chunk content is unchanged, its hash was carried over from the old
+    /// chunk with the same content
+    Unchanged(Chunk),
+    /// Chunk content changed and has to be re-hashed
+    Rehash(ChunkRef),
+}
+
+/// Predicted layout of an edited stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct Remap {
+    /// Size of the edited stream
+    pub stream_size: u64,
+    /// Chunks of the edited stream, in order
+    pub chunks: Vec<RemappedChunk>,
+}
+
+impl Remap {
+    /// Chunks of the edited stream that have to be re-hashed
+    pub fn rehash(&self) -> Vec<ChunkRef> {
+        self.chunks
+            .iter()
+            .filter_map(|chunk| match chunk {
+                RemappedChunk::Rehash(chunk) => Some(*chunk),
+                RemappedChunk::Unchanged(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Contiguous run of the edited stream, either copied from the old stream
+/// or written by an edit
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    length: u64,
+    /// Offset of the run in the old stream, `None` for edited bytes
+    source: Option<u64>,
+}
+
+/// Predict the chunk layout of a stream after applying an edit script
+/// # Arguments
+/// * `manifest` - fixed size chunks of the stream before editing
+/// * `stream_size` - size of the stream before editing
+/// * `chunk_size` - fixed chunk size the manifest was produced with
+/// * `edits` - edits to apply, in order
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{edit::{remap, Edit}, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let manifest: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let remapped = remap(&manifest, 40, 10, &[Edit::Overwrite { offset: 12, length: 3 }])?;
+/// assert_eq!(remapped.rehash().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn remap(
+    manifest: &[Chunk],
+    stream_size: u64,
+    chunk_size: u64,
+    edits: &[Edit],
+) -> Result<Remap> {
+    ensure!(chunk_size > 0, "Chunk size must be greater than zero");
+
+    let mut segments = vec![Segment {
+        length: stream_size,
+        source: Some(0),
+    }];
+    let mut new_size = stream_size;
+    for edit in edits {
+        match *edit {
+            Edit::Insert { offset, length } => {
+                ensure!(offset <= new_size, "Insert at {} is out of bounds", offset);
+                let at = split_at(&mut segments, offset);
+                segments.insert(
+                    at,
+                    Segment {
+                        length,
+                        source: None,
+                    },
+                );
+                new_size += length;
+            }
+            Edit::Delete { offset, length } | Edit::Overwrite { offset, length } => {
+                ensure!(
+                    matches!(offset.checked_add(length), Some(end) if end <= new_size),
+                    "Edit of {} bytes at {} is out of bounds",
+                    length,
+                    offset
+                );
+                let start = split_at(&mut segments, offset);
+                let end = split_at(&mut segments, offset + length);
+                segments.drain(start..end);
+                if let Edit::Overwrite { .. } = edit {
+                    segments.insert(
+                        start,
+                        Segment {
+                            length,
+                            source: None,
+                        },
+                    );
+                } else {
+                    new_size -= length;
+                }
+            }
+        }
+    }
+
+    let old_chunks: HashMap<(u64, u64), &Chunk> = manifest
+        .iter()
+        .map(|chunk| ((chunk.offset, chunk.size), chunk))
+        .collect();
+    let old_chunk_size = layout::clamp_chunk_size(chunk_size, stream_size);
+    let chunk_size = layout::clamp_chunk_size(chunk_size, new_size);
+    let count = layout::chunk_count(new_size, chunk_size, chunk_size);
+    let mut chunks = Vec::with_capacity(count as usize);
+    let mut segment = 0;
+    let mut segment_start = 0;
+    for index in 0..count {
+        let offset = index * chunk_size;
+        let size = layout::read_length(offset, chunk_size, new_size);
+        while segment_start + segments[segment].length <= offset {
+            segment_start += segments[segment].length;
+            segment += 1;
+        }
+        let current = segments[segment];
+        let old_chunk = match current.source {
+            Some(source) if offset + size <= segment_start + current.length => {
+                let old_offset = source + (offset - segment_start);
+                // A short last chunk keeps its hash only if it is zero-padded
+                // alike
+                old_chunks.get(&(old_offset, size)).filter(|_| {
+                    layout::padding(old_offset, size, old_chunk_size)
+                        == layout::padding(offset, size, chunk_size)
+                })
+            }
+            _ => None,
+        };
+        let chunk_ref = ChunkRef {
+            index,
+            offset,
+            size,
+        };
+        chunks.push(match old_chunk {
+            Some(old_chunk) => RemappedChunk::Unchanged(Chunk {
+                index,
+                offset,
+                size,
+                hash: old_chunk.hash.clone(),
+                similarity: old_chunk.similarity,
+            }),
+            None => RemappedChunk::Rehash(chunk_ref),
+        });
+    }
+
+    Ok(Remap {
+        stream_size: new_size,
+        chunks,
+    })
+}
+
+/// Make sure a segment starts at `offset`, returning the index of that
+/// segment (or the segment count if `offset` is the end of the stream)
+fn split_at(segments: &mut Vec<Segment>, offset: u64) -> usize {
+    let mut start = 0;
+    for index in 0..segments.len() {
+        let segment = segments[index];
+        if offset == start {
+            return index;
+        }
+        if offset < start + segment.length {
+            let head = offset - start;
+            segments[index].length = head;
+            segments.insert(
+                index + 1,
+                Segment {
+                    length: segment.length - head,
+                    source: segment.source.map(|source| source + head),
+                },
+            );
+            return index + 1;
+        }
+        start += segment.length;
+    }
+    segments.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    fn chunks(data: &[u8]) -> Result<Vec<Chunk>> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let chunks =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, data.len() as u64, 10)?
+                .collect();
+        Ok(chunks)
+    }
+
+    fn rehashed_indexes(remap: &Remap) -> Vec<u64> {
+        remap.rehash().iter().map(|chunk| chunk.index).collect()
+    }
+
+    #[test]
+    fn overwrite_only_touches_affected_chunks() -> Result<()> {
+        let manifest = chunks(DATA)?;
+        let remapped = remap(
+            &manifest,
+            DATA.len() as u64,
+            10,
+            &[Edit::Overwrite {
+                offset: 18,
+                length: 4,
+            }],
+        )?;
+        assert_eq!(remapped.stream_size, 50);
+        assert_eq!(rehashed_indexes(&remapped), vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn chunk_aligned_insert_and_delete_keep_hashes() -> Result<()> {
+        let manifest = chunks(DATA)?;
+        let mut edited = DATA.to_vec();
+        edited.splice(20..20, b"0123456789".iter().cloned());
+        edited.drain(40..50);
+        let remapped = remap(
+            &manifest,
+            DATA.len() as u64,
+            10,
+            &[
+                Edit::Insert {
+                    offset: 20,
+                    length: 10,
+                },
+                Edit::Delete {
+                    offset: 40,
+                    length: 10,
+                },
+            ],
+        )?;
+        assert_eq!(remapped.stream_size, edited.len() as u64);
+        assert_eq!(rehashed_indexes(&remapped), vec![2]);
+        // Carried over hashes match a fresh run over the edited data
+        let fresh = chunks(&edited)?;
+        for chunk in &remapped.chunks {
+            if let RemappedChunk::Unchanged(chunk) = chunk {
+                assert!(fresh.contains(chunk));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unaligned_insert_rehashes_the_tail() -> Result<()> {
+        let manifest = chunks(DATA)?;
+        let remapped = remap(
+            &manifest,
+            DATA.len() as u64,
+            10,
+            &[Edit::Insert {
+                offset: 25,
+                length: 1,
+            }],
+        )?;
+        assert_eq!(remapped.stream_size, 51);
+        assert_eq!(rehashed_indexes(&remapped), vec![2, 3, 4, 5]);
+        Ok(())
+    }
+
+    #[test]
+    fn padded_last_chunk_moved_to_the_start_is_rehashed() -> Result<()> {
+        let manifest = chunks(&DATA[..45])?;
+        let remapped = remap(
+            &manifest,
+            45,
+            10,
+            &[Edit::Delete {
+                offset: 0,
+                length: 40,
+            }],
+        )?;
+        assert_eq!(rehashed_indexes(&remapped), vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_bounds_edits_are_rejected() -> Result<()> {
+        let manifest = chunks(DATA)?;
+        assert!(remap(
+            &manifest,
+            50,
+            10,
+            &[Edit::Delete {
+                offset: 45,
+                length: 10
+            }]
+        )
+        .is_err());
+        assert!(remap(
+            &manifest,
+            50,
+            10,
+            &[Edit::Insert {
+                offset: 51,
+                length: 1
+            }]
+        )
+        .is_err());
+        Ok(())
+    }
+}
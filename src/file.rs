@@ -0,0 +1,205 @@
+//! Helpers for hashing files
+//!
+//! Chunked hashing seeks to every chunk, which only works on files that are
+//! actually seekable. FIFOs, sockets and character devices such as
+//! `/dev/stdin` accept seeks without complaint but don't honor them, which
+//! would silently produce a wrong chunk sequence, so they are hashed
+//! sequentially until EOF instead.
+//!
+//! Sparse files can be hashed without reading their holes, see
+//! [`data_regions`](fn.data_regions.html). Finding the holes calls `lseek`
+//! directly, so it is only built with the `seek-data` feature and only on
+//! Linux architectures whose `SEEK_DATA`, `SEEK_HOLE` and errno values were
+//! checked against the kernel headers.
+
+use crate::StreamSize;
+use anyhow::{bail, Result};
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom},
+    ops::Range,
+};
+
+/// Returns the stream size hint to hash a file with,
+/// [`StreamSize::Unknown`](../enum.StreamSize.html) for files that can't be
+/// seeked reliably, so they are read sequentially until EOF
+/// # Arguments
+/// * `file` - the file to hash, its position is reset to the start
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{file::stream_size, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/original.txt");
+/// let mut file = std::fs::File::open(path)?;
+/// let size = stream_size(&mut file)?;
+/// let chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut file, size, 40)?.collect();
+/// # Ok(())
+/// # }
+/// ```
+pub fn stream_size(file: &mut File) -> Result<StreamSize> {
+    let file_type = file.metadata()?.file_type();
+    if file_type.is_dir() {
+        bail!("Cannot hash a directory");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() || file_type.is_socket() || file_type.is_char_device() {
+            return Ok(StreamSize::Unknown);
+        }
+    }
+    // Block devices report a zero length in their metadata, seeking to the
+    // end gives the real size for them and regular files alike
+    let size = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(StreamSize::Known(size))
+}
+
+/// Returns the data regions of a sparse file, in order, the holes between
+/// them read as zeros
+///
+/// With the `seek-data` feature on Linux the regions are found with
+/// `SEEK_DATA` and `SEEK_HOLE`. Without the feature, on other targets or on
+/// file systems without hole support, the whole file is a single data region
+/// and holes are read like any other data.
+/// # Arguments
+/// * `file` - the file to inspect, its position is reset to the start, it
+///   must be seekable
+pub fn data_regions(file: &mut File) -> Result<Vec<Range<u64>>> {
+    let size = match stream_size(file)? {
+        StreamSize::Known(size) => size,
+        StreamSize::Unknown => bail!("Cannot find the data regions of a file that isn't seekable"),
+    };
+    let regions = seek_data_regions(file, size)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(regions)
+}
+
+#[cfg(all(feature = "seek-data", target_os = "linux"))]
+fn seek_data_regions(file: &File, size: u64) -> Result<Vec<Range<u64>>> {
+    use std::{convert::TryFrom, io, os::unix::io::AsRawFd};
+
+    let fd = file.as_raw_fd();
+    // `None` when there is no data, or no hole, at or after the offset
+    let seek = |offset: u64, whence| -> io::Result<Option<u64>> {
+        let offset = libc::off_t::try_from(offset)
+            .map_err(|_| io::Error::from_raw_os_error(libc::EOVERFLOW))?;
+        // Safety: lseek only operates on the descriptor, which the borrowed
+        // file keeps open
+        let result = unsafe { libc::lseek(fd, offset, whence) };
+        if result >= 0 {
+            return Ok(Some(result as u64));
+        }
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            Some(libc::ENXIO) => Ok(None),
+            _ => Err(error),
+        }
+    };
+    let mut regions = Vec::new();
+    let mut offset = 0;
+    while offset < size {
+        let start = match seek(offset, libc::SEEK_DATA) {
+            Ok(Some(start)) if start < size => start,
+            Ok(_) => break,
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) && offset == 0 => {
+                return Ok(std::iter::once(0..size).collect());
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let end = seek(start, libc::SEEK_HOLE)?.unwrap_or(size).min(size);
+        regions.push(start..end);
+        offset = end;
+    }
+    Ok(regions)
+}
+
+#[cfg(not(all(feature = "seek-data", target_os = "linux")))]
+fn seek_data_regions(_file: &File, size: u64) -> Result<Vec<Range<u64>>> {
+    Ok(std::iter::once(0..size)
+        .filter(|region| !region.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_file_size() -> Result<()> {
+        let mut file = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/test-data/original.txt"
+        ))?;
+        assert_eq!(stream_size(&mut file)?, StreamSize::Known(480));
+        Ok(())
+    }
+
+    #[test]
+    fn data_regions_cover_written_data() -> Result<()> {
+        use std::io::Write;
+        let path =
+            std::env::temp_dir().join(format!("chunked-hasher-sparse-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        file.set_len(4 * 1024 * 1024)?;
+        file.seek(SeekFrom::Start(2 * 1024 * 1024))?;
+        file.write_all(b"brainstorm")?;
+        let regions = data_regions(&mut file);
+        std::fs::remove_file(&path)?;
+        let regions = regions?;
+        assert!(regions
+            .iter()
+            .any(|region| region.contains(&(2 * 1024 * 1024))));
+        assert!(regions.windows(2).all(|pair| pair[0].end < pair[1].start));
+        assert!(regions.iter().all(|region| region.end <= 4 * 1024 * 1024));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn character_device_is_read_sequentially() -> Result<()> {
+        use crate::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+        let mut file = File::open("/dev/null")?;
+        assert_eq!(stream_size(&mut file)?, StreamSize::Unknown);
+        assert!(data_regions(&mut file).is_err());
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::sparse_file_chunks(&mut file, 10)?.collect();
+        assert!(chunks.is_empty());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifo_is_read_sequentially() -> Result<()> {
+        use crate::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+        use std::{io::Write, process::Command, thread};
+        let path = std::env::temp_dir().join(format!("chunked-hasher-fifo-{}", std::process::id()));
+        if !Command::new("mkfifo").arg(&path).status()?.success() {
+            return Ok(());
+        }
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || -> std::io::Result<()> {
+            File::create(writer_path)?.write_all(b"brainstormremuneratedisabilityexperiment")
+        });
+        let mut fifo = File::open(&path)?;
+        let size = stream_size(&mut fifo)?;
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut fifo, size, 16)?.collect();
+        writer.join().unwrap()?;
+        std::fs::remove_file(&path)?;
+        assert_eq!(size, StreamSize::Unknown);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.size).collect::<Vec<u64>>(),
+            vec![16, 16, 8]
+        );
+        Ok(())
+    }
+}
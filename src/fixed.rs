@@ -0,0 +1,180 @@
+//! Fixed size chunking with the chunk size known at compile time
+//!
+//! [`FixedChunkedHasher`](struct.FixedChunkedHasher.html) reads every chunk
+//! into a stack buffer of `N` bytes and produces chunks carrying the fixed size
+//! digest of the hasher, so hot paths hashing small chunks, such as 4 KiB
+//! pages, don't allocate at all.
+
+use crate::{hashers::Hasher, layout, read_full, Chunk, ReadAndSeek, StreamSize};
+use anyhow::{ensure, Result};
+use std::{io::SeekFrom, marker::PhantomData};
+
+/// Fixed size chunked hasher with a chunk size of `N` bytes
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{fixed::FixedChunkedHasher, hashers::xxhash::Xxh3Hasher, Chunk};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk<[u8; 8]>> =
+///     FixedChunkedHasher::<16, Xxh3Hasher>::new(&mut buffer, WORDSTRING.len() as u64)?.collect();
+/// assert_eq!(chunks.len(), 3);
+/// assert_eq!(chunks[2].size, 8);
+/// # Ok(())
+/// # }
+/// ```
+pub struct FixedChunkedHasher<'a, const N: usize, H> {
+    /// The buffer we'll iterate over when doing the chunked hashing
+    seekable_buffer: &'a mut dyn ReadAndSeek,
+    /// Read buffer holding the current chunk
+    chunk: [u8; N],
+    /// Next chunk index to process
+    next_chunk: u64,
+    /// Bytes left to read, `u64::MAX` if the stream size is unknown
+    remaining: u64,
+    /// Whether the end of the stream was reached
+    finished: bool,
+    /// Stream size, if known
+    #[cfg(feature = "paranoid")]
+    stream_size: Option<u64>,
+    /// Chunk accounting checked so far
+    #[cfg(feature = "paranoid")]
+    invariants: crate::paranoid::Invariants,
+    _marker: PhantomData<H>,
+}
+
+impl<'a, const N: usize, H: Hasher> FixedChunkedHasher<'a, N, H> {
+    /// Instantiate a fixed size chunked hasher, reading the stream
+    /// sequentially from its start
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size hint of the stream, or
+    ///   [`StreamSize::Unknown`](../enum.StreamSize.html) to read until EOF
+    pub fn new(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+    ) -> Result<Self> {
+        ensure!(N > 0, "Chunk size must be greater than zero");
+
+        let remaining = match stream_size.into() {
+            StreamSize::Known(size) => {
+                buffer.seek(SeekFrom::Start(0))?;
+                size
+            }
+            StreamSize::Unknown => u64::MAX,
+        };
+        Ok(Self {
+            seekable_buffer: buffer,
+            chunk: [0; N],
+            next_chunk: 0,
+            remaining,
+            finished: false,
+            #[cfg(feature = "paranoid")]
+            stream_size: Some(remaining).filter(|size| *size != u64::MAX),
+            #[cfg(feature = "paranoid")]
+            invariants: Default::default(),
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<const N: usize, H: Hasher> FixedChunkedHasher<'_, N, H> {
+    fn produce_next(&mut self) -> Option<Chunk<H::Output>> {
+        if self.finished || self.remaining == 0 {
+            return None;
+        }
+        let length = self.remaining.min(N as u64) as usize;
+        let read_bytes = read_full(self.seekable_buffer, &mut self.chunk[..length]).ok()?;
+        self.remaining -= read_bytes as u64;
+        if read_bytes < length {
+            #[cfg(feature = "paranoid")]
+            self.invariants.short_read();
+            self.finished = true;
+            if read_bytes == 0 {
+                return None;
+            }
+        }
+        let index = self.next_chunk;
+        self.next_chunk += 1;
+        let offset = index * N as u64;
+        // A short last chunk is hashed zero-padded, as by ChunkedHasher
+        let padding = layout::padding(offset, read_bytes as u64, N as u64) as usize;
+        self.chunk[read_bytes..read_bytes + padding]
+            .iter_mut()
+            .for_each(|byte| *byte = 0);
+        Some(Chunk {
+            index,
+            offset,
+            size: read_bytes as u64,
+            hash: H::hash(&self.chunk[..read_bytes + padding]),
+            similarity: None,
+        })
+    }
+}
+
+impl<const N: usize, H: Hasher> Iterator for FixedChunkedHasher<'_, N, H> {
+    type Item = Chunk<H::Output>;
+
+    fn next(&mut self) -> Option<Chunk<H::Output>> {
+        let chunk = self.produce_next();
+        #[cfg(feature = "paranoid")]
+        match &chunk {
+            Some(chunk) => self.invariants.chunk(chunk.into(), true),
+            None => {
+                let read_data = self.stream_size.unwrap_or(u64::MAX) - self.remaining;
+                self.invariants.complete(self.stream_size, read_data, true);
+            }
+        }
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hashers::{sha2::Sha256Hasher, Hasher},
+        ChunkRef, ChunkedHasher,
+    };
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    #[test]
+    fn matches_chunked_hasher() -> Result<()> {
+        for stream_size in [StreamSize::Known(DATA.len() as u64), StreamSize::Unknown] {
+            let mut buff_one: Cursor<&[u8]> = Cursor::new(DATA);
+            let mut buff_two: Cursor<&[u8]> = Cursor::new(DATA);
+            let expected: Vec<Chunk> =
+                ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buff_one, stream_size, 16)?
+                    .collect();
+            let fixed: Vec<_> =
+                FixedChunkedHasher::<16, Sha256Hasher>::new(&mut buff_two, stream_size)?.collect();
+            assert_eq!(expected.len(), 4);
+            assert_eq!(expected.len(), fixed.len());
+            for (expected, fixed) in expected.iter().zip(fixed.iter()) {
+                assert_eq!(ChunkRef::from(expected), ChunkRef::from(fixed));
+                assert_eq!(expected.hash, fixed.hash.to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn size_hint_limits_reading() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<_> = FixedChunkedHasher::<8, Sha256Hasher>::new(&mut buffer, 20)?.collect();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].offset, 16);
+        let mut padded = DATA[16..20].to_vec();
+        padded.resize(8, 0);
+        assert_eq!(chunks[2].hash, Sha256Hasher::hash(&padded));
+        assert!(FixedChunkedHasher::<0, Sha256Hasher>::new(&mut buffer, 20).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+//! Framing of chunks for datagram and stream transports
+//!
+//! A chunk and its payload are split into frames of at most a given size,
+//! so they fit QUIC datagrams or bounded stream writes. Every frame carries
+//! the chunk position, the first frame also carries the chunk hash, and
+//! oversized payloads continue in further frames. All integers are big
+//! endian:
+//!
+//! | field          | size               |
+//! |----------------|--------------------|
+//! | flags          | 1 (`FIRST`, `LAST`)|
+//! | chunk index    | 8                  |
+//! | chunk offset   | 8                  |
+//! | chunk size     | 8                  |
+//! | piece offset   | 8                  |
+//! | hash length    | 1                  |
+//! | hash           | hash length        |
+//! | payload length | 4                  |
+//! | payload        | payload length     |
+
+use crate::Chunk;
+use anyhow::{bail, ensure, Result};
+use std::convert::TryInto;
+
+/// Flag set on the first frame of a chunk
+pub const FLAG_FIRST: u8 = 0b01;
+/// Flag set on the last frame of a chunk
+pub const FLAG_LAST: u8 = 0b10;
+/// Size of a frame header without the hash
+pub const FRAME_HEADER_SIZE: usize = 38;
+/// Largest payload buffer reserved up front, the chunk size of a frame comes
+/// from the network and larger payloads grow the buffer as frames arrive
+const MAX_PREALLOCATION: u64 = 1024 * 1024;
+
+/// Decoded frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// `FLAG_FIRST` and/or `FLAG_LAST`
+    pub flags: u8,
+    /// Index of the chunk
+    pub index: u64,
+    /// Byte offset of the chunk in the stream
+    pub chunk_offset: u64,
+    /// Size of the chunk
+    pub chunk_size: u64,
+    /// Offset of this frame's payload inside the chunk
+    pub piece_offset: u64,
+    /// Chunk hash, only present in the first frame
+    pub hash: Vec<u8>,
+    /// Piece of the chunk payload
+    pub payload: Vec<u8>,
+}
+
+/// Split a chunk and its payload into frames
+/// # Arguments
+/// * `chunk` - the chunk to frame
+/// * `payload` - the chunk data, must be `chunk.size` bytes
+/// * `max_frame_size` - maximum size of an encoded frame, must leave room
+///   for the header, the hash and at least one payload byte
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{framing::{encode_chunk, ChunkAssembler, decode_frame}, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 20)?
+///         .collect();
+/// let frames = encode_chunk(&chunks[0], &WORDSTRING.as_bytes()[..20], 80)?;
+/// assert_eq!(frames.len(), 2);
+/// let mut assembler = ChunkAssembler::default();
+/// assert!(assembler.push(decode_frame(&frames[0])?)?.is_none());
+/// let (chunk, payload) = assembler.push(decode_frame(&frames[1])?)?.unwrap();
+/// assert!(chunk == chunks[0]);
+/// assert_eq!(payload, &WORDSTRING.as_bytes()[..20]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode_chunk(chunk: &Chunk, payload: &[u8], max_frame_size: usize) -> Result<Vec<Vec<u8>>> {
+    ensure!(
+        payload.len() as u64 == chunk.size,
+        "Payload is {} bytes but the chunk is {} bytes",
+        payload.len(),
+        chunk.size
+    );
+    ensure!(chunk.hash.len() <= 255, "Chunk hash is too long to frame");
+    ensure!(
+        max_frame_size > FRAME_HEADER_SIZE + chunk.hash.len(),
+        "Frame size {} leaves no room for payload",
+        max_frame_size
+    );
+    let mut frames = Vec::new();
+    let mut piece_offset = 0;
+    loop {
+        let first = piece_offset == 0;
+        let hash: &[u8] = if first { &chunk.hash } else { &[] };
+        let room = (max_frame_size - FRAME_HEADER_SIZE - hash.len()).min(u32::MAX as usize);
+        let piece = &payload[piece_offset..payload.len().min(piece_offset + room)];
+        let last = piece_offset + piece.len() == payload.len();
+        let mut flags = 0;
+        if first {
+            flags |= FLAG_FIRST;
+        }
+        if last {
+            flags |= FLAG_LAST;
+        }
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + hash.len() + piece.len());
+        frame.push(flags);
+        frame.extend_from_slice(&chunk.index.to_be_bytes());
+        frame.extend_from_slice(&chunk.offset.to_be_bytes());
+        frame.extend_from_slice(&chunk.size.to_be_bytes());
+        frame.extend_from_slice(&(piece_offset as u64).to_be_bytes());
+        frame.push(hash.len() as u8);
+        frame.extend_from_slice(hash);
+        frame.extend_from_slice(&(piece.len() as u32).to_be_bytes());
+        frame.extend_from_slice(piece);
+        frames.push(frame);
+        piece_offset += piece.len();
+        if last {
+            return Ok(frames);
+        }
+    }
+}
+
+/// Decode a single frame
+/// # Arguments
+/// * `bytes` - the encoded frame
+pub fn decode_frame(bytes: &[u8]) -> Result<Frame> {
+    let mut reader = FrameReader { bytes };
+    let flags = reader.take(1)?[0];
+    let index = reader.u64()?;
+    let chunk_offset = reader.u64()?;
+    let chunk_size = reader.u64()?;
+    let piece_offset = reader.u64()?;
+    let hash_length = reader.take(1)?[0] as usize;
+    let hash = reader.take(hash_length)?.to_vec();
+    let payload_length = u32::from_be_bytes(reader.take(4)?.try_into()?) as usize;
+    let payload = reader.take(payload_length)?.to_vec();
+    ensure!(reader.bytes.is_empty(), "Trailing data after frame");
+    Ok(Frame {
+        flags,
+        index,
+        chunk_offset,
+        chunk_size,
+        piece_offset,
+        hash,
+        payload,
+    })
+}
+
+struct FrameReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FrameReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= length, "Truncated frame");
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+/// Reassembles chunks from their frames, which must arrive in order
+#[derive(Default)]
+pub struct ChunkAssembler {
+    /// Chunk being assembled and the payload received so far
+    current: Option<(Chunk, Vec<u8>)>,
+}
+
+impl ChunkAssembler {
+    /// Add the next frame, returning the chunk and its payload once its last
+    /// frame arrived
+    /// # Arguments
+    /// * `frame` - the next decoded frame
+    pub fn push(&mut self, frame: Frame) -> Result<Option<(Chunk, Vec<u8>)>> {
+        if frame.flags & FLAG_FIRST != 0 {
+            ensure!(
+                self.current.is_none(),
+                "New chunk started before the previous one was complete"
+            );
+            ensure!(
+                frame.piece_offset == 0,
+                "First frame doesn't start the chunk"
+            );
+            self.current = Some((
+                Chunk {
+                    index: frame.index,
+                    offset: frame.chunk_offset,
+                    size: frame.chunk_size,
+                    hash: frame.hash,
+                    similarity: None,
+                },
+                Vec::with_capacity(frame.chunk_size.min(MAX_PREALLOCATION) as usize),
+            ));
+        }
+        let (chunk, payload) = match self.current.as_mut() {
+            Some(current) => current,
+            None => bail!("Continuation frame without a first frame"),
+        };
+        ensure!(
+            frame.index == chunk.index
+                && frame.chunk_offset == chunk.offset
+                && frame.chunk_size == chunk.size,
+            "Frame belongs to another chunk"
+        );
+        ensure!(
+            frame.piece_offset == payload.len() as u64,
+            "Frame is out of order"
+        );
+        payload.extend_from_slice(&frame.payload);
+        ensure!(
+            payload.len() as u64 <= chunk.size,
+            "Frames exceed the chunk size"
+        );
+        if frame.flags & FLAG_LAST == 0 {
+            return Ok(None);
+        }
+        ensure!(
+            payload.len() as u64 == chunk.size,
+            "Last frame arrived before the chunk was complete"
+        );
+        Ok(self.current.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(size: u64) -> Chunk {
+        Chunk {
+            index: 3,
+            offset: 60,
+            size,
+            hash: vec![0xab; 32],
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let payload: Vec<u8> = (0..=255).collect();
+        let frames = encode_chunk(&chunk(256), &payload, 100)?;
+        // 30 payload bytes in the first frame, 62 in the continuations
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|frame| frame.len() <= 100));
+        let mut assembler = ChunkAssembler::default();
+        let mut assembled = None;
+        for frame in &frames {
+            assembled = assembler.push(decode_frame(frame)?)?;
+        }
+        let (assembled_chunk, assembled_payload) = assembled.unwrap();
+        assert!(assembled_chunk == chunk(256));
+        assert_eq!(assembled_payload, payload);
+        Ok(())
+    }
+
+    #[test]
+    fn single_frame_and_empty_chunks() -> Result<()> {
+        let frames = encode_chunk(&chunk(4), b"data", 1500)?;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(decode_frame(&frames[0])?.flags, FLAG_FIRST | FLAG_LAST);
+        assert_eq!(encode_chunk(&chunk(0), b"", 100)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() -> Result<()> {
+        assert!(encode_chunk(&chunk(4), b"data", FRAME_HEADER_SIZE + 32).is_err());
+        assert!(encode_chunk(&chunk(5), b"data", 1500).is_err());
+        let frames = encode_chunk(&chunk(256), &[0u8; 256], 100)?;
+        assert!(decode_frame(&frames[0][..50]).is_err());
+        let mut assembler = ChunkAssembler::default();
+        assert!(assembler.push(decode_frame(&frames[1])?).is_err());
+        assembler.push(decode_frame(&frames[0])?)?;
+        assert!(assembler.push(decode_frame(&frames[2])?).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn huge_declared_chunk_size_is_not_preallocated() -> Result<()> {
+        let mut assembler = ChunkAssembler::default();
+        let frame = Frame {
+            flags: FLAG_FIRST,
+            index: 0,
+            chunk_offset: 0,
+            chunk_size: u64::MAX,
+            piece_offset: 0,
+            hash: vec![0xab; 32],
+            payload: b"data".to_vec(),
+        };
+        assert!(assembler.push(frame.clone())?.is_none());
+        let last = Frame {
+            flags: FLAG_LAST,
+            piece_offset: 4,
+            ..frame
+        };
+        assert!(assembler.push(last).is_err());
+        Ok(())
+    }
+}
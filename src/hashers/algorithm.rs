@@ -0,0 +1,176 @@
+//! Hash algorithm chosen at runtime, such as from a configuration file
+use super::{Hasher, KeyedHasher};
+use anyhow::{anyhow, Result};
+use std::{any::TypeId, fmt, str::FromStr};
+
+macro_rules! algorithms {
+    ($($(#[$attr:meta])* $variant:ident => $name:literal, $code:expr, $hasher:ty;)*) => {
+        /// Hash algorithm selectable at runtime, dispatching to the matching
+        /// typed hasher
+        ///
+        /// Parses from and displays as the lowercase algorithm name, such as
+        /// `sha256` or `blake3`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Algorithm {
+            $($(#[$attr])* $variant,)*
+        }
+
+        impl Algorithm {
+            /// All algorithms available with the enabled features
+            pub const ALL: &'static [Algorithm] = &[$($(#[$attr])* Algorithm::$variant,)*];
+
+            /// Lowercase name of the algorithm
+            pub fn name(self) -> &'static str {
+                match self {
+                    $($(#[$attr])* Algorithm::$variant => $name,)*
+                }
+            }
+
+            /// Multicodec code of the algorithm for
+            /// [`multihash`](../../multihash/index.html) encoding, `None` if
+            /// the multicodec table has no entry for it
+            pub fn multihash_code(self) -> Option<u64> {
+                match self {
+                    $($(#[$attr])* Algorithm::$variant => $code,)*
+                }
+            }
+
+            /// Algorithm of a typed hasher, `None` for hashers without a
+            /// runtime equivalent
+            pub fn of<H: Hasher + 'static>() -> Option<Algorithm> {
+                let id = TypeId::of::<H>();
+                $($(#[$attr])* {
+                    if id == TypeId::of::<$hasher>() {
+                        return Some(Algorithm::$variant);
+                    }
+                })*
+                None
+            }
+
+            /// Returns the hashed bytes
+            /// # Arguments
+            /// * `bytes` - byte slice to hash
+            pub fn hash_bytes(self, bytes: &[u8]) -> Vec<u8> {
+                match self {
+                    $($(#[$attr])* Algorithm::$variant => <$hasher>::hash_bytes(bytes),)*
+                }
+            }
+        }
+    };
+}
+
+algorithms! {
+    Sha256 => "sha256", Some(0x12), super::sha2::Sha256Hasher;
+    Sha512 => "sha512", Some(0x13), super::sha2::Sha512Hasher;
+    Blake3 => "blake3", Some(0x1e), super::blake3::Blake3Hasher;
+    Shake128 => "shake128", Some(0x18), super::shake::Shake128Hasher;
+    Shake256 => "shake256", Some(0x19), super::shake::Shake256Hasher;
+    Xxh64 => "xxh64", Some(0xb3e2), super::xxhash::Xxh64Hasher;
+    Xxh3 => "xxh3", Some(0xb3e3), super::xxhash::Xxh3Hasher;
+    Crc32 => "crc32", Some(0x0132), super::crc::Crc32Hasher;
+    Crc32c => "crc32c", None, super::crc::Crc32cHasher;
+    Crc64 => "crc64", None, super::crc::Crc64Hasher;
+    #[cfg(feature = "legacy-hashes")]
+    Md5 => "md5", Some(0xd5), super::legacy::Md5Hasher;
+    #[cfg(feature = "legacy-hashes")]
+    Sha1 => "sha1", Some(0x11), super::legacy::Sha1Hasher;
+    #[cfg(feature = "ripemd")]
+    Ripemd160 => "ripemd160", Some(0x1053), super::ripemd::Ripemd160Hasher;
+    #[cfg(feature = "sm3")]
+    Sm3 => "sm3", Some(0x534d), super::sm3::Sm3Hasher;
+    #[cfg(feature = "streebog")]
+    Streebog256 => "streebog256", None, super::streebog::Streebog256Hasher;
+    #[cfg(feature = "streebog")]
+    Streebog512 => "streebog512", None, super::streebog::Streebog512Hasher;
+    #[cfg(feature = "whirlpool")]
+    Whirlpool => "whirlpool", None, super::whirlpool::WhirlpoolHasher;
+}
+
+impl KeyedHasher for Algorithm {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        self.hash_bytes(bytes)
+    }
+}
+
+/// Hasher dispatching to an algorithm chosen at runtime, the hasher type of
+/// [`ChunkedHasher::with_algorithm`](../../struct.ChunkedHasher.html#method.with_algorithm)
+///
+/// The algorithm is a value, so hashing goes through
+/// [`hash_keyed`](../trait.KeyedHasher.html#tymethod.hash_keyed) and the
+/// chunked hasher keeps the algorithm next to the type.
+///
+/// # Panics
+///
+/// The static [`Hasher::hash`](../trait.Hasher.html#tymethod.hash) has no
+/// algorithm to dispatch to and panics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DynHasher(pub Algorithm);
+
+impl Hasher for DynHasher {
+    /// Unknown without an algorithm, like the static `hash`
+    const BLOCK_SIZE: usize = 0;
+
+    type Output = Vec<u8>;
+
+    fn hash(_bytes: &[u8]) -> Vec<u8> {
+        panic!("DynHasher has no static algorithm, hash through its value instead")
+    }
+}
+
+impl KeyedHasher for DynHasher {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        self.0.hash_bytes(bytes)
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim();
+        Algorithm::ALL
+            .iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+            .copied()
+            .ok_or_else(|| anyhow!("Unknown or disabled hash algorithm {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::{blake3::Blake3Hasher, sha2::Sha512Hasher};
+
+    #[test]
+    fn names_round_trip() -> Result<()> {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.to_string().parse::<Algorithm>()?, *algorithm);
+        }
+        assert_eq!("SHA512".parse::<Algorithm>()?, Algorithm::Sha512);
+        assert!("sha3".parse::<Algorithm>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dispatches_to_typed_hashers() {
+        assert_eq!(
+            Algorithm::Blake3.hash_bytes(b"brainstorm"),
+            Blake3Hasher::hash_bytes(b"brainstorm")
+        );
+        assert_eq!(
+            Algorithm::Sha512.hash_bytes(b"brainstorm"),
+            Sha512Hasher::hash_bytes(b"brainstorm")
+        );
+        assert_eq!(
+            DynHasher(Algorithm::Blake3).hash_keyed(b"brainstorm"),
+            Blake3Hasher::hash_bytes(b"brainstorm")
+        );
+        assert_eq!(Algorithm::of::<DynHasher>(), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! BLAKE3 hashers, including the keyed and key derivation modes
+//!
+//! Backed by the official `blake3` crate.
+use super::{Hasher, KeyedHasher, XofHasher};
+
+/// Plain BLAKE3 hasher with a 32 byte digest
+pub struct Blake3Hasher;
+
+impl Hasher for Blake3Hasher {
+    const BLOCK_SIZE: usize = 64;
+
+    type Output = [u8; 32];
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        *::blake3::hash(bytes).as_bytes()
+    }
+}
+
+impl XofHasher for Blake3Hasher {
+    fn hash_xof(bytes: &[u8], output: &mut [u8]) {
+        ::blake3::Hasher::new()
+            .update(bytes)
+            .finalize_xof()
+            .fill(output)
+    }
+}
+
+/// BLAKE3 hasher in keyed hash or key derivation mode, for per-context
+/// chunk IDs
+pub struct Blake3KeyedHasher {
+    /// Hasher set up with the key, cloned for every input
+    hasher: ::blake3::Hasher,
+}
+
+impl Blake3KeyedHasher {
+    /// Instantiate a BLAKE3 keyed hasher, a MAC over every chunk
+    /// # Arguments
+    /// * `key` - secret key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::hashers::{blake3::Blake3KeyedHasher, KeyedHasher};
+    /// let hasher = Blake3KeyedHasher::keyed(b"whats the Elvish word for friend");
+    /// assert_eq!(hasher.hash_keyed(b"chunk").len(), 32);
+    /// ```
+    pub fn keyed(key: &[u8; 32]) -> Self {
+        Self {
+            hasher: ::blake3::Hasher::new_keyed(key),
+        }
+    }
+
+    /// Instantiate a BLAKE3 key derivation hasher, deriving a key from every
+    /// chunk within an application specific context
+    /// # Arguments
+    /// * `context` - hardcoded, globally unique and application specific
+    ///   context string
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::hashers::{blake3::Blake3KeyedHasher, KeyedHasher};
+    /// let hasher = Blake3KeyedHasher::derive_key("example.com 2020-01-01 chunk ids");
+    /// assert_eq!(hasher.hash_keyed(b"chunk").len(), 32);
+    /// ```
+    pub fn derive_key(context: &str) -> Self {
+        Self {
+            hasher: ::blake3::Hasher::new_derive_key(context),
+        }
+    }
+}
+
+impl KeyedHasher for Blake3KeyedHasher {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        self.hasher
+            .clone()
+            .update(bytes)
+            .finalize()
+            .as_bytes()
+            .to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8; 32] = b"whats the Elvish word for friend";
+    const CONTEXT: &str = "BLAKE3 2019-12-27 16:29:52 test vectors context";
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i % 251) as u8).collect()
+    }
+
+    // Input lengths and their hash, keyed hash and derived key, inputs as
+    // in the official BLAKE3 test vectors
+    const KNOWN_ANSWERS: [(usize, &str, &str, &str); 6] = [
+        (
+            0,
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+            "92b2b75604ed3c761f9d6f62392c8a9227ad0ea3f09573e783f1498a4ed60d26",
+            "2cc39783c223154fea8dfb7c1b1660f2ac2dcbd1c1de8277b0b0dd39b7e50d7d",
+        ),
+        (
+            1023,
+            "10108970eeda3eb932baac1428c7a2163b0e924c9a9e25b35bba72b28f70bd11",
+            "c951ecdf03288d0fcc96ee3413563d8a6d3589547f2c2fb36d9786470f1b9d6e",
+            "74a16c1c3d44368a86e1ca6df64be6a2f64cce8f09220787450722d85725dea5",
+        ),
+        (
+            1024,
+            "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+            "75c46f6f3d9eb4f55ecaaee480db732e6c2105546f1e675003687c31719c7ba4",
+            "7356cd7720d5b66b6d0697eb3177d9f8d73a4a5c5e968896eb6a689684302706",
+        ),
+        (
+            1025,
+            "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+            "357dc55de0c7e382c900fd6e320acc04146be01db6a8ce7210b7189bd664ea69",
+            "effaa245f065fbf82ac186839a249707c3bddf6d3fdda22d1b95a3c970379bcb",
+        ),
+        (
+            3073,
+            "7124b49501012f81cc7f11ca069ec9226cecb8a2c850cfe644e327d22d3e1cd3",
+            "68dede9bef00ba89e43f31a6825f4cf433389fedae75c04ee9f0cf16a427c95a",
+            "72613c9ec9ff7e40f8f5c173784c532ad852e827dba2bf85b2ab4b76f7079081",
+        ),
+        (
+            31744,
+            "62b6960e1a44bcc1eb1a611a8d6235b6b4b78f32e7abc4fb4c6cdcce94895c47",
+            "efa53b389ab67c593dba624d898d0f7353ab99e4ac9d42302ee64cbf9939a419",
+            "39772aef80e0ebe60596361e45b061e8f417429d529171b6764468c22928e28e",
+        ),
+    ];
+
+    #[test]
+    fn extended_output() {
+        // Official test vector of the empty input, extended to 131 bytes
+        let mut output = [0; 131];
+        Blake3Hasher::hash_xof(&[], &mut output);
+        assert_eq!(
+            hex::encode(&output[..]),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262e00f03e7b69af26b7faaf09fcd333050338ddfe085b8cc869ca98b206c08243a26f5487789e8f660afe6c99ef9e0c52b92e7393024a80459cf91f476f9ffdbda7001c22e159b402631f277ca96f2defdf1078282314e763699a31c5363165421cce14d"
+        );
+    }
+
+    #[test]
+    fn known_answers() {
+        let keyed = Blake3KeyedHasher::keyed(KEY);
+        let derive_key = Blake3KeyedHasher::derive_key(CONTEXT);
+        for (length, plain, keyed_answer, derived) in KNOWN_ANSWERS.iter() {
+            let input = data(*length);
+            assert_eq!(hex::encode(Blake3Hasher::hash_bytes(&input)), *plain);
+            assert_eq!(hex::encode(keyed.hash_keyed(&input)), *keyed_answer);
+            assert_eq!(hex::encode(derive_key.hash_keyed(&input)), *derived);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! CRC checksums
+//!
+//! Cyclic redundancy checks aren't cryptographic hashes, but storage systems
+//! (S3, iSCSI, ZFS style scrubbing) record them, so chunk records made with
+//! these hashers can be cross-checked against them. Digests are big endian.
+//!
+//! Unlike the other hashers, CRCs of adjacent chunks can be combined into the
+//! CRC of the whole stream, see [`combine_chunks`](fn.combine_chunks.html).
+use super::Hasher;
+use crate::Chunk;
+use anyhow::{ensure, Result};
+
+/// Parameters of a reflected CRC with all-ones initial value and final xor
+struct Crc {
+    width: u32,
+    /// Reflected polynomial
+    polynomial: u64,
+    table: [u64; 256],
+}
+
+impl Crc {
+    const fn new(width: u32, polynomial: u64) -> Self {
+        let mut table = [0; 256];
+        let mut byte = 0;
+        while byte < 256 {
+            let mut value = byte as u64;
+            let mut bit = 0;
+            while bit < 8 {
+                value = if value & 1 == 1 {
+                    (value >> 1) ^ polynomial
+                } else {
+                    value >> 1
+                };
+                bit += 1;
+            }
+            table[byte] = value;
+            byte += 1;
+        }
+        Crc {
+            width,
+            polynomial,
+            table,
+        }
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.width)
+    }
+
+    fn checksum(&self, bytes: &[u8]) -> u64 {
+        let crc = bytes.iter().fold(self.mask(), |crc, byte| {
+            self.table[((crc ^ u64::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+        });
+        crc ^ self.mask()
+    }
+
+    /// Multiply two polynomials modulo the CRC polynomial, in reflected form
+    fn multiply(&self, a: u64, mut b: u64) -> u64 {
+        let mut bit = 1 << (self.width - 1);
+        let mut product = 0;
+        while bit != 0 {
+            if a & bit != 0 {
+                product ^= b;
+            }
+            bit >>= 1;
+            b = if b & 1 == 1 {
+                (b >> 1) ^ self.polynomial
+            } else {
+                b >> 1
+            };
+        }
+        product
+    }
+
+    /// CRC of `first` followed by `second`, given only their CRCs and the
+    /// length of `second`
+    fn combine(&self, first: u64, second: u64, second_length: u64) -> u64 {
+        // x^8, one byte worth of shifting
+        let mut square = 1 << (self.width - 9);
+        let mut shift = 1 << (self.width - 1);
+        let mut length = second_length;
+        while length != 0 {
+            if length & 1 == 1 {
+                shift = self.multiply(square, shift);
+            }
+            square = self.multiply(square, square);
+            length >>= 1;
+        }
+        self.multiply(shift, first) ^ second
+    }
+}
+
+const CRC32: Crc = Crc::new(32, 0xedb8_8320);
+const CRC32C: Crc = Crc::new(32, 0x82f6_3b78);
+const CRC64: Crc = Crc::new(64, 0x9a6c_9329_ac4b_c9b5);
+
+/// Hasher whose digests of adjacent inputs can be combined
+pub trait CombinableHasher: Hasher {
+    /// Returns the digest of `first` followed by `second`
+    /// # Arguments
+    /// * `first` - digest of the leading bytes
+    /// * `second` - digest of the trailing bytes
+    /// * `second_length` - number of trailing bytes
+    fn combine(first: &[u8], second: &[u8], second_length: u64) -> Vec<u8>;
+}
+
+macro_rules! crc_hasher {
+    ($(#[$doc: meta])* $name: ident, $crc: ident, $int: ty) => {
+        $(#[$doc])*
+        pub struct $name;
+
+        impl Hasher for $name {
+            const BLOCK_SIZE: usize = 1;
+
+            type Output = [u8; std::mem::size_of::<$int>()];
+
+            fn hash(bytes: &[u8]) -> Self::Output {
+                ($crc.checksum(bytes) as $int).to_be_bytes()
+            }
+        }
+
+        impl CombinableHasher for $name {
+            fn combine(first: &[u8], second: &[u8], second_length: u64) -> Vec<u8> {
+                let decode = |digest: &[u8]| {
+                    digest.iter().fold(0, |crc, byte| (crc << 8) | u64::from(*byte))
+                };
+                ($crc.combine(decode(first), decode(second), second_length) as $int)
+                    .to_be_bytes()
+                    .to_vec()
+            }
+        }
+    };
+}
+
+crc_hasher!(
+    /// CRC-32 (IEEE 802.3), as used by zlib, gzip and S3
+    Crc32Hasher,
+    CRC32,
+    u32
+);
+crc_hasher!(
+    /// CRC-32C (Castagnoli), as used by iSCSI, ext4 and S3
+    Crc32cHasher,
+    CRC32C,
+    u32
+);
+crc_hasher!(
+    /// CRC-64/NVME, as used by NVMe and S3
+    Crc64Hasher,
+    CRC64,
+    u64
+);
+
+/// Combine the CRCs of fixed or dynamic chunks into the CRC of the whole
+/// stream, matching what a single pass checksum tool reports
+///
+/// The chunks must be hashed over their data only, see
+/// [`ChunkedHasher::unpadded_last_chunk`](../../struct.ChunkedHasher.html#method.unpadded_last_chunk).
+/// # Arguments
+/// * `chunks` - chunks covering the stream without gaps or overlaps, in any order
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::{crc::{combine_chunks, Crc32Hasher}, Hasher}, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Crc32Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 12)?
+///         .unpadded_last_chunk()
+///         .collect();
+/// assert_eq!(
+///     combine_chunks::<Crc32Hasher>(&chunks)?,
+///     Crc32Hasher::hash_bytes(WORDSTRING.as_bytes())
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn combine_chunks<H: CombinableHasher>(chunks: &[Chunk]) -> Result<Vec<u8>> {
+    let mut ordered: Vec<&Chunk> = chunks.iter().collect();
+    ordered.sort_by_key(|chunk| chunk.offset);
+    let mut digest = H::hash_bytes(&[]);
+    let mut covered = 0;
+    for chunk in ordered {
+        ensure!(
+            chunk.offset == covered,
+            "Chunk {} at {} does not continue the stream at {}",
+            chunk.index,
+            chunk.offset,
+            covered
+        );
+        digest = H::combine(&digest, &chunk.hash, chunk.size);
+        covered += chunk.size;
+    }
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkedHasher;
+    use std::io::Cursor;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| ((i * 7 + 3) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn check_values() {
+        assert_eq!(
+            hex::encode(Crc32Hasher::hash_bytes(b"123456789")),
+            "cbf43926"
+        );
+        assert_eq!(
+            hex::encode(Crc32cHasher::hash_bytes(b"123456789")),
+            "e3069283"
+        );
+        assert_eq!(
+            hex::encode(Crc64Hasher::hash_bytes(b"123456789")),
+            "ae8b14860a799888"
+        );
+        assert_eq!(Crc32Hasher::hash_bytes(b""), vec![0; 4]);
+    }
+
+    #[test]
+    fn combine_matches_single_pass() {
+        let input = data(1000);
+        let (first, second) = input.split_at(333);
+        assert_eq!(
+            hex::encode(Crc32cHasher::combine(
+                &Crc32cHasher::hash_bytes(first),
+                &Crc32cHasher::hash_bytes(second),
+                second.len() as u64
+            )),
+            "a4c0fde8"
+        );
+        assert_eq!(
+            hex::encode(Crc64Hasher::combine(
+                &Crc64Hasher::hash_bytes(first),
+                &Crc64Hasher::hash_bytes(second),
+                second.len() as u64
+            )),
+            "0ede9db129d4b7d4"
+        );
+    }
+
+    #[test]
+    fn combine_chunks_of_stream() -> Result<()> {
+        let input = data(1000);
+        let mut buffer: Cursor<&[u8]> = Cursor::new(&input);
+        let mut chunks: Vec<Chunk> =
+            ChunkedHasher::<Crc64Hasher>::fixed_chunks(&mut buffer, input.len() as u64, 96)?
+                .unpadded_last_chunk()
+                .collect();
+        chunks.reverse();
+        assert_eq!(
+            combine_chunks::<Crc64Hasher>(&chunks)?,
+            Crc64Hasher::hash_bytes(&input)
+        );
+        chunks.remove(3);
+        assert!(combine_chunks::<Crc64Hasher>(&chunks).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! Adapter for RustCrypto digests
+use super::{hash_in_increments, Hasher, StreamingHasher};
+use ::digest::{
+    generic_array::{typenum::Unsigned, GenericArray},
+    BlockInput, Digest,
+};
+use std::io::{self, Read};
+
+/// Hasher wrapping any RustCrypto digest
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::digest::DigestHasher, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> = ChunkedHasher::<DigestHasher<sha2::Sha384>>::fixed_chunks(
+///     &mut buffer,
+///     WORDSTRING.len() as u64,
+///     10,
+/// )?
+/// .collect();
+/// assert_eq!(chunks[0].hash.len(), 48);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DigestHasher<D> {
+    /// Digest state while streaming
+    state: D,
+}
+
+impl<D: Digest + BlockInput> Hasher for DigestHasher<D> {
+    const BLOCK_SIZE: usize = D::BlockSize::USIZE;
+
+    type Output = GenericArray<u8, D::OutputSize>;
+
+    fn hash(bytes: &[u8]) -> Self::Output {
+        let mut hasher = D::new();
+        hasher.input(bytes);
+        hasher.result()
+    }
+
+    fn hash_reader(reader: &mut dyn Read, length: u64) -> io::Result<(u64, Self::Output)> {
+        hash_in_increments::<Self>(reader, length)
+    }
+}
+
+impl<D: Digest + BlockInput> StreamingHasher for DigestHasher<D> {
+    fn new() -> Self {
+        Self { state: D::new() }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.state.input(bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.state.result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::{Sha256Hasher, Sha512Hasher};
+
+    #[test]
+    fn block_sizes() {
+        assert_eq!(Sha256Hasher::BLOCK_SIZE, 64);
+        assert_eq!(Sha512Hasher::BLOCK_SIZE, 128);
+        assert_eq!(
+            hex::encode(DigestHasher::<sha2::Sha224>::hash_bytes(b"abc")),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+    }
+
+    #[test]
+    fn reader_is_hashed_in_increments() -> std::io::Result<()> {
+        let data = vec![7u8; 200 * 1024];
+        let (size, digest) = Sha256Hasher::hash_reader(&mut &data[..], 150 * 1024)?;
+        assert_eq!(size, 150 * 1024);
+        assert_eq!(digest, Sha256Hasher::hash(&data[..150 * 1024]));
+        // A claimed length beyond the data only hashes what is there
+        let (size, digest) = Sha256Hasher::hash_reader(&mut &data[..10], u64::MAX)?;
+        assert_eq!(size, 10);
+        assert_eq!(digest, Sha256Hasher::hash(&data[..10]));
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+//! HMAC (RFC 2104) over any hasher, to authenticate chunk hashes with a
+//! secret key
+use super::{Hasher, KeyedHasher};
+use std::marker::PhantomData;
+
+/// HMAC keyed hasher wrapping `H`
+pub struct HmacHasher<H> {
+    /// Key padded to the block size, xored with the inner pad
+    inner_key: Vec<u8>,
+    /// Key padded to the block size, xored with the outer pad
+    outer_key: Vec<u8>,
+    _marker: PhantomData<H>,
+}
+
+impl<H: Hasher> HmacHasher<H> {
+    /// Instantiate an HMAC hasher
+    /// # Arguments
+    /// * `key` - secret key, keys longer than the block size of `H` are
+    ///   hashed first
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::hashers::{hmac::HmacHasher, sha2::Sha256Hasher, KeyedHasher};
+    /// let hasher = HmacHasher::<Sha256Hasher>::new(b"secret");
+    /// let other = HmacHasher::<Sha256Hasher>::new(b"other");
+    /// assert_ne!(hasher.hash_keyed(b"chunk"), other.hash_keyed(b"chunk"));
+    /// ```
+    pub fn new(key: &[u8]) -> Self {
+        let mut padded = if key.len() > H::BLOCK_SIZE {
+            H::hash_bytes(key)
+        } else {
+            key.to_vec()
+        };
+        padded.resize(H::BLOCK_SIZE, 0);
+        Self {
+            inner_key: padded.iter().map(|byte| byte ^ 0x36).collect(),
+            outer_key: padded.iter().map(|byte| byte ^ 0x5c).collect(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H: Hasher> KeyedHasher for HmacHasher<H> {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut inner = Vec::with_capacity(self.inner_key.len() + bytes.len());
+        inner.extend_from_slice(&self.inner_key);
+        inner.extend_from_slice(bytes);
+        let mut outer = self.outer_key.clone();
+        outer.extend_from_slice(&H::hash_bytes(&inner));
+        H::hash_bytes(&outer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::{Sha256Hasher, Sha512Hasher};
+
+    // RFC 4231 test cases 2 and 6
+    const DATA: &[u8] = b"what do ya want for nothing?";
+    const LONG_KEY_DATA: &[u8] = b"Test Using Larger Than Block-Size Key - Hash Key First";
+
+    #[test]
+    fn rfc4231() {
+        assert_eq!(
+            hex::encode(HmacHasher::<Sha256Hasher>::new(b"Jefe").hash_keyed(DATA)),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex::encode(HmacHasher::<Sha512Hasher>::new(b"Jefe").hash_keyed(DATA)),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+        assert_eq!(
+            hex::encode(HmacHasher::<Sha256Hasher>::new(&[0xaa; 131]).hash_keyed(LONG_KEY_DATA)),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+        assert_eq!(
+            hex::encode(HmacHasher::<Sha512Hasher>::new(&[0xaa; 131]).hash_keyed(LONG_KEY_DATA)),
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+             6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"
+        );
+    }
+}
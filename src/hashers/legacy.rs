@@ -0,0 +1,57 @@
+//! Legacy MD5 and SHA-1 hashers
+//!
+//! These exist for interoperability with legacy chunk catalogs and signature
+//! formats only. Both algorithms are broken: collisions can be produced at
+//! will, so they must not be relied upon to detect malicious modification.
+use super::digest::DigestHasher;
+
+/// MD5 hasher backed by the RustCrypto implementation, see the
+/// [module documentation](index.html) before use
+pub type Md5Hasher = DigestHasher<::md5::Md5>;
+
+/// SHA-1 hasher backed by the RustCrypto implementation, see the
+/// [module documentation](index.html) before use
+pub type Sha1Hasher = DigestHasher<::sha1::Sha1>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::Hasher;
+    use hex::encode;
+
+    #[test]
+    fn md5_known_answers() {
+        assert_eq!(
+            encode(Md5Hasher::hash_bytes(b"")),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            encode(Md5Hasher::hash_bytes(b"abc")),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            encode(Md5Hasher::hash_bytes(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+
+    #[test]
+    fn sha1_known_answers() {
+        assert_eq!(
+            encode(Sha1Hasher::hash_bytes(b"")),
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+        );
+        assert_eq!(
+            encode(Sha1Hasher::hash_bytes(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            encode(Sha1Hasher::hash_bytes(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+}
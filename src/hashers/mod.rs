@@ -1,9 +1,137 @@
+pub mod algorithm;
+pub mod blake3;
+pub mod crc;
+pub mod digest;
+pub mod hmac;
+#[cfg(feature = "legacy-hashes")]
+pub mod legacy;
+pub mod multi;
+pub mod null;
+pub mod poly1305;
+#[cfg(feature = "ripemd")]
+pub mod ripemd;
 pub mod sha2;
+pub mod shake;
+pub mod siphash;
+#[cfg(feature = "sm3")]
+pub mod sm3;
+#[cfg(feature = "streebog")]
+pub mod streebog;
+#[cfg(feature = "whirlpool")]
+pub mod whirlpool;
+pub mod xof;
+pub mod xxhash;
+
+use std::{
+    fmt::Debug,
+    io::{self, Read},
+};
+
+/// Size of the reads when hashing a reader in increments
+const READ_SIZE: u64 = 64 * 1024;
 
 /// Hasher trait, which provides a pluggable way to swap hashing algorithm used
 pub trait Hasher {
+    /// Size in bytes of the blocks the hash function processes, used when
+    /// deriving keyed hashers such as [`HmacHasher`](hmac/struct.HmacHasher.html)
+    const BLOCK_SIZE: usize;
+
+    /// Digest produced by the hash function, fixed size for all built-in
+    /// hashers so hashing doesn't need to allocate
+    type Output: AsRef<[u8]> + Clone + Debug + PartialEq;
+
+    /// Returns the digest of the bytes
+    /// # Arguments
+    /// * `bytes` - byte slice to hash
+    fn hash(bytes: &[u8]) -> Self::Output;
+
     /// Returns the hashed bytes
     /// # Arguments
     /// * `bytes` - byte slice to hash
-    fn hash_bytes(bytes: &[u8]) -> Vec<u8>;
+    fn hash_bytes(bytes: &[u8]) -> Vec<u8> {
+        Self::hash(bytes).as_ref().to_vec()
+    }
+
+    /// Returns the digest of up to `length` bytes of a reader, along with
+    /// the amount of bytes read, which is less than `length` if the reader
+    /// ends first
+    ///
+    /// Only the bytes actually read are held in memory, never `length`
+    /// bytes up front. Hashers that can be fed in increments hash in bounded
+    /// reads instead, see [`hash_in_increments`](fn.hash_in_increments.html).
+    /// # Arguments
+    /// * `reader` - reader to hash from
+    /// * `length` - amount of bytes to hash
+    fn hash_reader(reader: &mut dyn Read, length: u64) -> io::Result<(u64, Self::Output)> {
+        let mut bytes = Vec::new();
+        reader.take(length).read_to_end(&mut bytes)?;
+        Ok((bytes.len() as u64, Self::hash(&bytes)))
+    }
+}
+
+/// Hash up to `length` bytes of a reader in bounded reads, implementing
+/// [`Hasher::hash_reader`](trait.Hasher.html#method.hash_reader) for hashers
+/// that can be fed in increments
+/// # Arguments
+/// * `reader` - reader to hash from
+/// * `length` - amount of bytes to hash
+pub fn hash_in_increments<S: StreamingHasher>(
+    reader: &mut dyn Read,
+    length: u64,
+) -> io::Result<(u64, S::Output)> {
+    let mut hasher = S::new();
+    let mut buffer = vec![0; length.min(READ_SIZE) as usize];
+    let mut size = 0;
+    while size < length {
+        let wanted = (length - size).min(READ_SIZE) as usize;
+        let read_bytes = match reader.read(&mut buffer[..wanted]) {
+            Ok(0) => break,
+            Ok(read_bytes) => read_bytes,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buffer[..read_bytes]);
+        size += read_bytes as u64;
+    }
+    Ok((size, hasher.finalize()))
+}
+
+/// Hasher carrying state, such as a secret key, that can't be expressed by a
+/// static [`Hasher`](trait.Hasher.html)
+///
+/// Used through [`ChunkedHasher::with_keyed_hasher`](../struct.ChunkedHasher.html#method.with_keyed_hasher).
+pub trait KeyedHasher {
+    /// Returns the hashed bytes
+    /// # Arguments
+    /// * `bytes` - byte slice to hash
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8>;
+}
+
+/// Hasher that can be fed its input in increments instead of all at once
+///
+/// Used through [`ChunkedHasher::with_read_buffer`](../struct.ChunkedHasher.html#method.with_read_buffer).
+pub trait StreamingHasher: Hasher + Sized {
+    /// Start hashing a new input
+    fn new() -> Self;
+
+    /// Feed the next bytes of the input
+    /// # Arguments
+    /// * `bytes` - next bytes of the input
+    fn update(&mut self, bytes: &[u8]);
+
+    /// Finish hashing, returning the digest
+    fn finalize(self) -> Self::Output;
+}
+
+/// Extendable-output function, producing a digest of any length from the
+/// same input
+///
+/// Used with a caller selected output length through
+/// [`Xof`](xof/struct.Xof.html).
+pub trait XofHasher: Hasher {
+    /// Fill the output with the digest of the bytes
+    /// # Arguments
+    /// * `bytes` - byte slice to hash
+    /// * `output` - digest to fill, its length is the output length
+    fn hash_xof(bytes: &[u8], output: &mut [u8]);
 }
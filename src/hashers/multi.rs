@@ -0,0 +1,147 @@
+//! Several digests per chunk in a single read pass
+use super::{Hasher, StreamingHasher};
+
+/// Hasher combining a tuple of two to four hashers, so chunks carry the
+/// digests of all of them, such as when migrating manifests to a new
+/// algorithm
+///
+/// Regular chunks carry the concatenated digests, chunks produced through
+/// [`ChunkedHasher::typed`](../../struct.ChunkedHasher.html#method.typed)
+/// carry a [`MultiDigest`](struct.MultiDigest.html) with every digest typed.
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::{blake3::Blake3Hasher, multi::MultiHasher, sha2::Sha256Hasher, Hasher}, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<_> = ChunkedHasher::<MultiHasher<(Sha256Hasher, Blake3Hasher)>>::fixed_chunks(
+///     &mut buffer,
+///     WORDSTRING.len() as u64,
+///     10,
+/// )?
+/// .typed()?
+/// .collect();
+/// let (sha256, blake3) = &chunks[0].hash.digests;
+/// assert_eq!(sha256.as_slice(), Sha256Hasher::hash_bytes(b"brainstorm").as_slice());
+/// assert_eq!(blake3, &Blake3Hasher::hash(b"brainstorm"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultiHasher<T> {
+    /// States of the combined hashers while streaming
+    state: T,
+}
+
+/// Digests of a [`MultiHasher`](struct.MultiHasher.html), in the order of
+/// its hashers
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiDigest<T> {
+    /// Tuple of the individual digests
+    pub digests: T,
+    /// All digests concatenated
+    concatenated: Vec<u8>,
+}
+
+impl<T> AsRef<[u8]> for MultiDigest<T> {
+    fn as_ref(&self) -> &[u8] {
+        &self.concatenated
+    }
+}
+
+/// Largest of the block sizes of the hashers
+const fn largest(block_sizes: &[usize]) -> usize {
+    let mut largest = 0;
+    let mut index = 0;
+    while index < block_sizes.len() {
+        if block_sizes[index] > largest {
+            largest = block_sizes[index];
+        }
+        index += 1;
+    }
+    largest
+}
+
+macro_rules! multi_hasher {
+    ($($hasher:ident $index:tt),+) => {
+        impl<$($hasher: Hasher),+> Hasher for MultiHasher<($($hasher,)+)> {
+            const BLOCK_SIZE: usize = largest(&[$($hasher::BLOCK_SIZE),+]);
+
+            type Output = MultiDigest<($($hasher::Output,)+)>;
+
+            fn hash(bytes: &[u8]) -> Self::Output {
+                let digests = ($($hasher::hash(bytes),)+);
+                MultiDigest {
+                    concatenated: [$(digests.$index.as_ref()),+].concat(),
+                    digests,
+                }
+            }
+        }
+
+        impl<$($hasher: StreamingHasher),+> StreamingHasher for MultiHasher<($($hasher,)+)> {
+            fn new() -> Self {
+                Self {
+                    state: ($($hasher::new(),)+),
+                }
+            }
+
+            fn update(&mut self, bytes: &[u8]) {
+                $(self.state.$index.update(bytes);)+
+            }
+
+            fn finalize(self) -> Self::Output {
+                let digests = ($(self.state.$index.finalize(),)+);
+                MultiDigest {
+                    concatenated: [$(digests.$index.as_ref()),+].concat(),
+                    digests,
+                }
+            }
+        }
+    };
+}
+
+multi_hasher!(A 0, B 1);
+multi_hasher!(A 0, B 1, C 2);
+multi_hasher!(A 0, B 1, C 2, D 3);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hashers::{null::NullHasher, sha2::Sha256Hasher, sha2::Sha512Hasher, xxhash::Xxh3Hasher},
+        Chunk, ChunkedHasher,
+    };
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    #[test]
+    fn concatenates_digests() {
+        type Multi = MultiHasher<(Sha256Hasher, Xxh3Hasher, Sha512Hasher)>;
+        let mut expected = Sha256Hasher::hash_bytes(DATA);
+        expected.extend(Xxh3Hasher::hash_bytes(DATA));
+        expected.extend(Sha512Hasher::hash_bytes(DATA));
+        assert_eq!(Multi::hash_bytes(DATA), expected);
+        assert_eq!(Multi::hash(DATA).digests.1, Xxh3Hasher::hash(DATA));
+    }
+
+    #[test]
+    fn streams_every_hasher() -> anyhow::Result<()> {
+        type Multi = MultiHasher<(Sha256Hasher, NullHasher)>;
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Multi>::fixed_chunks(&mut buffer, DATA.len() as u64, 20)?
+                .with_read_buffer(7)
+                .unpadded_last_chunk()
+                .collect();
+        for chunk in &chunks {
+            let start = chunk.offset as usize;
+            let end = start + chunk.size as usize;
+            assert_eq!(chunk.hash, Multi::hash_bytes(&DATA[start..end]));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Poly1305 message authentication (RFC 8439), for in-process integrity
+//! checks such as of cache entries
+//!
+//! Poly1305 authenticates a single message per one-time key. As in RFC 8439
+//! section 2.6, the one-time key is the first 32 bytes of the ChaCha20 block
+//! with counter 0 under a long-term key and a nonce, so the caller must never
+//! reuse a nonce with the same key. Tags therefore aren't chunk identities:
+//! use [`Blake3KeyedHasher`](../blake3/struct.Blake3KeyedHasher.html) or
+//! [`SipHasher24`](../siphash/struct.SipHasher24.html) to key chunk hashes.
+//! Backed by the RustCrypto `chacha20` and `poly1305` crates.
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20,
+};
+use poly1305::{universal_hash::KeyInit, Poly1305};
+
+/// Poly1305 tag of the bytes, under the one-time key generated from the key
+/// and the nonce
+/// # Arguments
+/// * `key` - long-term secret key
+/// * `nonce` - value never used twice with the same key, such as a message
+///   counter
+/// * `bytes` - authenticated bytes
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::hashers::poly1305::{mac, verify};
+/// let key = b"whats the Elvish word for friend";
+/// let nonce = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+/// let tag = mac(key, &nonce, b"chunk");
+/// assert!(verify(key, &nonce, b"chunk", &tag));
+/// assert!(!verify(key, &nonce, b"chunks", &tag));
+/// ```
+pub fn mac(key: &[u8; 32], nonce: &[u8; 12], bytes: &[u8]) -> [u8; 16] {
+    Poly1305::new(&one_time_key(key, nonce).into())
+        .compute_unpadded(bytes)
+        .into()
+}
+
+/// Whether a tag authenticates the bytes under the key and the nonce
+///
+/// The tags are compared in constant time, not to leak where they differ.
+/// # Arguments
+/// * `key` - long-term secret key the tag was produced with
+/// * `nonce` - nonce the tag was produced with
+/// * `bytes` - authenticated bytes
+/// * `tag` - tag to check
+pub fn verify(key: &[u8; 32], nonce: &[u8; 12], bytes: &[u8], tag: &[u8; 16]) -> bool {
+    mac(key, nonce, bytes)
+        .iter()
+        .zip(tag.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b))
+        == 0
+}
+
+/// One-time Poly1305 key of a nonce, RFC 8439 section 2.6
+fn one_time_key(key: &[u8; 32], nonce: &[u8; 12]) -> [u8; 32] {
+    let mut one_time_key = [0; 32];
+    ChaCha20::new(key.into(), nonce.into()).apply_keystream(&mut one_time_key);
+    one_time_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn bytes<const N: usize>(hex: &str) -> [u8; N] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn key_generation() {
+        // RFC 8439 section 2.6.2
+        let key = bytes("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f");
+        let nonce = bytes("000000000001020304050607");
+        assert_eq!(
+            hex::encode(one_time_key(&key, &nonce)),
+            "8ad5a08b905f81cc815040274ab29471a833b637e3fd0da508dbb8e2fdd1a646"
+        );
+    }
+
+    #[test]
+    fn known_answers() {
+        // RFC 8439 appendix A.3, test vector 2, with a key and nonce whose
+        // one-time key has an all-zero r
+        let one_time_key: [u8; 32] =
+            bytes("0000000000000000000000000000000036e5f6b5c5e06070f0efca96227a863e");
+        let text = b"Any submission to the IETF intended by the Contributor for publication as all or part of an IETF Internet-Draft or RFC and any statement made within the context of an IETF activity is considered an \"IETF Contribution\". Such statements include oral statements in IETF sessions, as well as written and electronic communications made at any time or place, which are addressed to";
+        assert_eq!(
+            hex::encode(Poly1305::new(&one_time_key.into()).compute_unpadded(text)),
+            "36e5f6b5c5e06070f0efca96227a863e"
+        );
+        // RFC 8439 section 2.5.2
+        let one_time_key: [u8; 32] =
+            bytes("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        assert_eq!(
+            hex::encode(
+                Poly1305::new(&one_time_key.into())
+                    .compute_unpadded(b"Cryptographic Forum Research Group")
+            ),
+            "a8061dc1305136c6c22b8baf0c0127a9"
+        );
+    }
+
+    #[test]
+    fn tags_are_bound_to_key_nonce_and_bytes() {
+        let key = [7; 32];
+        let nonce = [1; 12];
+        let tag = mac(&key, &nonce, b"chunk");
+        // The same message under the same key and nonce gets the same tag
+        assert_eq!(mac(&key, &nonce, b"chunk"), tag);
+        assert!(verify(&key, &nonce, b"chunk", &tag));
+        assert!(!verify(&key, &nonce, b"chunk!", &tag));
+        assert!(!verify(&key, &[2; 12], b"chunk", &tag));
+        assert!(!verify(&[8; 32], &nonce, b"chunk", &tag));
+        assert_ne!(mac(&key, &[2; 12], b"chunk"), tag);
+    }
+}
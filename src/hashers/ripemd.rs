@@ -0,0 +1,36 @@
+//! RIPEMD-160 hasher, for compatibility with existing chunk catalogs
+use super::digest::DigestHasher;
+
+/// RIPEMD-160 hasher, backed by the RustCrypto implementation
+pub type Ripemd160Hasher = DigestHasher<::ripemd160::Ripemd160>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::Hasher;
+    use hex::encode;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| ((i * 7 + 3) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn known_answers() {
+        assert_eq!(
+            encode(Ripemd160Hasher::hash_bytes(b"")),
+            "9c1185a5c5e9fc54612808977ee8f548b2258d31"
+        );
+        assert_eq!(
+            encode(Ripemd160Hasher::hash_bytes(b"abc")),
+            "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"
+        );
+        assert_eq!(
+            encode(Ripemd160Hasher::hash_bytes(&data(64))),
+            "9e25d3bd50070e4e0ae8c8b93db31db2317b9dab"
+        );
+        assert_eq!(
+            encode(Ripemd160Hasher::hash_bytes(&data(1000))),
+            "c69184480d74f42ff96ebee2c29933febf891acb"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! SHAKE128 and SHAKE256 extendable-output functions from FIPS 202
+use super::{Hasher, XofHasher};
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000_0000_0000_0001,
+    0x0000_0000_0000_8082,
+    0x8000_0000_0000_808a,
+    0x8000_0000_8000_8000,
+    0x0000_0000_0000_808b,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8009,
+    0x0000_0000_0000_008a,
+    0x0000_0000_0000_0088,
+    0x0000_0000_8000_8009,
+    0x0000_0000_8000_000a,
+    0x0000_0000_8000_808b,
+    0x8000_0000_0000_008b,
+    0x8000_0000_0000_8089,
+    0x8000_0000_0000_8003,
+    0x8000_0000_0000_8002,
+    0x8000_0000_0000_0080,
+    0x0000_0000_0000_800a,
+    0x8000_0000_8000_000a,
+    0x8000_0000_8000_8081,
+    0x8000_0000_0000_8080,
+    0x0000_0000_8000_0001,
+    0x8000_0000_8000_8008,
+];
+/// Rotation of every lane, indexed by `x + 5 * y`
+const ROTATIONS: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+/// Domain separation and first padding bit of the SHAKE functions
+const SHAKE_PADDING: u8 = 0x1f;
+
+/// SHAKE128 hasher, with a 32 byte digest when used as a fixed size hasher
+pub struct Shake128Hasher;
+
+impl Hasher for Shake128Hasher {
+    const BLOCK_SIZE: usize = 168;
+
+    type Output = [u8; 32];
+
+    fn hash(bytes: &[u8]) -> [u8; 32] {
+        let mut digest = [0; 32];
+        Self::hash_xof(bytes, &mut digest);
+        digest
+    }
+}
+
+impl XofHasher for Shake128Hasher {
+    fn hash_xof(bytes: &[u8], output: &mut [u8]) {
+        sponge::<168>(bytes, output)
+    }
+}
+
+/// SHAKE256 hasher, with a 64 byte digest when used as a fixed size hasher
+pub struct Shake256Hasher;
+
+impl Hasher for Shake256Hasher {
+    const BLOCK_SIZE: usize = 136;
+
+    type Output = [u8; 64];
+
+    fn hash(bytes: &[u8]) -> [u8; 64] {
+        let mut digest = [0; 64];
+        Self::hash_xof(bytes, &mut digest);
+        digest
+    }
+}
+
+impl XofHasher for Shake256Hasher {
+    fn hash_xof(bytes: &[u8], output: &mut [u8]) {
+        sponge::<136>(bytes, output)
+    }
+}
+
+fn keccak_f(state: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS.iter() {
+        // Theta
+        let mut parity = [0; 5];
+        for (x, column) in parity.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let effect = parity[(x + 4) % 5] ^ parity[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= effect;
+            }
+        }
+        // Rho and pi
+        let mut moved = [0; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                moved[y + 5 * ((2 * x + 3 * y) % 5)] =
+                    state[x + 5 * y].rotate_left(ROTATIONS[x + 5 * y]);
+            }
+        }
+        // Chi
+        for y in 0..5 {
+            for x in 0..5 {
+                state[x + 5 * y] =
+                    moved[x + 5 * y] ^ (!moved[(x + 1) % 5 + 5 * y] & moved[(x + 2) % 5 + 5 * y]);
+            }
+        }
+        // Iota
+        state[0] ^= round_constant;
+    }
+}
+
+fn xor_block(state: &mut [u64; 25], block: &[u8]) {
+    for (lane, bytes) in state.iter_mut().zip(block.chunks(8)) {
+        let mut padded = [0; 8];
+        padded[..bytes.len()].copy_from_slice(bytes);
+        *lane ^= u64::from_le_bytes(padded);
+    }
+}
+
+/// Absorb the input with the given rate in bytes and squeeze the output
+fn sponge<const RATE: usize>(input: &[u8], output: &mut [u8]) {
+    let mut state = [0; 25];
+    let mut blocks = input.chunks_exact(RATE);
+    for block in &mut blocks {
+        xor_block(&mut state, block);
+        keccak_f(&mut state);
+    }
+    let remainder = blocks.remainder();
+    let mut last = [0; RATE];
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[remainder.len()] ^= SHAKE_PADDING;
+    last[RATE - 1] ^= 0x80;
+    xor_block(&mut state, &last);
+    for block in output.chunks_mut(RATE) {
+        keccak_f(&mut state);
+        for (bytes, lane) in block.chunks_mut(8).zip(state.iter()) {
+            bytes.copy_from_slice(&lane.to_le_bytes()[..bytes.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xof<H: XofHasher>(bytes: &[u8], length: usize) -> String {
+        let mut output = vec![0; length];
+        H::hash_xof(bytes, &mut output);
+        hex::encode(output)
+    }
+
+    #[test]
+    fn known_answers() {
+        assert_eq!(
+            xof::<Shake128Hasher>(b"", 32),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+        assert_eq!(
+            hex::encode(Shake256Hasher::hash(b"")),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be"
+        );
+        // Inputs filling a whole block, and outputs longer than a block
+        assert_eq!(
+            xof::<Shake128Hasher>(&[b'x'; 168], 16),
+            "6dcbd4e3c171a95da0e9f51875f647a1"
+        );
+        assert_eq!(
+            xof::<Shake256Hasher>(&[b'x'; 136], 16),
+            "7614c58639bf53a94aab54261d1f9b26"
+        );
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        assert!(
+            xof::<Shake128Hasher>(&data, 200).ends_with("07a8d14285e2ab4f96a07f13312d73f25c0b28a4")
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! SipHash-2-4, a fast keyed short-tag hasher for in-process chunk integrity
+//! checks, such as of cache entries, backed by the `siphasher` crate
+use super::KeyedHasher;
+use std::hash::Hasher;
+
+/// SipHash-2-4 keyed hasher with an 8 byte tag
+pub struct SipHasher24 {
+    key: [u8; 16],
+}
+
+impl SipHasher24 {
+    /// Instantiate a SipHash-2-4 hasher
+    /// # Arguments
+    /// * `key` - secret key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::hashers::{siphash::SipHasher24, KeyedHasher};
+    /// let hasher = SipHasher24::new(b"sixteen byte key");
+    /// assert_eq!(hasher.hash_keyed(b"chunk").len(), 8);
+    /// ```
+    pub fn new(key: &[u8; 16]) -> Self {
+        Self { key: *key }
+    }
+}
+
+impl KeyedHasher for SipHasher24 {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut hasher = siphasher::sip::SipHasher24::new_with_key(&self.key);
+        hasher.write(bytes);
+        hasher.finish().to_le_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn reference_vectors() {
+        // Key and inputs of the reference implementation test vectors
+        let key: Vec<u8> = (0..16).collect();
+        let hasher = SipHasher24::new(key[..].try_into().unwrap());
+        let input: Vec<u8> = (0..15).collect();
+        assert_eq!(hex::encode(hasher.hash_keyed(&[])), "310e0edd47db6f72");
+        assert_eq!(hex::encode(hasher.hash_keyed(&input)), "e545be4961ca29a1");
+        assert_eq!(
+            hex::encode(hasher.hash_keyed(&input[..8])),
+            "6224939a79f5f593"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! SM3 hasher (GB/T 32905-2016)
+use super::digest::DigestHasher;
+
+/// SM3 hasher with a 32 byte digest, backed by the RustCrypto
+/// implementation
+pub type Sm3Hasher = DigestHasher<::sm3::Sm3>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::Hasher;
+    use hex::encode;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| ((i * 7 + 3) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn known_answers() {
+        assert_eq!(
+            encode(Sm3Hasher::hash_bytes(b"")),
+            "1ab21d8355cfa17f8e61194831e81a8f22bec8c728fefb747ed035eb5082aa2b"
+        );
+        assert_eq!(
+            encode(Sm3Hasher::hash_bytes(b"abc")),
+            "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"
+        );
+        assert_eq!(
+            encode(Sm3Hasher::hash_bytes(&data(56))),
+            "28a02b6882c7d81162c83e3e5554fa90f291263c6b47135bcff6a8b87c11f772"
+        );
+        assert_eq!(
+            encode(Sm3Hasher::hash_bytes(&data(1000))),
+            "b1fe453124186cf0f8a989dc8c3b4aae0b756ca378010f32ab7382da53e628d1"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Whirlpool hasher, for compatibility with existing chunk catalogs
+use super::digest::DigestHasher;
+
+/// Whirlpool hasher with a 64 byte digest, backed by the RustCrypto
+/// implementation
+pub type WhirlpoolHasher = DigestHasher<::whirlpool::Whirlpool>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::Hasher;
+    use hex::encode;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| ((i * 7 + 3) % 251) as u8).collect()
+    }
+
+    #[test]
+    fn known_answers() {
+        assert_eq!(
+            encode(WhirlpoolHasher::hash_bytes(b"")),
+            "19fa61d75522a4669b44e39c1d2e1726c530232130d407f89afee0964997f7a7\
+             3e83be698b288febcf88e3e03c4f0757ea8964e59b63d93708b138cc42a66eb3"
+        );
+        assert_eq!(
+            encode(WhirlpoolHasher::hash_bytes(b"abc")),
+            "4e2448a4c6f486bb16b6562c73b4020bf3043e3a731bce721ae1b303d97e6d4c\
+             7181eebdb6c57e277d0e34957114cbd6c797fc9d95d8b582d225292076d4eef5"
+        );
+        assert_eq!(
+            encode(WhirlpoolHasher::hash_bytes(&data(32))),
+            "c7faf637a891dcc975d06d72ac3b11af5a31a894fe338ba86dbf0b29667e9737\
+             155c58b51bbd826b811f9614b200d014eb02f82ea37a4877a0d7571e65f38455"
+        );
+        assert_eq!(
+            encode(WhirlpoolHasher::hash_bytes(&data(1000))),
+            "cfe7ee93197f98ae66a9176eeb3399ecb093cd86c01883354c0b07f6c7706bfe\
+             e411f2bcb33a3eaeed0d7f2bdda93d5efadc4a229d196a97e142907d55b995ca"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Extendable-output functions with a caller selected output length, such
+//! as 16 byte or 64 byte chunk tags from the same primitive
+use super::{KeyedHasher, XofHasher};
+use anyhow::{ensure, Result};
+use std::marker::PhantomData;
+
+/// Hasher producing digests of a fixed, caller selected length with the
+/// extendable-output function `H`
+pub struct Xof<H> {
+    length: usize,
+    _marker: PhantomData<H>,
+}
+
+impl<H: XofHasher> Xof<H> {
+    /// Instantiate an extendable-output hasher
+    /// # Arguments
+    /// * `length` - amount of digest bytes to produce for every chunk
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::{shake::Shake256Hasher, xof::Xof}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Shake256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_keyed_hasher(Xof::<Shake256Hasher>::new(16)?)
+    ///         .collect();
+    /// assert!(chunks.iter().all(|chunk| chunk.hash.len() == 16));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(length: usize) -> Result<Self> {
+        ensure!(length > 0, "Output length must be greater than zero");
+        Ok(Self {
+            length,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Amount of digest bytes produced for every chunk
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+impl<H: XofHasher> KeyedHasher for Xof<H> {
+    fn hash_keyed(&self, bytes: &[u8]) -> Vec<u8> {
+        let mut digest = vec![0; self.length];
+        H::hash_xof(bytes, &mut digest);
+        digest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::{blake3::Blake3Hasher, shake::Shake128Hasher, Hasher};
+
+    #[test]
+    fn shorter_outputs_are_prefixes() -> Result<()> {
+        let short = Xof::<Shake128Hasher>::new(16)?.hash_keyed(b"chunk");
+        let long = Xof::<Shake128Hasher>::new(64)?.hash_keyed(b"chunk");
+        assert_eq!(short[..], long[..16]);
+        assert_eq!(
+            Xof::<Blake3Hasher>::new(32)?.hash_keyed(b"chunk"),
+            Blake3Hasher::hash_bytes(b"chunk")
+        );
+        assert!(Xof::<Blake3Hasher>::new(0).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! xxHash non-cryptographic hashers
+//!
+//! Much faster than the cryptographic hashers, for deduplication and change
+//! detection where inputs aren't adversarial. Digests are 8 bytes in the
+//! canonical (big endian) xxHash representation. Backed by the `xxhash-rust`
+//! crate.
+use super::Hasher;
+use xxhash_rust::{xxh3::xxh3_64, xxh64::xxh64};
+
+/// XXH64 hasher with a zero seed
+pub struct Xxh64Hasher;
+
+impl Hasher for Xxh64Hasher {
+    const BLOCK_SIZE: usize = 32;
+
+    type Output = [u8; 8];
+
+    fn hash(bytes: &[u8]) -> [u8; 8] {
+        xxh64(bytes, 0).to_be_bytes()
+    }
+}
+
+/// XXH3 64-bit hasher with the default secret and a zero seed
+pub struct Xxh3Hasher;
+
+impl Hasher for Xxh3Hasher {
+    const BLOCK_SIZE: usize = 64;
+
+    type Output = [u8; 8];
+
+    fn hash(bytes: &[u8]) -> [u8; 8] {
+        xxh3_64(bytes).to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(length: usize) -> Vec<u8> {
+        (0..length).map(|i| ((i * 7 + 3) % 251) as u8).collect()
+    }
+
+    // Reference values computed with libxxhash 0.8.1 over `data(length)`
+    const KNOWN_ANSWERS: [(usize, u64, u64); 18] = [
+        (0, 0xef46db3751d8e999, 0x2d06800538d394c2),
+        (1, 0x1f25c8d0bc1f4bb6, 0x13e608bc156defed),
+        (3, 0x31d2363f52e564c9, 0xa9088dda485b481c),
+        (4, 0x9bb64b7d66ee9fda, 0x6d9253b16c8b1ed3),
+        (8, 0xdab99d95c6f90092, 0x60539db630471163),
+        (9, 0x170bb6bf975b4c02, 0xfeff668361d723a8),
+        (16, 0x434850232b787be2, 0xb8c859b0f030b585),
+        (17, 0x1efa7025f1b97a7a, 0x714a04408e79b80f),
+        (100, 0x778e26df8290f456, 0x0de27c6732e616cb),
+        (128, 0xa0a0ae06f88ab606, 0x4634ae6a253a60e4),
+        (129, 0x8ed7d1d815851679, 0xc095b9b1b087722d),
+        (200, 0x1852812fa0b23ef3, 0xa369f2930049476f),
+        (240, 0x3e675f9e43d97699, 0x887af00281f75d38),
+        (241, 0xc2d85dd2fcc8dc06, 0x82b1de299f6e411e),
+        (1000, 0x021f7a7424085ea4, 0xd45f5a87c5c20462),
+        (1024, 0xbcc14bbbbb35ede2, 0xf75e768c7cdd54b2),
+        (2048, 0x997b5b566a535621, 0x9e5e4a8160109a5d),
+        (5000, 0x07d6f0f382184d67, 0x8e5898f51713d386),
+    ];
+
+    #[test]
+    fn known_answers() {
+        for (length, xxh64_answer, xxh3_answer) in KNOWN_ANSWERS.iter() {
+            let input = data(*length);
+            assert_eq!(
+                Xxh64Hasher::hash(&input),
+                xxh64_answer.to_be_bytes(),
+                "XXH64 of {} bytes",
+                length
+            );
+            assert_eq!(
+                Xxh3Hasher::hash(&input),
+                xxh3_answer.to_be_bytes(),
+                "XXH3 of {} bytes",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn canonical_digests() {
+        assert_eq!(
+            hex::encode(Xxh64Hasher::hash_bytes(b"")),
+            "ef46db3751d8e999"
+        );
+        assert_eq!(hex::encode(Xxh3Hasher::hash_bytes(b"")), "2d06800538d394c2");
+    }
+}
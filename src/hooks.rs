@@ -0,0 +1,197 @@
+//! Per-chunk hooks observing a chunked hasher
+//!
+//! A hook sees the data of every chunk read from the stream, the time reading
+//! and hashing it took and the produced chunk, without changing any of them.
+//! Dictionary sampling, slow chunk detection and chunk subscribers are all
+//! hooks, and any amount of hooks can be registered with
+//! [`ChunkedHasher::with_hook`](../struct.ChunkedHasher.html#method.with_hook).
+
+use crate::{dictionary::DictionarySamples, slow::SlowChunkDetector, Chunk, ChunkRef};
+use std::time::Duration;
+
+/// Observer of the chunks of a chunked hasher, every method does nothing by
+/// default
+pub trait ChunkHook {
+    /// Whether the hook needs the whole data of every chunk at once, which
+    /// keeps chunks from being fed to the hasher in increments of a read
+    /// buffer
+    fn needs_data(&self) -> bool {
+        false
+    }
+
+    /// Called with the data of every chunk read from the stream, without
+    /// padding, when [`needs_data`](#method.needs_data) is set
+    /// # Arguments
+    /// * `data` - data of the chunk
+    fn data(&mut self, _data: &[u8]) {}
+
+    /// Called with the time a chunk took to process
+    /// # Arguments
+    /// * `chunk` - position of the chunk
+    /// * `read` - time seeking to and reading the chunk took
+    /// * `hash` - time hashing the chunk took
+    fn timing(&mut self, _chunk: ChunkRef, _read: Duration, _hash: Duration) {}
+
+    /// Called with every chunk, before the iterator returns it
+    /// # Arguments
+    /// * `chunk` - produced chunk
+    fn chunk(&mut self, _chunk: &Chunk) {}
+}
+
+impl<K: ChunkHook + ?Sized> ChunkHook for &mut K {
+    fn needs_data(&self) -> bool {
+        (**self).needs_data()
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        (**self).data(data)
+    }
+
+    fn timing(&mut self, chunk: ChunkRef, read: Duration, hash: Duration) {
+        (**self).timing(chunk, read, hash)
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) {
+        (**self).chunk(chunk)
+    }
+}
+
+/// Hooks of a chunked hasher, called in registration order
+#[derive(Default)]
+pub(crate) struct Hooks<'a> {
+    hooks: Vec<Box<dyn ChunkHook + 'a>>,
+}
+
+impl<'a> Hooks<'a> {
+    /// Register a hook after the ones registered so far
+    pub(crate) fn push(&mut self, hook: impl ChunkHook + 'a) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Whether no hook is registered
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+}
+
+impl ChunkHook for Hooks<'_> {
+    fn needs_data(&self) -> bool {
+        self.hooks.iter().any(|hook| hook.needs_data())
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        for hook in self.hooks.iter_mut().filter(|hook| hook.needs_data()) {
+            hook.data(data);
+        }
+    }
+
+    fn timing(&mut self, chunk: ChunkRef, read: Duration, hash: Duration) {
+        for hook in self.hooks.iter_mut() {
+            hook.timing(chunk, read, hash);
+        }
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) {
+        for hook in self.hooks.iter_mut() {
+            hook.chunk(chunk);
+        }
+    }
+}
+
+impl ChunkHook for DictionarySamples {
+    fn needs_data(&self) -> bool {
+        true
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.offer(data);
+    }
+}
+
+impl ChunkHook for SlowChunkDetector<'_> {
+    fn timing(&mut self, chunk: ChunkRef, read: Duration, hash: Duration) {
+        self.record(chunk, read, hash);
+    }
+}
+
+/// Hook calling a closure with every chunk
+pub(crate) struct Subscriber<F>(pub(crate) F);
+
+impl<F: FnMut(&Chunk)> ChunkHook for Subscriber<F> {
+    fn chunk(&mut self, chunk: &Chunk) {
+        (self.0)(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    /// Records everything it's called with
+    #[derive(Default)]
+    struct Recorder {
+        needs_data: bool,
+        data: Vec<Vec<u8>>,
+        timings: Vec<u64>,
+        chunks: Vec<u64>,
+    }
+
+    impl ChunkHook for Recorder {
+        fn needs_data(&self) -> bool {
+            self.needs_data
+        }
+
+        fn data(&mut self, data: &[u8]) {
+            self.data.push(data.to_vec());
+        }
+
+        fn timing(&mut self, chunk: ChunkRef, _read: Duration, _hash: Duration) {
+            self.timings.push(chunk.index);
+        }
+
+        fn chunk(&mut self, chunk: &Chunk) {
+            self.chunks.push(chunk.index);
+        }
+    }
+
+    #[test]
+    fn hooks_compose() -> anyhow::Result<()> {
+        let mut observer = Recorder::default();
+        let mut sampler = Recorder {
+            needs_data: true,
+            ..Recorder::default()
+        };
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let streamed: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 20)?
+                .with_read_buffer(8)
+                .with_hook(&mut observer)
+                .with_hook(&mut sampler)
+                .collect();
+        // Only hooks needing the data get it, whole
+        assert!(observer.data.is_empty());
+        assert_eq!(
+            sampler.data,
+            vec![
+                DATA[..20].to_vec(),
+                DATA[20..40].to_vec(),
+                DATA[40..].to_vec()
+            ]
+        );
+        for recorder in [&observer, &sampler].iter() {
+            assert_eq!(recorder.timings, vec![0, 1, 2]);
+            assert_eq!(recorder.chunks, vec![0, 1, 2]);
+        }
+        // Reading whole chunks for a hook doesn't change the hashes
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let whole: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 20)?
+                .collect();
+        assert_eq!(streamed, whole);
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Offset and length arithmetic
+//!
+//! All chunk layout math lives here so it can be audited in one place. Every
+//! operation is checked or saturating, and stays correct for the boundary
+//! values of `u64`.
+
+/// Chunk size to use for a fixed chunk request, never larger than the stream
+/// # Arguments
+/// * `requested` - requested chunk size
+/// * `stream_size` - total stream size
+pub(crate) fn clamp_chunk_size(requested: u64, stream_size: u64) -> u64 {
+    requested.min(stream_size)
+}
+
+/// Chunk size splitting the stream into `amount` equally sized chunks, the
+/// remainder of the division ends up in its own chunk
+/// # Arguments
+/// * `stream_size` - total stream size
+/// * `amount` - amount of chunks to split into, must be greater than zero
+pub(crate) fn dynamic_chunk_size(stream_size: u64, amount: u64) -> u64 {
+    if amount <= stream_size {
+        stream_size / amount
+    } else {
+        stream_size
+    }
+}
+
+/// Smallest multiple of `alignment` not below `size`, saturating at the
+/// largest multiple representable
+/// # Arguments
+/// * `size` - size to align
+/// * `alignment` - block size to align to, must be greater than zero
+pub(crate) fn align_up(size: u64, alignment: u64) -> u64 {
+    match size % alignment {
+        0 => size,
+        remainder => size
+            .checked_add(alignment - remainder)
+            .unwrap_or(u64::MAX - u64::MAX % alignment),
+    }
+}
+
+/// Smallest BitTorrent style piece size
+pub(crate) const MIN_PIECE_SIZE: u64 = 16 * 1024;
+/// Largest BitTorrent style piece size
+pub(crate) const MAX_PIECE_SIZE: u64 = 16 * 1024 * 1024;
+/// Amount of pieces the piece size is picked to stay within
+pub(crate) const MAX_PIECES: u64 = 2000;
+
+/// BitTorrent style piece size, the smallest power of two from 16 KiB to
+/// 16 MiB splitting the stream into at most 2000 pieces, which makes for
+/// 1000 to 2000 pieces unless the piece size hits one of its limits
+/// # Arguments
+/// * `stream_size` - total stream size
+pub(crate) fn piece_size(stream_size: u64) -> u64 {
+    let mut piece_size = MIN_PIECE_SIZE;
+    while piece_size < MAX_PIECE_SIZE
+        && chunk_count(stream_size, piece_size, piece_size) > MAX_PIECES
+    {
+        piece_size *= 2;
+    }
+    piece_size
+}
+
+/// Amount of chunks produced when windows of `chunk_size` bytes start every
+/// `stride` bytes, the last window ending at the end of the stream
+/// # Arguments
+/// * `stream_size` - total stream size
+/// * `chunk_size` - size of each chunk, must be greater than zero
+/// * `stride` - distance between chunk starts, must be greater than zero
+pub(crate) fn chunk_count(stream_size: u64, chunk_size: u64, stride: u64) -> u64 {
+    if stream_size == 0 {
+        return 0;
+    }
+    if stream_size <= chunk_size {
+        return 1;
+    }
+    let tail = stream_size - chunk_size;
+    match tail % stride {
+        0 => tail / stride + 1,
+        _ => tail / stride + 2,
+    }
+}
+
+/// Byte offset of the chunk with the given index, `None` if it is not
+/// representable
+/// # Arguments
+/// * `index` - chunk index
+/// * `stride` - distance between chunk starts
+pub(crate) fn chunk_offset(index: u64, stride: u64) -> Option<u64> {
+    index.checked_mul(stride)
+}
+
+/// Byte offset right after a chunk, `None` if it is not representable
+/// # Arguments
+/// * `offset` - start of the chunk
+/// * `size` - size of the chunk
+pub(crate) fn chunk_end(offset: u64, size: u64) -> Option<u64> {
+    offset.checked_add(size)
+}
+
+/// Amount of zeros a chunk is hashed padded with to reach the padded size,
+/// only a chunk past the start of the stream and holding data is padded
+/// # Arguments
+/// * `offset` - start of the chunk
+/// * `size` - size of the chunk data
+/// * `padded_size` - size to pad to, zero to not pad
+pub(crate) fn padding(offset: u64, size: u64, padded_size: u64) -> u64 {
+    if offset == 0 || size == 0 {
+        0
+    } else {
+        padded_size.saturating_sub(size)
+    }
+}
+
+/// Amount of bytes from `start` up to `end`, zero if `end` isn't past `start`
+/// # Arguments
+/// * `start` - first offset
+/// * `end` - offset right after the last byte
+pub(crate) fn distance(start: u64, end: u64) -> u64 {
+    end.saturating_sub(start)
+}
+
+/// Index of the first chunk ending past `offset`, the first chunk
+/// overlapping a range starting there
+/// # Arguments
+/// * `offset` - start of the range
+/// * `chunk_size` - size of each chunk
+/// * `stride` - distance between chunk starts, must be greater than zero
+pub(crate) fn first_chunk_ending_after(offset: u64, chunk_size: u64, stride: u64) -> u64 {
+    match offset.checked_sub(chunk_size) {
+        Some(tail) => tail / stride + 1,
+        None => 0,
+    }
+}
+
+/// Amount of bytes to read for a chunk starting at `offset`, never reaching
+/// past the end of the stream
+/// # Arguments
+/// * `offset` - start of the chunk
+/// * `chunk_size` - size of each chunk
+/// * `stream_size` - total stream size
+pub(crate) fn read_length(offset: u64, chunk_size: u64, stream_size: u64) -> u64 {
+    chunk_size.min(stream_size.saturating_sub(offset))
+}
+
+/// Whether the chunk with the given index lies past the end of the stream,
+/// either because it starts there or because the previous (overlapping)
+/// chunk already reached the end
+/// # Arguments
+/// * `index` - chunk index
+/// * `chunk_size` - size of each chunk
+/// * `stride` - distance between chunk starts
+/// * `stream_size` - total stream size
+pub(crate) fn is_exhausted(index: u64, chunk_size: u64, stride: u64, stream_size: u64) -> bool {
+    match chunk_offset(index, stride) {
+        Some(offset) if offset < stream_size => {}
+        _ => return true,
+    }
+    if index == 0 {
+        return false;
+    }
+    // The previous offset is representable as the current one is
+    let previous_offset = (index - 1) * stride;
+    match previous_offset.checked_add(chunk_size) {
+        Some(previous_end) => previous_end >= stream_size,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_sizes() {
+        assert_eq!(clamp_chunk_size(40, 480), 40);
+        assert_eq!(clamp_chunk_size(u64::MAX, 480), 480);
+        assert_eq!(dynamic_chunk_size(480, 12), 40);
+        assert_eq!(dynamic_chunk_size(45, 4), 11);
+        assert_eq!(dynamic_chunk_size(3, 4), 3);
+        assert_eq!(dynamic_chunk_size(u64::MAX, 1), u64::MAX);
+        assert_eq!(dynamic_chunk_size(u64::MAX, u64::MAX), 1);
+    }
+
+    #[test]
+    fn ends_and_distances() {
+        assert_eq!(chunk_end(10, 5), Some(15));
+        assert_eq!(chunk_end(u64::MAX, 1), None);
+        assert_eq!(distance(10, 15), 5);
+        assert_eq!(distance(15, 10), 0);
+        assert_eq!(first_chunk_ending_after(0, 10, 10), 0);
+        assert_eq!(first_chunk_ending_after(9, 10, 10), 0);
+        assert_eq!(first_chunk_ending_after(10, 10, 10), 1);
+        assert_eq!(first_chunk_ending_after(12, 10, 5), 1);
+        assert_eq!(first_chunk_ending_after(u64::MAX, u64::MAX, 1), 1);
+    }
+
+    #[test]
+    fn aligned_sizes() {
+        assert_eq!(align_up(0, 4096), 0);
+        assert_eq!(align_up(1, 4096), 4096);
+        assert_eq!(align_up(8192, 4096), 8192);
+        assert_eq!(align_up(u64::MAX, 1), u64::MAX);
+        assert_eq!(align_up(u64::MAX, 4096), u64::MAX - 4095);
+    }
+
+    #[test]
+    fn piece_sizes() {
+        assert_eq!(piece_size(0), MIN_PIECE_SIZE);
+        assert_eq!(piece_size(2000 * MIN_PIECE_SIZE), MIN_PIECE_SIZE);
+        assert_eq!(piece_size(2000 * MIN_PIECE_SIZE + 1), 2 * MIN_PIECE_SIZE);
+        let size = 4 * 1024 * 1024 * 1024;
+        assert_eq!(piece_size(size), 4 * 1024 * 1024);
+        assert!((1000..=2000).contains(&(size / piece_size(size))));
+        assert_eq!(piece_size(u64::MAX), MAX_PIECE_SIZE);
+    }
+
+    #[test]
+    fn chunk_counts() {
+        assert_eq!(chunk_count(0, 1, 1), 0);
+        assert_eq!(chunk_count(480, 40, 40), 12);
+        assert_eq!(chunk_count(45, 11, 11), 5);
+        assert_eq!(chunk_count(40, 10, 5), 7);
+        assert_eq!(chunk_count(10, 40, 40), 1);
+        assert_eq!(chunk_count(u64::MAX, 1, 1), u64::MAX);
+        assert_eq!(chunk_count(u64::MAX, u64::MAX, u64::MAX), 1);
+        assert_eq!(chunk_count(u64::MAX, 2, 2), u64::MAX / 2 + 1);
+        assert_eq!(chunk_count(u64::MAX, u64::MAX - 1, 1), 2);
+    }
+
+    #[test]
+    fn offsets_and_lengths() {
+        assert_eq!(chunk_offset(3, 40), Some(120));
+        assert_eq!(chunk_offset(u64::MAX, 1), Some(u64::MAX));
+        assert_eq!(chunk_offset(u64::MAX, 2), None);
+        assert_eq!(read_length(440, 40, 450), 10);
+        assert_eq!(read_length(460, 40, 450), 0);
+        assert_eq!(read_length(u64::MAX - 1, u64::MAX, u64::MAX), 1);
+    }
+
+    #[test]
+    fn exhaustion() {
+        assert!(!is_exhausted(0, 40, 40, 480));
+        assert!(!is_exhausted(11, 40, 40, 480));
+        assert!(is_exhausted(12, 40, 40, 480));
+        assert!(!is_exhausted(6, 10, 5, 40));
+        assert!(is_exhausted(7, 10, 5, 40));
+        assert!(!is_exhausted(u64::MAX - 1, 1, 1, u64::MAX));
+        assert!(is_exhausted(u64::MAX, 1, 1, u64::MAX));
+        assert!(is_exhausted(2, u64::MAX, u64::MAX, u64::MAX));
+        assert!(!is_exhausted(1, u64::MAX - 1, 1, u64::MAX));
+        assert!(is_exhausted(2, u64::MAX - 1, 1, u64::MAX));
+    }
+}
@@ -1,40 +1,153 @@
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
+use hooks::ChunkHook;
 use std::{
-    io::{Read, Seek, SeekFrom},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
     iter::Iterator,
     marker::PhantomData,
+    time::{Duration, Instant},
 };
+pub mod allocation;
+pub mod append_only;
+pub mod cdc;
+pub mod cluster;
+mod config;
+pub mod convergent;
+pub mod dictionary;
+pub mod domain;
+pub mod download;
+pub mod edit;
+pub mod file;
+pub mod fixed;
+pub mod framing;
 pub mod hashers;
+pub mod hooks;
+mod layout;
+pub mod manifest_log;
+pub mod memory;
+pub mod merkle;
+pub mod mmr;
+pub mod multihash;
+#[cfg(feature = "paranoid")]
+mod paranoid;
+pub mod rabin;
+pub mod ranges;
+pub mod reconstruct;
+pub mod replicas;
+pub mod report;
+pub mod scope;
+pub mod self_test;
+pub mod similarity;
+pub mod sketch;
+pub mod slow;
+pub mod sources;
+pub mod strategy;
+pub mod summary;
+pub mod verify;
 
 /// Combination trait of Read + Seek
 pub trait ReadAndSeek: Read + Seek {}
-impl<'a, T: Read + Seek> ReadAndSeek for T {}
+impl<T: Read + Seek> ReadAndSeek for T {}
+
+/// Size of the stream to hash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamSize {
+    /// Size is known up front, as neither Read nor Seek implements the
+    /// ability to get the full size this is a hint given by the caller
+    Known(u64),
+    /// Size is unknown, the stream is read sequentially until EOF
+    Unknown,
+}
+
+impl From<u64> for StreamSize {
+    fn from(size: u64) -> Self {
+        StreamSize::Known(size)
+    }
+}
+
+/// Chunk layout, for choosing between the chunked hasher constructors at
+/// runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chunking {
+    /// Fixed size chunks, see [`ChunkedHasher::fixed_chunks`](struct.ChunkedHasher.html#method.fixed_chunks)
+    Fixed(u64),
+    /// Given amount of chunks, see [`ChunkedHasher::dynamic_chunks`](struct.ChunkedHasher.html#method.dynamic_chunks)
+    Dynamic(u64),
+    /// Overlapping windows, see [`ChunkedHasher::overlapping_windows`](struct.ChunkedHasher.html#method.overlapping_windows)
+    Overlapping { window_size: u64, stride: u64 },
+}
 
 /// Chunked hasher instance
-pub struct ChunkedHasher<'a, H> {
+pub struct ChunkedHasher<'a, H: hashers::Hasher> {
     /// The buffer we'll iterate over when doing the chunked hashing
     seekable_buffer: &'a mut dyn ReadAndSeek,
-    /// Size of the chunks to use per read cycle
-    chunk_size: u64,
+    /// Where chunks start and end
+    layout: config::ChunkLayout,
+    /// How chunk data is hashed
+    hashing: config::HashConfig<'a>,
+    /// Observers of every chunk, in registration order
+    hooks: hooks::Hooks<'a>,
     /// Next chunk index to process
     next_chunk: u64,
     /// How much data we've read so far
     read_data: u64,
-    // Hint pertaining to the total stream size
-    stream_size: u64,
+    /// Position of the buffer when reading sequentially
+    position: u64,
+    /// Whether the end of the stream was reached before the size hint
+    finished: bool,
+    /// Read buffer and hashing function when chunks are fed to the hasher
+    /// in increments
+    streaming: Option<(Vec<u8>, StreamChunk<H::Output>)>,
+    /// Boundary selection and the data read past the last boundary, when
+    /// chunks are selected by a strategy
+    content_defined: Option<(Box<dyn strategy::ChunkingStrategy + 'a>, Vec<u8>)>,
+    /// Hash of the last chunk of zeros along with the padding it was hashed
+    /// with, reused for holes of the same size and padding
+    zero_chunk: Option<(u64, Hashed<H::Output>)>,
+    /// Chunk accounting checked so far
+    #[cfg(feature = "paranoid")]
+    invariants: paranoid::Invariants,
     _marker: PhantomData<H>,
 }
 
 impl<'a, H: hashers::Hasher> ChunkedHasher<'a, H> {
+    fn new(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: StreamSize,
+        chunk_size: u64,
+        stride: u64,
+    ) -> Self {
+        Self {
+            seekable_buffer: buffer,
+            _marker: PhantomData,
+            layout: config::ChunkLayout::new(stream_size, chunk_size, stride),
+            hashing: config::HashConfig::default(),
+            hooks: hooks::Hooks::default(),
+            position: 0,
+            finished: false,
+            streaming: None,
+            content_defined: None,
+            zero_chunk: None,
+            #[cfg(feature = "paranoid")]
+            invariants: paranoid::Invariants::default(),
+            read_data: 0,
+            next_chunk: 0,
+        }
+    }
+
     /// Instantiate a fixed size chunked hasher
     ///
     /// # Arguments
     /// * `buffer` - the buffer to hash
     /// * `stream_size` - as neither Read nor Seek implements the ability to get
-    ///   the full size, we need to give this hint
+    ///   the full size, we need to give this hint, or
+    ///   [`StreamSize::Unknown`](enum.StreamSize.html) to read until EOF
     /// * `fixed_size` - fixed chunk size, the last chunk will contain the
     ///   remainder
     ///
+    /// A short last chunk is hashed zero-padded to the chunk size, unless
+    /// [`unpadded_last_chunk`](#method.unpadded_last_chunk) is set.
+    ///
     /// # Example
     ///
     /// ```
@@ -52,36 +165,929 @@ impl<'a, H: hashers::Hasher> ChunkedHasher<'a, H> {
     /// ```
     pub fn fixed_chunks(
         buffer: &'a mut dyn ReadAndSeek,
-        stream_size: u64,
+        stream_size: impl Into<StreamSize>,
         fixed_size: u64,
     ) -> Result<Self> {
-        ensure!(stream_size > 0, "Stream size must be greater than zero");
         ensure!(fixed_size > 0, "Fixed size must be greater than zero");
 
-        let chunk_size = if fixed_size <= stream_size {
-            fixed_size
-        } else {
-            stream_size
+        let stream_size = stream_size.into();
+        let chunk_size = match stream_size {
+            StreamSize::Known(size) => layout::clamp_chunk_size(fixed_size, size),
+            StreamSize::Unknown => fixed_size,
         };
 
-        Ok(Self {
-            seekable_buffer: buffer,
-            _marker: PhantomData,
+        Ok(Self::new(buffer, stream_size, chunk_size, chunk_size))
+    }
+
+    /// Instantiate a fixed size chunked hasher picking the chunk size like
+    /// BitTorrent picks its piece size, the smallest power of two from 16 KiB
+    /// to 16 MiB that splits the stream into at most 2000 pieces
+    ///
+    /// The picked size is available through
+    /// [`chunk_size`](#method.chunk_size), it is clamped to the stream size
+    /// for streams smaller than a piece.
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - as neither Read nor Seek implements the ability to get
+    ///   the full size, we need to give this hint, it can't be unknown
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// let data = vec![0u8; 64 * 1024 * 1024];
+    /// let mut buffer: Cursor<&[u8]> = Cursor::new(&data);
+    /// let hasher = ChunkedHasher::<Sha256Hasher>::piece_chunks(&mut buffer, data.len() as u64)?;
+    /// assert_eq!(hasher.chunk_size(), 64 * 1024);
+    /// assert_eq!(hasher.chunk_count(), Some(1024));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn piece_chunks(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+    ) -> Result<Self> {
+        let stream_size = match stream_size.into() {
+            StreamSize::Known(size) => size,
+            StreamSize::Unknown => bail!("Piece chunks require a known stream size"),
+        };
+        Self::fixed_chunks(buffer, stream_size, layout::piece_size(stream_size))
+    }
+
+    /// Instantiate a fixed size chunked hasher for a sparse file, chunks
+    /// entirely within a hole of the file are hashed as zeros without
+    /// reading them, see [`file::data_regions`](file/fn.data_regions.html)
+    ///
+    /// The chunks are the same as those of
+    /// [`fixed_chunks`](#method.fixed_chunks) over the whole file.
+    ///
+    /// # Arguments
+    /// * `file` - the file to hash
+    /// * `fixed_size` - fixed chunk size, the last chunk will contain the
+    ///   remainder
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # let path = concat!(env!("CARGO_MANIFEST_DIR"), "/test-data/original.txt");
+    /// let mut file = std::fs::File::open(path)?;
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::sparse_file_chunks(&mut file, 40)?.collect();
+    /// assert_eq!(chunks.len(), 12);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sparse_file_chunks(file: &'a mut File, fixed_size: u64) -> Result<Self> {
+        let stream_size = file::stream_size(file)?;
+        // Files that can't be seeked have no holes to skip
+        if stream_size == StreamSize::Unknown {
+            return Self::fixed_chunks(file, stream_size, fixed_size);
+        }
+        let regions = file::data_regions(file)?;
+        let mut chunked_hasher = Self::fixed_chunks(file, stream_size, fixed_size)?;
+        chunked_hasher.layout.data_regions = Some(regions);
+        Ok(chunked_hasher)
+    }
+
+    /// Instantiate a dynamic size chunked hasher
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - as neither Read nor Seek implements the ability to get
+    ///   the full size, we need to give this hint, it can't be unknown
+    /// * `dynamic_amount` - amount of chunks to chunk into, if it's not
+    ///   perfectly divisible the remainder will be in its own chunk
+    ///
+    /// As with [`fixed_chunks`](#method.fixed_chunks), the remainder chunk is
+    /// hashed zero-padded to the chunk size unless
+    /// [`unpadded_last_chunk`](#method.unpadded_last_chunk) is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let original_chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::dynamic_chunks(&mut buffer, WORDSTRING.len() as u64, 4)?
+    ///         .collect();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dynamic_chunks(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        dynamic_amount: u64,
+    ) -> Result<Self> {
+        ensure!(
+            dynamic_amount > 0,
+            "Dynamic amount must be greater than zero"
+        );
+        let stream_size = match stream_size.into() {
+            StreamSize::Known(size) => size,
+            StreamSize::Unknown => bail!("Dynamic chunks require a known stream size"),
+        };
+
+        let chunk_size = layout::dynamic_chunk_size(stream_size, dynamic_amount);
+
+        Ok(Self::new(
+            buffer,
+            StreamSize::Known(stream_size),
             chunk_size,
-            stream_size,
-            read_data: 0,
-            next_chunk: 0,
-        })
+            chunk_size,
+        ))
+    }
+
+    /// Instantiate an overlapping window chunked hasher
+    ///
+    /// Windows of `window_size` bytes are hashed every `stride` bytes, so
+    /// content shifted by less than a window will still share hashes with the
+    /// original. The last window ends at the end of the stream and may be
+    /// shorter than `window_size`, it is hashed zero-padded to the window
+    /// size unless [`unpadded_last_chunk`](#method.unpadded_last_chunk) is
+    /// set.
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - as neither Read nor Seek implements the ability to get
+    ///   the full size, we need to give this hint, it can't be unknown
+    /// * `window_size` - size of each hashed window
+    /// * `stride` - distance between the start of two windows, must not exceed
+    ///   `window_size`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let windows: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::overlapping_windows(
+    ///     &mut buffer,
+    ///     WORDSTRING.len() as u64,
+    ///     10,
+    ///     5,
+    /// )?
+    /// .collect();
+    /// assert_eq!(windows.len(), 7);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn overlapping_windows(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        window_size: u64,
+        stride: u64,
+    ) -> Result<Self> {
+        ensure!(window_size > 0, "Window size must be greater than zero");
+        ensure!(stride > 0, "Stride must be greater than zero");
+        ensure!(
+            stride <= window_size,
+            "Stride must not be greater than the window size"
+        );
+        let stream_size = match stream_size.into() {
+            StreamSize::Known(size) => size,
+            StreamSize::Unknown => bail!("Overlapping windows require a known stream size"),
+        };
+
+        let chunk_size = layout::clamp_chunk_size(window_size, stream_size);
+        let stride = layout::clamp_chunk_size(stride, chunk_size);
+
+        Ok(Self::new(
+            buffer,
+            StreamSize::Known(stream_size),
+            chunk_size,
+            stride,
+        ))
+    }
+
+    /// Instantiate a chunked hasher hashing caller supplied chunks, such as
+    /// the boundaries of a previous content-defined run to re-hash with a
+    /// different algorithm
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - as neither Read nor Seek implements the ability to get
+    ///   the full size, we need to give this hint, it can't be unknown
+    /// * `offsets` - start offset of every chunk, strictly increasing and
+    ///   within the stream, every chunk ends where the next one starts and
+    ///   the last one at the end of the stream
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::{blake3::Blake3Hasher, sha2::Sha256Hasher}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let previous: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::cdc_chunks(&mut buffer, WORDSTRING.len() as u64, 4, 8, 16)?
+    ///         .collect();
+    /// let offsets: Vec<u64> = previous.iter().map(|chunk| chunk.offset).collect();
+    /// let rehashed: Vec<Chunk> =
+    ///     ChunkedHasher::<Blake3Hasher>::from_boundaries(&mut buffer, WORDSTRING.len() as u64, &offsets)?
+    ///         .collect();
+    /// assert!(rehashed.iter().zip(&previous).all(|(new, old)| new.size == old.size));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_boundaries(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        offsets: &[u64],
+    ) -> Result<Self> {
+        let stream_size = match stream_size.into() {
+            StreamSize::Known(size) => size,
+            StreamSize::Unknown => bail!("Explicit boundaries require a known stream size"),
+        };
+        ensure!(
+            offsets.windows(2).all(|pair| pair[0] < pair[1]),
+            "Chunk offsets must be strictly increasing"
+        );
+        ensure!(
+            offsets.iter().all(|offset| *offset < stream_size),
+            "Chunk offsets must be within the stream"
+        );
+        let ends = offsets.iter().skip(1).chain(std::iter::once(&stream_size));
+        let max_size = offsets
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| layout::distance(*start, *end))
+            .max()
+            .unwrap_or(0);
+        let mut chunked_hasher =
+            Self::new(buffer, StreamSize::Known(stream_size), max_size, max_size);
+        chunked_hasher.layout.boundaries = Some(offsets.to_vec());
+        chunked_hasher.layout.pad_last_chunk = false;
+        Ok(chunked_hasher)
+    }
+
+    /// Instantiate a content-defined chunked hasher using FastCDC, see
+    /// [`cdc`](cdc/index.html)
+    ///
+    /// The stream is read sequentially from its start. Allocation bitmaps and
+    /// read buffers don't apply to content-defined chunks.
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size hint of the stream, or
+    ///   [`StreamSize::Unknown`](enum.StreamSize.html) to read until EOF
+    /// * `min_size` - minimum chunk size, except for the last chunk
+    /// * `avg_size` - desired average chunk size
+    /// * `max_size` - maximum chunk size
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::cdc_chunks(&mut buffer, WORDSTRING.len() as u64, 4, 8, 16)?
+    ///         .collect();
+    /// assert_eq!(chunks.iter().map(|chunk| chunk.size).sum::<u64>(), 40);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cdc_chunks(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        min_size: u64,
+        avg_size: u64,
+        max_size: u64,
+    ) -> Result<Self> {
+        let cdc = cdc::FastCdc::new(min_size, avg_size, max_size)?;
+        Self::strategy_chunks(buffer, stream_size, cdc)
+    }
+
+    /// Instantiate a content-defined chunked hasher using Rabin
+    /// fingerprints, see [`rabin`](rabin/index.html)
+    ///
+    /// The stream is read sequentially from its start. Allocation bitmaps and
+    /// read buffers don't apply to content-defined chunks.
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size hint of the stream, or
+    ///   [`StreamSize::Unknown`](enum.StreamSize.html) to read until EOF
+    /// * `chunker` - boundary finder holding the polynomial, window size,
+    ///   boundary mask and chunk size limits
+    pub fn rabin_chunks(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        chunker: rabin::RabinChunker,
+    ) -> Result<Self> {
+        Self::strategy_chunks(buffer, stream_size, chunker)
+    }
+
+    /// Instantiate a chunked hasher selecting chunk boundaries with a
+    /// strategy, see [`strategy`](strategy/index.html)
+    ///
+    /// The stream is read sequentially from its start. Allocation bitmaps and
+    /// read buffers don't apply to chunks selected by a strategy.
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size hint of the stream, or
+    ///   [`StreamSize::Unknown`](enum.StreamSize.html) to read until EOF
+    /// * `strategy` - chunk boundary selection
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, strategy::ChunkingStrategy, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// /// Ends chunks after every 'e'
+    /// struct AfterE;
+    ///
+    /// impl ChunkingStrategy for AfterE {
+    ///     fn max_size(&self) -> u64 {
+    ///         64
+    ///     }
+    ///
+    ///     fn cut_point(&self, data: &[u8]) -> usize {
+    ///         data.iter().position(|byte| *byte == b'e').map_or(data.len(), |index| index + 1)
+    ///     }
+    /// }
+    ///
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::strategy_chunks(&mut buffer, WORDSTRING.len() as u64, AfterE)?
+    ///         .collect();
+    /// assert_eq!(chunks.len(), 7);
+    /// assert_eq!(chunks[1].offset, 12);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn strategy_chunks(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        strategy: impl strategy::ChunkingStrategy + 'a,
+    ) -> Result<Self> {
+        let max_size = strategy.max_size();
+        ensure!(max_size > 0, "Maximum chunk size must be greater than zero");
+        let stream_size = stream_size.into();
+        if let StreamSize::Known(_) = stream_size {
+            buffer.seek(SeekFrom::Start(0))?;
+        }
+        let mut chunked_hasher = Self::new(buffer, stream_size, max_size, max_size);
+        chunked_hasher.layout.sequential = true;
+        chunked_hasher.layout.pad_last_chunk = false;
+        chunked_hasher.content_defined = Some((Box::new(strategy), Vec::new()));
+        Ok(chunked_hasher)
+    }
+
+    /// Also compute a similarity digest for every chunk, see
+    /// [`similarity`](similarity/index.html)
+    pub fn with_similarity(mut self) -> Self {
+        self.hashing.similarity = true;
+        self
+    }
+
+    /// Sample the data of the chunks to train a compression dictionary on
+    /// while hashing, see [`dictionary`](dictionary/index.html)
+    ///
+    /// Chunks are read whole when sampled. Chunks within the holes of a
+    /// sparse file aren't read and aren't sampled.
+    /// # Arguments
+    /// * `samples` - sample set the chunk data is offered to
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{dictionary::DictionarySamples, hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let mut samples = DictionarySamples::new(1024, 4)?;
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_dictionary_samples(&mut samples)
+    ///         .collect();
+    /// assert_eq!(chunks.len(), 4);
+    /// assert_eq!(samples.data(), b"brairemudisaexpe");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_dictionary_samples(
+        mut self,
+        samples: &'a mut dictionary::DictionarySamples,
+    ) -> Self {
+        self.hooks.push(samples);
+        self
+    }
+
+    /// Hash a last chunk shorter than [`chunk_size`](#method.chunk_size)
+    /// over its data only, instead of zero-padded to the chunk size
+    ///
+    /// By default the last chunk of fixed size chunks, dynamic chunks and
+    /// overlapping windows is hashed zero-padded, as protocols hashing padded
+    /// pieces expect, while produced chunks report the size of their actual
+    /// data. A stream shorter than the requested chunk size is a single chunk
+    /// of the stream size and is never padded, neither are content-defined
+    /// chunks and explicit boundaries, whose sizes vary by design.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::{sha2::Sha256Hasher, Hasher}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let padded: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 16)?
+    ///         .collect();
+    /// assert_eq!(padded[2].size, 8);
+    /// assert_eq!(padded[2].hash, Sha256Hasher::hash_bytes(b"periment\0\0\0\0\0\0\0\0"));
+    /// let unpadded: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 16)?
+    ///         .unpadded_last_chunk()
+    ///         .collect();
+    /// assert_eq!(unpadded[2].hash, Sha256Hasher::hash_bytes(b"periment"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unpadded_last_chunk(mut self) -> Self {
+        self.layout.pad_last_chunk = false;
+        self
+    }
+
+    /// Only hash chunks containing allocated blocks, chunks consisting only of
+    /// free blocks are skipped and not produced at all
+    ///
+    /// [`chunk_count`](#method.chunk_count) still counts skipped chunks.
+    /// # Arguments
+    /// * `bitmap` - allocation bitmap of the stream
+    pub fn with_allocation_bitmap(mut self, bitmap: allocation::AllocationBitmap) -> Self {
+        self.layout.allocation = Some(bitmap);
+        self
+    }
+
+    /// Only hash the chunks overlapping a byte range of the stream, such as a
+    /// damaged region to re-verify, chunks keep the indices, offsets and
+    /// sizes they have when hashing the whole stream
+    ///
+    /// Chunks before the range are skipped without being read, unless the
+    /// stream size is unknown. [`chunk_count`](#method.chunk_count) still
+    /// counts the chunks of the whole stream. Content-defined chunks can't be
+    /// limited to a range, as their boundaries depend on all preceding data.
+    /// # Arguments
+    /// * `start` - offset of the first byte of the range
+    /// * `end` - offset past the last byte of the range
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_range(12, 25)?
+    ///         .collect();
+    /// assert_eq!(chunks.iter().map(|chunk| chunk.index).collect::<Vec<_>>(), vec![1, 2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_range(mut self, start: u64, end: u64) -> Result<Self> {
+        ensure!(start < end, "Range start must be before its end");
+        ensure!(
+            self.content_defined.is_none(),
+            "Content-defined chunks can't be limited to a range"
+        );
+        self.next_chunk = match &self.layout.boundaries {
+            Some(boundaries) => boundaries
+                .partition_point(|offset| *offset <= start)
+                .saturating_sub(1) as u64,
+            None => {
+                layout::first_chunk_ending_after(start, self.layout.chunk_size, self.layout.stride)
+            }
+        };
+        self.layout.range_end = Some(end);
+        Ok(self)
+    }
+
+    /// Align every chunk boundary to a multiple of a block size, such as the
+    /// sector size of a device written with `O_DIRECT`
+    ///
+    /// Fixed and dynamic chunk sizes, as well as window sizes and strides, are
+    /// rounded up to the next multiple of `block_size`, so
+    /// [`chunk_count`](#method.chunk_count) may shrink. Content-defined
+    /// boundaries are moved forward to the next multiple, see
+    /// [`strategy::Aligned`](strategy/struct.Aligned.html). Explicit
+    /// boundaries must already be aligned. Only the last chunk may end
+    /// unaligned, as it holds the remainder of the stream.
+    /// # Arguments
+    /// * `block_size` - block size boundaries are aligned to
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunked_hasher =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_alignment(16)?;
+    /// assert_eq!(chunked_hasher.chunk_count(), Some(3));
+    /// let chunks: Vec<Chunk> = chunked_hasher.collect();
+    /// assert_eq!(chunks[2].offset, 32);
+    /// assert_eq!(chunks[2].size, 8);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_alignment(mut self, block_size: u64) -> Result<Self> {
+        ensure!(block_size > 0, "Block size must be greater than zero");
+        ensure!(
+            self.layout.range_end.is_none(),
+            "Alignment must be set before limiting chunks to a range"
+        );
+        if let Some(boundaries) = &self.layout.boundaries {
+            ensure!(
+                boundaries.iter().all(|offset| offset % block_size == 0),
+                "Chunk boundaries must be multiples of {}",
+                block_size
+            );
+        } else if let Some((strategy, pending)) = self.content_defined.take() {
+            let aligned = strategy::Aligned::new(strategy, block_size)?;
+            self.layout.chunk_size = strategy::ChunkingStrategy::max_size(&aligned);
+            self.layout.stride = self.layout.chunk_size;
+            self.content_defined = Some((Box::new(aligned), pending));
+        } else {
+            let chunk_layout = &mut self.layout;
+            chunk_layout.chunk_size =
+                layout::align_up(chunk_layout.chunk_size, block_size).min(chunk_layout.stream_size);
+            chunk_layout.stride = layout::align_up(chunk_layout.stride, block_size);
+        }
+        Ok(self)
+    }
+
+    /// Report chunks that take unusually long to read or hash
+    ///
+    /// A chunk is reported when a phase takes longer than `multiple` times the
+    /// median of the recent chunks, once enough chunks were processed to have
+    /// a meaningful median.
+    /// # Arguments
+    /// * `multiple` - multiple of the median a chunk must exceed
+    /// * `callback` - called with every slow chunk
+    pub fn with_slow_chunk_callback<F: FnMut(&slow::SlowChunk) + 'a>(
+        mut self,
+        multiple: f64,
+        callback: F,
+    ) -> Self {
+        self.hooks
+            .push(slow::SlowChunkDetector::new(multiple, Box::new(callback)));
+        self
+    }
+
+    /// Hash chunks with a hasher carrying state, such as a secret key, instead
+    /// of `H`
+    /// # Arguments
+    /// * `hasher` - keyed hasher to hash every chunk with
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::{hmac::HmacHasher, sha2::Sha256Hasher}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let authenticated_chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_keyed_hasher(HmacHasher::<Sha256Hasher>::new(b"secret"))
+    ///         .collect();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_keyed_hasher<K: hashers::KeyedHasher + 'a>(mut self, hasher: K) -> Self {
+        self.hashing.keyed_hasher = Some(Box::new(hasher));
+        self.hashing.algorithm = None;
+        self
+    }
+
+    /// Bind every chunk hash to a salt and the position of the chunk, see
+    /// [`domain`](domain/index.html) for the scheme
+    /// # Arguments
+    /// * `salt` - salt to hash before every chunk, along with its index and
+    ///   offset
+    pub fn with_domain_separation(mut self, salt: &[u8]) -> Self {
+        self.hashing.domain_salt = Some(salt.to_vec());
+        self
+    }
+
+    /// Keep only the first bytes of every digest, to shrink manifests when
+    /// full digests aren't needed
+    ///
+    /// Record the length along with the chunks, for example with
+    /// [`ManifestLogWriter::with_truncation`](manifest_log/struct.ManifestLogWriter.html#method.with_truncation).
+    /// # Arguments
+    /// * `length` - amount of leading digest bytes to keep, at least one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha512Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha512Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .with_truncation(16)?
+    ///         .collect();
+    /// assert!(chunks.iter().all(|chunk| chunk.hash.len() == 16));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_truncation(mut self, length: usize) -> Result<Self> {
+        ensure!(length > 0, "Truncation length must be greater than zero");
+        self.hashing.truncation = Some(length);
+        Ok(self)
+    }
+
+    /// Amount of leading digest bytes kept, if digests are truncated
+    pub fn truncation(&self) -> Option<usize> {
+        self.hashing.truncation
+    }
+
+    /// Algorithm the chunk hashes are plain digests of, to tag them with,
+    /// such as in [`multihash`](multihash/index.html) format
+    ///
+    /// `None` if the hasher has no runtime equivalent or the hashes aren't
+    /// plain digests of the chunk data, as with keyed hashers or domain
+    /// separation.
+    pub fn algorithm(&self) -> Option<hashers::algorithm::Algorithm>
+    where
+        H: 'static,
+    {
+        if !self.hashing.is_positionless() || self.hashing.keyed_hasher.is_some() {
+            return None;
+        }
+        match self.hashing.algorithm {
+            Some(hashers::algorithm::DynHasher(algorithm)) => Some(algorithm),
+            None => hashers::algorithm::Algorithm::of::<H>(),
+        }
+    }
+
+    /// Register a subscriber called with every chunk as it is produced, so
+    /// several consumers can react to the chunks in a single pass
+    ///
+    /// Subscribers are called in registration order, before the chunk is
+    /// returned by the iterator.
+    /// # Arguments
+    /// * `subscriber` - called with every chunk
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let mut hashed_bytes = 0;
+    /// let mut manifest = Vec::new();
+    /// ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///     .on_chunk(|chunk| hashed_bytes += chunk.size)
+    ///     .on_chunk(|chunk| manifest.push(chunk.to_string()))
+    ///     .for_each(drop);
+    /// assert_eq!(hashed_bytes, 40);
+    /// assert_eq!(manifest.len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_chunk<F: FnMut(&Chunk) + 'a>(mut self, subscriber: F) -> Self {
+        self.hooks.push(hooks::Subscriber(subscriber));
+        self
+    }
+
+    /// Register a hook observing every chunk, see [`hooks`](hooks/index.html)
+    ///
+    /// Hooks are called in registration order, along with the ones
+    /// registered through [`on_chunk`](#method.on_chunk),
+    /// [`with_slow_chunk_callback`](#method.with_slow_chunk_callback) and
+    /// [`with_dictionary_samples`](#method.with_dictionary_samples).
+    /// # Arguments
+    /// * `hook` - hook to register, or a mutable reference to one to inspect
+    ///   it afterwards
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, hooks::ChunkHook, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// /// Counts the vowels of the hashed data
+    /// #[derive(Default)]
+    /// struct Vowels(usize);
+    ///
+    /// impl ChunkHook for Vowels {
+    ///     fn needs_data(&self) -> bool {
+    ///         true
+    ///     }
+    ///
+    ///     fn data(&mut self, data: &[u8]) {
+    ///         self.0 += data.iter().filter(|byte| b"aeiou".contains(byte)).count();
+    ///     }
+    /// }
+    ///
+    /// let mut vowels = Vowels::default();
+    /// ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///     .with_hook(&mut vowels)
+    ///     .for_each(drop);
+    /// assert_eq!(vowels.0, 16);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_hook<K: hooks::ChunkHook + 'a>(mut self, hook: K) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Produce chunks carrying the fixed size digest of `H` instead of a
+    /// `Vec<u8>`, so no allocation is needed per hash
+    ///
+    /// Keyed hashers and truncated digests produce variable length hashes and
+    /// can't be combined with typed chunks.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::blake3::Blake3Hasher, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk<[u8; 32]>> =
+    ///     ChunkedHasher::<Blake3Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .typed()?
+    ///         .collect();
+    /// assert_eq!(chunks.len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn typed(self) -> Result<TypedChunks<'a, H>> {
+        ensure!(
+            self.hashing.keyed_hasher.is_none() && self.hashing.algorithm.is_none(),
+            "Keyed hashers and runtime algorithms can't produce typed chunks"
+        );
+        ensure!(
+            self.hashing.truncation.is_none(),
+            "Truncated digests can't produce typed chunks"
+        );
+        Ok(TypedChunks { inner: self })
+    }
+
+    /// Produce the chunks followed by a summary of the whole stream, see
+    /// [`summary`](summary/index.html)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, summary::StreamItem, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let items: Vec<StreamItem> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .summarized()
+    ///         .collect();
+    /// match items.last() {
+    ///     Some(StreamItem::Summary(summary)) => assert_eq!(summary.chunk_count, 4),
+    ///     _ => unreachable!(),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn summarized(self) -> summary::SummarizedChunks<'a, H> {
+        summary::SummarizedChunks::new(self)
+    }
+
+    /// Size of the chunks except for the last remainer chunk, if any of those,
+    /// or the maximum chunk size for content-defined chunks and explicit
+    /// boundaries
+    pub fn chunk_size(&self) -> u64 {
+        self.layout.chunk_size
+    }
+
+    /// Distance between the start of two consecutive chunks, equal to the
+    /// chunk size unless windows overlap
+    pub fn stride(&self) -> u64 {
+        self.layout.stride
+    }
+
+    /// Amount of chunks we will expect to be produced, `None` if the stream
+    /// size is unknown or chunks are content-defined
+    pub fn chunk_count(&self) -> Option<u64> {
+        self.layout.chunk_count()
+    }
+}
+
+impl<'a> ChunkedHasher<'a, hashers::algorithm::DynHasher> {
+    /// Instantiate a chunked hasher with a layout and algorithm chosen at
+    /// runtime
+    ///
+    /// # Arguments
+    /// * `buffer` - the buffer to hash
+    /// * `stream_size` - size hint of the stream, see the constructor
+    ///   matching `chunking`
+    /// * `chunking` - chunk layout to use
+    /// * `algorithm` - hash algorithm to use
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::{algorithm::Algorithm, blake3::Blake3Hasher}, Chunk, ChunkedHasher, Chunking};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let algorithm: Algorithm = "blake3".parse()?;
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::with_algorithm(&mut buffer, WORDSTRING.len() as u64, Chunking::Fixed(10), algorithm)?
+    ///         .collect();
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let typed_chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Blake3Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?.collect();
+    /// assert_eq!(chunks, typed_chunks);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_algorithm(
+        buffer: &'a mut dyn ReadAndSeek,
+        stream_size: impl Into<StreamSize>,
+        chunking: Chunking,
+        algorithm: hashers::algorithm::Algorithm,
+    ) -> Result<Self> {
+        let chunked_hasher = match chunking {
+            Chunking::Fixed(fixed_size) => {
+                ChunkedHasher::fixed_chunks(buffer, stream_size, fixed_size)?
+            }
+            Chunking::Dynamic(amount) => {
+                ChunkedHasher::dynamic_chunks(buffer, stream_size, amount)?
+            }
+            Chunking::Overlapping {
+                window_size,
+                stride,
+            } => ChunkedHasher::overlapping_windows(buffer, stream_size, window_size, stride)?,
+        };
+        let mut chunked_hasher = chunked_hasher;
+        chunked_hasher.hashing.algorithm = Some(hashers::algorithm::DynHasher(algorithm));
+        Ok(chunked_hasher)
     }
+}
 
-    /// Instantiate a dynamic size chunked hasher
+impl<'a, H: hashers::StreamingHasher> ChunkedHasher<'a, H> {
+    /// Feed chunks to the hasher in increments of a read buffer instead of
+    /// reading whole chunks into memory, allowing chunk sizes larger than the
+    /// available memory
     ///
+    /// Chunks are still read whole when similarity digests, a keyed hasher or
+    /// a [hook needing the chunk data](hooks/trait.ChunkHook.html#method.needs_data)
+    /// are requested, as those need all bytes of a chunk at once.
     /// # Arguments
-    /// * `buffer` - the buffer to hash
-    /// * `stream_size` - as neither Read nor Seek implements the ability to get
-    ///   the full size, we need to give this hint
-    /// * `dynamic_amount` - amount of chunks to chunk into, if it's not
-    ///   perfectly divisible the remainder will be in its own chunk
+    /// * `size` - size of the read buffer
     ///
     /// # Example
     ///
@@ -92,101 +1098,504 @@ impl<'a, H: hashers::Hasher> ChunkedHasher<'a, H> {
     /// # pub fn main() -> Result<()> {
     /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
     /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
-    /// let original_chunks: Vec<Chunk> =
-    ///     ChunkedHasher::<Sha256Hasher>::dynamic_chunks(&mut buffer, WORDSTRING.len() as u64, 4)?
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 20)?
+    ///         .with_read_buffer(8)
     ///         .collect();
+    /// assert_eq!(chunks.len(), 2);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn dynamic_chunks(
-        buffer: &'a mut dyn ReadAndSeek,
-        stream_size: u64,
-        dynamic_amount: u64,
-    ) -> Result<Self> {
-        ensure!(stream_size > 0, "Stream size must be greater than zero");
-        ensure!(
-            dynamic_amount > 0,
-            "Dynamic amount must be greater than zero"
-        );
+    pub fn with_read_buffer(mut self, size: usize) -> Self {
+        self.streaming = Some((vec![0; size.max(1)], stream_chunk::<H>));
+        self
+    }
+}
 
-        let chunk_size = if dynamic_amount <= stream_size {
-            (stream_size - (stream_size % dynamic_amount)) / dynamic_amount
-        } else {
-            stream_size
-        };
+impl<'a, H: hashers::Hasher> Iterator for ChunkedHasher<'a, H> {
+    type Item = Chunk;
 
-        Ok(Self {
-            seekable_buffer: buffer,
-            _marker: PhantomData,
-            chunk_size,
-            stream_size,
-            read_data: 0,
-            next_chunk: 0,
-        })
+    fn next(&mut self) -> Option<Chunk> {
+        let chunk = self.next_hashed()?;
+        let hash = self.hashing.finish(chunk.hash);
+        #[cfg(feature = "paranoid")]
+        {
+            let expected =
+                self.hashing
+                    .algorithm
+                    .map(|hashers::algorithm::DynHasher(algorithm)| {
+                        let length = algorithm.hash_bytes(&[]).len();
+                        self.hashing
+                            .truncation
+                            .map_or(length, |truncation| truncation.min(length))
+                    });
+            self.invariants.digest(hash.len(), expected);
+        }
+        let chunk = Chunk {
+            index: chunk.index,
+            offset: chunk.offset,
+            size: chunk.size,
+            hash,
+            similarity: chunk.similarity,
+        };
+        self.hooks.chunk(&chunk);
+        Some(chunk)
     }
+}
 
-    /// Size of the chunks except for the last remainer chunk, if any of those
-    pub fn chunk_size(&self) -> u64 {
-        self.chunk_size
-    }
+/// Chunked hasher producing chunks with the fixed size digest of `H`, see
+/// [`ChunkedHasher::typed`](struct.ChunkedHasher.html#method.typed)
+pub struct TypedChunks<'a, H: hashers::Hasher> {
+    inner: ChunkedHasher<'a, H>,
+}
+
+impl<'a, H: hashers::Hasher> Iterator for TypedChunks<'a, H> {
+    type Item = Chunk<H::Output>;
 
-    /// Amount of chunks we will expect to be produced
-    pub fn chunk_count(&self) -> u64 {
-        f64::ceil(self.stream_size as f64 / self.chunk_size as f64) as u64
+    fn next(&mut self) -> Option<Chunk<H::Output>> {
+        let chunk = self.inner.next_hashed()?;
+        let hash = match chunk.hash {
+            ChunkHash::Static(hash) => hash,
+            ChunkHash::Keyed(_) => unreachable!("Typed chunks are never keyed"),
+        };
+        // Hooks receive regular chunks, only convert when needed
+        if !self.inner.hooks.is_empty() {
+            self.inner.hooks.chunk(&Chunk {
+                index: chunk.index,
+                offset: chunk.offset,
+                size: chunk.size,
+                hash: hash.as_ref().to_vec(),
+                similarity: chunk.similarity,
+            });
+        }
+        Some(Chunk {
+            index: chunk.index,
+            offset: chunk.offset,
+            size: chunk.size,
+            hash,
+            similarity: chunk.similarity,
+        })
     }
 }
 
-impl<'a, H: hashers::Hasher> Iterator for ChunkedHasher<'a, H> {
-    type Item = Chunk;
+impl<'a, H: hashers::Hasher> ChunkedHasher<'a, H> {
+    /// Produce the next chunk, hashed either by `H` or by the keyed hasher,
+    /// checking the chunk accounting once exhausted with the `paranoid`
+    /// feature
+    fn next_hashed(&mut self) -> Option<Chunk<ChunkHash<H::Output>>> {
+        let chunk = self.produce_next();
+        #[cfg(feature = "paranoid")]
+        if chunk.is_none() {
+            // Chunks of a range don't reach the end of the stream
+            let stream_size = Some(self.layout.stream_size)
+                .filter(|size| *size != u64::MAX && self.layout.range_end.is_none());
+            self.invariants
+                .complete(stream_size, self.read_data, self.layout.is_contiguous());
+        }
+        chunk
+    }
 
-    fn next(&mut self) -> Option<Chunk> {
-        if self.read_data >= self.stream_size {
+    /// Produce the next chunk, hashed either by `H` or by the keyed hasher
+    fn produce_next(&mut self) -> Option<Chunk<ChunkHash<H::Output>>> {
+        if self.finished {
             return None;
         }
-        match self
-            .seekable_buffer
-            .seek(SeekFrom::Start(self.next_chunk * self.chunk_size))
-        {
-            Ok(_) => {
-                self.next_chunk += 1;
-                let mut buf = vec![0u8; self.chunk_size as usize];
-                match self.seekable_buffer.read(&mut buf) {
-                    Ok(read_bytes) => {
-                        self.read_data += read_bytes as u64;
-                        Some(Chunk {
-                            index: self.next_chunk - 1,
-                            size: read_bytes as u64,
-                            hash: H::hash_bytes(&buf),
-                        })
-                    }
-                    Err(_) => None,
+        if self.content_defined.is_some() {
+            return self.next_content_defined();
+        }
+        let (offset, length) = loop {
+            let (offset, length) = self.layout.chunk_range(self.next_chunk)?;
+            if self.layout.is_allocated(offset, length) {
+                break (offset, length);
+            }
+            self.next_chunk += 1;
+        };
+        if self.layout.is_hole(offset, length) {
+            self.next_chunk += 1;
+            let hashed = self.hash_zeros(offset, length);
+            return Some(self.record(offset, Duration::default(), hashed));
+        }
+        let seek_start = Instant::now();
+        if self.position_at(offset).is_err() {
+            return None;
+        }
+        let seek_time = seek_start.elapsed();
+        self.next_chunk += 1;
+        let streamed = self.hashing.is_incremental() && !self.hooks.needs_data();
+        let padded_size = self.layout.padded_size();
+        let prefix = self.hashing.prefix(self.next_chunk - 1, offset);
+        let hashed = match &mut self.streaming {
+            Some((buffer, stream_chunk)) if streamed => stream_chunk(
+                self.seekable_buffer,
+                buffer,
+                &prefix,
+                offset,
+                length,
+                padded_size,
+            ),
+            _ => self.read_and_hash(&prefix, offset, length),
+        };
+        let hashed = hashed.ok()?;
+        // The stream ended, either as expected when reading until EOF or
+        // earlier than the size hint promised
+        if hashed.size < length {
+            #[cfg(feature = "paranoid")]
+            self.invariants.short_read();
+            self.finished = true;
+            if hashed.size == 0 {
+                return None;
+            }
+        }
+        Some(self.record(offset, seek_time, hashed))
+    }
+
+    /// Hash a chunk of zeros, reusing the hash of the previous one if
+    /// nothing but the chunk content goes into the hash
+    fn hash_zeros(&mut self, offset: u64, length: u64) -> Hashed<H::Output> {
+        let padding = layout::padding(offset, length, self.layout.padded_size());
+        match &self.zero_chunk {
+            Some((zero_padding, zero_chunk))
+                if zero_chunk.size == length && *zero_padding == padding =>
+            {
+                zero_chunk.clone()
+            }
+            _ => {
+                let mut buf = self.hashing.prefix(self.next_chunk - 1, offset);
+                let prefix_length = buf.len();
+                buf.resize(prefix_length + (length + padding) as usize, 0);
+                let hashed = self.hashing.hash::<H>(&buf, prefix_length, length as usize);
+                if self.hashing.is_positionless() {
+                    self.zero_chunk = Some((padding, hashed.clone()));
                 }
+                hashed
             }
-            Err(_) => None,
         }
     }
+
+    /// Produce the next content-defined chunk, continuing from the data read
+    /// past the previous boundary
+    fn next_content_defined(&mut self) -> Option<Chunk<ChunkHash<H::Output>>> {
+        let (boundaries, pending) = self.content_defined.as_mut()?;
+        let read_start = Instant::now();
+        let wanted = boundaries
+            .max_size()
+            .min(layout::distance(self.read_data, self.layout.stream_size))
+            .max(pending.len() as u64) as usize;
+        let filled = pending.len();
+        pending.resize(wanted, 0);
+        let read_bytes = read_full(self.seekable_buffer, &mut pending[filled..]).ok()?;
+        #[cfg(feature = "paranoid")]
+        if filled + read_bytes < wanted {
+            self.invariants.short_read();
+        }
+        pending.truncate(filled + read_bytes);
+        let read_time = read_start.elapsed();
+        if pending.is_empty() {
+            self.finished = true;
+            return None;
+        }
+        let length = boundaries.cut_point(pending);
+        let offset = self.position;
+        let mut buf = self.hashing.prefix(self.next_chunk, offset);
+        let prefix_length = buf.len();
+        buf.extend(pending.drain(..length));
+        self.next_chunk += 1;
+        if self.hooks.needs_data() {
+            self.hooks.data(&buf[prefix_length..]);
+        }
+        let data_length = buf.len() - prefix_length;
+        let mut hashed = self.hashing.hash::<H>(&buf, prefix_length, data_length);
+        hashed.read_time = read_time;
+        Some(self.record(offset, Duration::default(), hashed))
+    }
+
+    /// Account for a hashed chunk and turn it into a chunk
+    fn record(
+        &mut self,
+        offset: u64,
+        seek_time: Duration,
+        hashed: Hashed<H::Output>,
+    ) -> Chunk<ChunkHash<H::Output>> {
+        self.read_data += hashed.size;
+        self.position = layout::chunk_end(offset, hashed.size).unwrap_or(u64::MAX);
+        let chunk = ChunkRef {
+            index: self.next_chunk - 1,
+            offset,
+            size: hashed.size,
+        };
+        #[cfg(feature = "paranoid")]
+        self.invariants.chunk(chunk, self.layout.is_contiguous());
+        self.hooks
+            .timing(chunk, seek_time + hashed.read_time, hashed.hash_time);
+        Chunk {
+            index: chunk.index,
+            offset,
+            size: hashed.size,
+            hash: hashed.hash,
+            similarity: hashed.similarity,
+        }
+    }
+
+    /// Read a whole chunk into memory and hash it along with the prefix
+    fn read_and_hash(
+        &mut self,
+        prefix: &[u8],
+        offset: u64,
+        length: u64,
+    ) -> io::Result<Hashed<H::Output>> {
+        let read_start = Instant::now();
+        let mut buf = vec![0u8; prefix.len() + length as usize];
+        buf[..prefix.len()].copy_from_slice(prefix);
+        let read_bytes = read_full(self.seekable_buffer, &mut buf[prefix.len()..])?;
+        buf.truncate(prefix.len() + read_bytes);
+        let read_time = read_start.elapsed();
+        if self.hooks.needs_data() {
+            self.hooks.data(&buf[prefix.len()..]);
+        }
+        let padding = layout::padding(offset, read_bytes as u64, self.layout.padded_size());
+        buf.resize(buf.len() + padding as usize, 0);
+        let mut hashed = self.hashing.hash::<H>(&buf, prefix.len(), read_bytes);
+        hashed.read_time = read_time;
+        Ok(hashed)
+    }
+}
+
+/// Hash of a chunk, produced by the static hasher or a keyed hasher
+#[derive(Clone)]
+enum ChunkHash<O> {
+    Static(O),
+    Keyed(Vec<u8>),
+}
+
+/// Chunk read from the stream and hashed, along with the time it took
+#[derive(Clone)]
+struct Hashed<O> {
+    size: u64,
+    hash: ChunkHash<O>,
+    similarity: Option<u64>,
+    read_time: Duration,
+    hash_time: Duration,
+}
+
+/// Reads and hashes a chunk at the given offset and of the given length in
+/// increments of the buffer, after hashing the prefix, zero-padding a short
+/// chunk to the padded size
+type StreamChunk<O> =
+    fn(&mut dyn ReadAndSeek, &mut [u8], &[u8], u64, u64, u64) -> io::Result<Hashed<O>>;
+
+fn stream_chunk<S: hashers::StreamingHasher>(
+    reader: &mut dyn ReadAndSeek,
+    buffer: &mut [u8],
+    prefix: &[u8],
+    offset: u64,
+    length: u64,
+    padded_size: u64,
+) -> io::Result<Hashed<S::Output>> {
+    let mut hasher = S::new();
+    hasher.update(prefix);
+    let mut size = 0;
+    let mut read_time = Duration::default();
+    let mut hash_time = Duration::default();
+    while size < length {
+        let wanted = (length - size).min(buffer.len() as u64) as usize;
+        let read_start = Instant::now();
+        let read_bytes = read_full(reader, &mut buffer[..wanted])?;
+        read_time += read_start.elapsed();
+        let hash_start = Instant::now();
+        hasher.update(&buffer[..read_bytes]);
+        hash_time += hash_start.elapsed();
+        size += read_bytes as u64;
+        if read_bytes < wanted {
+            break;
+        }
+    }
+    let mut padding = layout::padding(offset, size, padded_size);
+    if padding > 0 {
+        let hash_start = Instant::now();
+        buffer.iter_mut().for_each(|byte| *byte = 0);
+        while padding > 0 {
+            let zeros = padding.min(buffer.len() as u64) as usize;
+            hasher.update(&buffer[..zeros]);
+            padding -= zeros as u64;
+        }
+        hash_time += hash_start.elapsed();
+    }
+    Ok(Hashed {
+        size,
+        hash: ChunkHash::Static(hasher.finalize()),
+        similarity: None,
+        read_time,
+        hash_time,
+    })
+}
+
+impl<'a, H: hashers::Hasher> ChunkedHasher<'a, H> {
+    /// Move the buffer to the start of a chunk, by seeking or, when reading
+    /// sequentially, by skipping forward
+    fn position_at(&mut self, offset: u64) -> io::Result<()> {
+        if !self.layout.sequential {
+            self.seekable_buffer.seek(SeekFrom::Start(offset))?;
+            return Ok(());
+        }
+        let skip = layout::distance(self.position, offset);
+        if skip > 0 {
+            let skipped = io::copy(&mut (&mut self.seekable_buffer).take(skip), &mut io::sink())?;
+            self.position += skipped;
+        }
+        Ok(())
+    }
+}
+
+/// Whole-stream digest derived from the chunks of a stream, the hash of the
+/// concatenated chunk hashes in index order
+///
+/// Zero-length streams produce no chunks, their digest is defined as the hash
+/// of empty input so that empty files still have a stable identity.
+/// # Arguments
+/// * `chunks` - all chunks of the stream, hashed with `H`
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::{sha2::Sha256Hasher, Hasher}, stream_digest, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// let mut empty: Cursor<&[u8]> = Cursor::new(b"");
+/// let chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut empty, 0, 10)?.collect();
+/// assert!(chunks.is_empty());
+/// assert_eq!(stream_digest::<Sha256Hasher>(&chunks), Sha256Hasher::hash_bytes(b""));
+/// # Ok(())
+/// # }
+/// ```
+pub fn stream_digest<H: hashers::Hasher>(chunks: &[Chunk]) -> Vec<u8> {
+    let mut sorted: Vec<&Chunk> = chunks.iter().collect();
+    sorted.sort_by_key(|chunk| chunk.index);
+    let concatenated: Vec<u8> = sorted
+        .iter()
+        .flat_map(|chunk| chunk.hash.iter().cloned())
+        .collect();
+    H::hash_bytes(&concatenated)
+}
+
+/// Fill the buffer as far as possible, as a single read may return less than
+/// requested even if more data is available
+fn read_full(reader: &mut dyn ReadAndSeek, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read_bytes) => filled += read_bytes,
+            Err(ref e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
 }
 
 /// Representation of a chunk including its position and hashed value
-pub struct Chunk {
+///
+/// The hash is a `Vec<u8>` by default, chunks produced through
+/// [`ChunkedHasher::typed`](struct.ChunkedHasher.html#method.typed) carry the
+/// fixed size [`Hasher::Output`](hashers/trait.Hasher.html#associatedtype.Output)
+/// instead.
+#[derive(Clone, Debug)]
+pub struct Chunk<O = Vec<u8>> {
     /// Index in the streamed data this chunk pertains to
     pub index: u64,
+    /// Byte offset in the streamed data where this chunk starts
+    pub offset: u64,
     /// Size of the chunk that was hashed
     pub size: u64,
     /// Hash of chunked data
-    pub hash: Vec<u8>,
+    pub hash: O,
+    /// Similarity digest of chunked data, if requested
+    pub similarity: Option<u64>,
 }
 
-impl std::fmt::Display for Chunk {
+impl<O: AsRef<[u8]>> std::fmt::Display for Chunk<O> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use hex::encode;
         write!(f, "{}/{}/{}", self.index, self.size, encode(&self.hash))
     }
 }
 
-impl PartialEq for Chunk {
+impl<O: AsRef<[u8]>> Chunk<O> {
+    /// Whether a digest of the chunk's data matches its hash, the hash being
+    /// the leading bytes of the digest when digests are truncated
+    pub(crate) fn matches(&self, digest: &[u8]) -> bool {
+        let hash = self.hash.as_ref();
+        !hash.is_empty() && digest.starts_with(hash)
+    }
+
+    /// Whether the chunk's data hashes to its hash, as is or followed by
+    /// `padding` zeros
+    pub(crate) fn matches_data<H: hashers::Hasher>(&self, data: &[u8], padding: u64) -> bool {
+        if self.matches(&H::hash_bytes(data)) {
+            return true;
+        }
+        if padding == 0 {
+            return false;
+        }
+        match H::hash_reader(&mut data.chain(io::repeat(0)), data.len() as u64 + padding) {
+            Ok((_, digest)) => self.matches(digest.as_ref()),
+            Err(_) => false,
+        }
+    }
+
+    /// Amount of zeros the chunk may have been hashed padded with in a
+    /// manifest: the last chunk of a manifest is padded to the size of the
+    /// first one, unless hashed with
+    /// [`unpadded_last_chunk`](struct.ChunkedHasher.html#method.unpadded_last_chunk)
+    /// # Arguments
+    /// * `manifest` - chunks of the stream, in offset order
+    pub(crate) fn padding_in<P>(&self, manifest: &[Chunk<P>]) -> u64 {
+        match (manifest.first(), manifest.last()) {
+            (Some(first), Some(last))
+                if first.offset == 0 && last.index == self.index && last.offset == self.offset =>
+            {
+                layout::padding(self.offset, self.size, first.size)
+            }
+            _ => 0,
+        }
+    }
+}
+
+impl<O: PartialEq> PartialEq for Chunk<O> {
     fn eq(&self, other: &Self) -> bool {
-        self.index == other.index && self.size == other.size && self.hash == other.hash
+        self.index == other.index
+            && self.offset == other.offset
+            && self.size == other.size
+            && self.hash == other.hash
+    }
+}
+
+/// Position of a chunk in the streamed data, without its hash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkRef {
+    /// Index in the streamed data this chunk pertains to
+    pub index: u64,
+    /// Byte offset in the streamed data where this chunk starts
+    pub offset: u64,
+    /// Size of the chunk
+    pub size: u64,
+}
+
+impl ChunkRef {
+    /// Byte offset right after the end of the chunk, saturating at
+    /// `u64::MAX`
+    pub fn end(&self) -> u64 {
+        layout::chunk_end(self.offset, self.size).unwrap_or(u64::MAX)
+    }
+}
+
+impl<O> From<&Chunk<O>> for ChunkRef {
+    fn from(chunk: &Chunk<O>) -> Self {
+        Self {
+            index: chunk.index,
+            offset: chunk.offset,
+            size: chunk.size,
+        }
     }
 }
 
@@ -359,4 +1768,642 @@ mod tests {
         dynamic_chunks,
         12
     );
+
+    #[cfg(feature = "legacy-hashes")]
+    perform_test!(
+        compare_two_strings_fixed_md5,
+        hashers::legacy::Md5Hasher,
+        fixed_chunks,
+        40
+    );
+
+    #[cfg(feature = "legacy-hashes")]
+    perform_test!(
+        compare_two_strings_dynamic_sha1,
+        hashers::legacy::Sha1Hasher,
+        dynamic_chunks,
+        12
+    );
+
+    #[cfg(feature = "legacy-hashes")]
+    perform_test_file!(
+        compare_two_strings_fixed_sha1_file,
+        hashers::legacy::Sha1Hasher,
+        fixed_chunks,
+        40
+    );
+
+    #[cfg(feature = "ripemd")]
+    perform_test!(
+        compare_two_strings_fixed_ripemd160,
+        hashers::ripemd::Ripemd160Hasher,
+        fixed_chunks,
+        40
+    );
+
+    #[cfg(feature = "whirlpool")]
+    perform_test!(
+        compare_two_strings_dynamic_whirlpool,
+        hashers::whirlpool::WhirlpoolHasher,
+        dynamic_chunks,
+        12
+    );
+
+    #[cfg(feature = "streebog")]
+    perform_test!(
+        compare_two_strings_fixed_streebog256,
+        hashers::streebog::Streebog256Hasher,
+        fixed_chunks,
+        40
+    );
+
+    #[cfg(feature = "streebog")]
+    perform_test_file!(
+        compare_two_strings_dynamic_streebog512_file,
+        hashers::streebog::Streebog512Hasher,
+        dynamic_chunks,
+        12
+    );
+
+    #[cfg(feature = "sm3")]
+    perform_test!(
+        compare_two_strings_dynamic_sm3,
+        hashers::sm3::Sm3Hasher,
+        dynamic_chunks,
+        12
+    );
+
+    #[cfg(feature = "sm3")]
+    perform_test_file!(
+        compare_two_strings_fixed_sm3_file,
+        hashers::sm3::Sm3Hasher,
+        fixed_chunks,
+        40
+    );
+
+    perform_test!(
+        compare_two_strings_dynamic_blake3,
+        hashers::blake3::Blake3Hasher,
+        dynamic_chunks,
+        12
+    );
+
+    perform_test!(
+        compare_two_strings_fixed_xxh3,
+        hashers::xxhash::Xxh3Hasher,
+        fixed_chunks,
+        40
+    );
+
+    perform_test_file!(
+        compare_two_strings_dynamic_xxh64_file,
+        hashers::xxhash::Xxh64Hasher,
+        dynamic_chunks,
+        12
+    );
+
+    #[test]
+    fn compare_two_strings_overlapping_sha256() -> Result<()> {
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(WORDSTRING_DIFF.as_bytes());
+        let hasher = ChunkedHasher::<Sha256Hasher>::overlapping_windows(
+            &mut buff_one,
+            WORDSTRING.len() as u64,
+            40,
+            20,
+        )?;
+        let expected_count = hasher.chunk_count();
+        let original_chunks: Vec<Chunk> = hasher.collect();
+        let different_chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::overlapping_windows(
+            &mut buff_two,
+            WORDSTRING_DIFF.len() as u64,
+            40,
+            20,
+        )?
+        .collect();
+        assert_eq!(Some(original_chunks.len() as u64), expected_count);
+        assert_eq!(original_chunks.len(), 23);
+        let diffed_indexes = original_chunks
+            .iter()
+            .filter(|element| !different_chunks.contains(element))
+            .map(|element| element.index)
+            .collect::<Vec<u64>>();
+        assert_eq!(diffed_indexes, vec![2, 3, 10, 11]);
+        Ok(())
+    }
+
+    #[test]
+    fn similarity_digests_are_optional() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let plain: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .collect();
+        assert!(plain.iter().all(|chunk| chunk.similarity.is_none()));
+        let with_similarity: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .with_similarity()
+                .collect();
+        assert!(with_similarity
+            .iter()
+            .all(|chunk| chunk.similarity.is_some()));
+        assert!(plain == with_similarity);
+        Ok(())
+    }
+
+    #[test]
+    fn unallocated_chunks_are_skipped() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        // 40 byte blocks, only the second and last ones allocated
+        let bitmap = allocation::AllocationBitmap::new(vec![0b0000_0010, 0b0000_1000], 40)?;
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .with_allocation_bitmap(bitmap)
+                .collect();
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.index).collect::<Vec<u64>>(),
+            vec![1, 11]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_stream_size_reads_until_eof() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let known: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 35)?
+                .collect();
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let hasher =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, StreamSize::Unknown, 35)?;
+        assert_eq!(hasher.chunk_count(), None);
+        let unknown: Vec<Chunk> = hasher.collect();
+        assert_eq!(unknown.len(), 14);
+        assert!(known == unknown);
+
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(
+            ChunkedHasher::<Sha256Hasher>::dynamic_chunks(&mut buffer, StreamSize::Unknown, 4)
+                .is_err()
+        );
+        assert!(ChunkedHasher::<Sha256Hasher>::overlapping_windows(
+            &mut buffer,
+            StreamSize::Unknown,
+            10,
+            5
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn zero_length_streams_have_no_chunks() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(b"");
+        let hasher = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, 0, 40)?;
+        assert_eq!(hasher.chunk_count(), Some(0));
+        assert_eq!(hasher.count(), 0);
+        assert_eq!(
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, StreamSize::Unknown, 40)?
+                .count(),
+            0
+        );
+        assert_eq!(
+            ChunkedHasher::<Sha256Hasher>::dynamic_chunks(&mut buffer, 0, 4)?.count(),
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stream_shorter_than_hint_stops_early() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(&WORDSTRING.as_bytes()[..95]);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .collect();
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.size).collect::<Vec<u64>>(),
+            vec![40, 40, 15]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn runtime_algorithm_is_not_a_keyed_hasher() -> Result<()> {
+        use hashers::{algorithm::Algorithm, blake3::Blake3Hasher, Hasher};
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let chunked_hasher = ChunkedHasher::with_algorithm(
+            &mut buffer,
+            WORDSTRING.len() as u64,
+            Chunking::Fixed(40),
+            Algorithm::Blake3,
+        )?;
+        assert!(chunked_hasher.hashing.keyed_hasher.is_none());
+        assert_eq!(chunked_hasher.algorithm(), Some(Algorithm::Blake3));
+        let chunks: Vec<Chunk> = chunked_hasher.collect();
+        for chunk in &chunks {
+            let start = chunk.offset as usize;
+            let end = start + chunk.size as usize;
+            assert_eq!(
+                chunk.hash,
+                Blake3Hasher::hash_bytes(&WORDSTRING.as_bytes()[start..end])
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn keyed_hasher_replaces_static_hasher() -> Result<()> {
+        use hashers::{hmac::HmacHasher, KeyedHasher};
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .with_keyed_hasher(HmacHasher::<Sha256Hasher>::new(b"secret"))
+                .collect();
+        let hasher = HmacHasher::<Sha256Hasher>::new(b"secret");
+        for chunk in &chunks {
+            let start = chunk.offset as usize;
+            let end = start + chunk.size as usize;
+            assert_eq!(
+                chunk.hash,
+                hasher.hash_keyed(&WORDSTRING.as_bytes()[start..end])
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn typed_chunks_match_regular_chunks() -> Result<()> {
+        use hashers::xxhash::Xxh64Hasher;
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let regular: Vec<Chunk> =
+            ChunkedHasher::<Xxh64Hasher>::fixed_chunks(&mut buff_one, WORDSTRING.len() as u64, 30)?
+                .collect();
+        let typed: Vec<Chunk<[u8; 8]>> =
+            ChunkedHasher::<Xxh64Hasher>::fixed_chunks(&mut buff_two, WORDSTRING.len() as u64, 30)?
+                .typed()?
+                .collect();
+        assert_eq!(regular.len(), typed.len());
+        for (regular, typed) in regular.iter().zip(typed.iter()) {
+            assert_eq!(ChunkRef::from(regular), ChunkRef::from(typed));
+            assert_eq!(regular.hash, typed.hash);
+        }
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(ChunkedHasher::<Sha256Hasher>::fixed_chunks(
+            &mut buffer,
+            WORDSTRING.len() as u64,
+            30
+        )?
+        .with_keyed_hasher(hashers::hmac::HmacHasher::<Sha256Hasher>::new(b"secret"))
+        .typed()
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn read_buffer_matches_whole_chunks() -> Result<()> {
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let whole: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(
+            &mut buff_one,
+            WORDSTRING.len() as u64,
+            40,
+        )?
+        .collect();
+        let streamed: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buff_two, StreamSize::Unknown, 40)?
+                .with_read_buffer(7)
+                .collect();
+        assert_eq!(whole, streamed);
+        Ok(())
+    }
+
+    #[test]
+    fn dictionary_samples_cover_streamed_and_content_defined_chunks() -> Result<()> {
+        let data = WORDSTRING.repeat(20);
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let mut fixed_samples = dictionary::DictionarySamples::new(1 << 20, 16)?;
+        let mut cdc_samples = dictionary::DictionarySamples::new(1 << 20, 16)?;
+        let fixed: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buff_one, data.len() as u64, 100)?
+                .with_read_buffer(7)
+                .with_dictionary_samples(&mut fixed_samples)
+                .collect();
+        let cdc: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::strategy_chunks(
+            &mut buff_two,
+            data.len() as u64,
+            cdc::FastCdc::new(64, 256, 1024)?,
+        )?
+        .with_dictionary_samples(&mut cdc_samples)
+        .collect();
+        for (chunks, samples) in &[(fixed, fixed_samples), (cdc, cdc_samples)] {
+            assert_eq!(samples.sizes().len(), chunks.len());
+            let mut start = 0;
+            for (chunk, size) in chunks.iter().zip(samples.sizes()) {
+                let offset = chunk.offset as usize;
+                assert_eq!(
+                    &samples.data()[start..start + size],
+                    &data.as_bytes()[offset..offset + size]
+                );
+                start += size;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn domain_separation_binds_position() -> Result<()> {
+        // Two identical chunks must hash differently once bound to their index
+        let data = b"brainstormbrainstorm";
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(data);
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(data);
+        let whole: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buff_one, 20, 10)?
+            .with_domain_separation(b"salt")
+            .collect();
+        let streamed: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buff_two, 20, 10)?
+                .with_domain_separation(b"salt")
+                .with_read_buffer(3)
+                .collect();
+        assert_ne!(whole[0].hash, whole[1].hash);
+        assert_eq!(whole, streamed);
+        assert_eq!(
+            whole[1].hash,
+            domain::hash_chunk::<Sha256Hasher>(b"salt", 1, 10, b"brainstorm")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cdc_chunks_follow_cut_points() -> Result<()> {
+        use hashers::Hasher;
+        use strategy::ChunkingStrategy;
+        let data = WORDSTRING.repeat(20);
+        let cdc = cdc::FastCdc::new(64, 256, 1024)?;
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let known: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::cdc_chunks(
+            &mut buff_one,
+            data.len() as u64,
+            64,
+            256,
+            1024,
+        )?
+        .collect();
+        let unknown: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::cdc_chunks(
+            &mut buff_two,
+            StreamSize::Unknown,
+            64,
+            256,
+            1024,
+        )?
+        .collect();
+        assert_eq!(known, unknown);
+        let mut offset = 0;
+        for (index, chunk) in known.iter().enumerate() {
+            let size = cdc.cut_point(&data.as_bytes()[offset..]);
+            assert_eq!(chunk.index, index as u64);
+            assert_eq!(chunk.offset, offset as u64);
+            assert_eq!(chunk.size, size as u64);
+            assert_eq!(
+                chunk.hash,
+                Sha256Hasher::hash_bytes(&data.as_bytes()[offset..offset + size])
+            );
+            offset += size;
+        }
+        assert_eq!(offset, data.len());
+        Ok(())
+    }
+
+    #[test]
+    fn rabin_chunks_follow_cut_points() -> Result<()> {
+        use strategy::ChunkingStrategy;
+        let data = WORDSTRING.repeat(20);
+        let chunker = rabin::RabinChunker::new(rabin::DEFAULT_POLYNOMIAL, 8, 0x3f, 16, 256)?;
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::rabin_chunks(
+            &mut buffer,
+            StreamSize::Unknown,
+            chunker.clone(),
+        )?
+        .collect();
+        let mut offset = 0;
+        for chunk in &chunks {
+            assert_eq!(chunk.offset, offset as u64);
+            assert_eq!(
+                chunk.size,
+                chunker.cut_point(&data.as_bytes()[offset..]) as u64
+            );
+            offset += chunk.size as usize;
+        }
+        assert_eq!(offset, data.len());
+        Ok(())
+    }
+
+    #[test]
+    fn aligned_chunks_end_on_block_multiples() -> Result<()> {
+        let data = WORDSTRING.repeat(20);
+        let chunker = rabin::RabinChunker::new(rabin::DEFAULT_POLYNOMIAL, 8, 0x3f, 16, 256)?;
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data.as_bytes());
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::rabin_chunks(&mut buffer, data.len() as u64, chunker)?
+                .with_alignment(64)?
+                .collect();
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(rest.iter().all(|chunk| chunk.size % 64 == 0));
+        assert_eq!(last.offset + last.size, data.len() as u64);
+
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let hasher = ChunkedHasher::<Sha256Hasher>::overlapping_windows(&mut buffer, 40, 10, 5)?
+            .with_alignment(8)?;
+        assert_eq!((hasher.chunk_size(), hasher.stride()), (16, 8));
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(
+            ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 10, 25])?
+                .with_alignment(4)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_last_chunk_is_hashed_padded() -> Result<()> {
+        let data = &WORDSTRING.as_bytes()[..40];
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, 40, 16)?.collect();
+        assert_eq!(
+            hex::encode(&chunks[2].hash),
+            "2df7aef1a53972d0c83ff99a2a0415355d262020228ebf56ee230b3e7f079b13"
+        );
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, 40, 16)?
+            .unpadded_last_chunk()
+            .collect();
+        assert_eq!(
+            hex::encode(&chunks[2].hash),
+            "3d8f3df83816962faf541afc96c2b2d0b9d6a8731a28e907401192717f6b759a"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn padded_last_chunk_reports_its_real_size() -> Result<()> {
+        use hashers::Hasher;
+        let data = &WORDSTRING.as_bytes()[..40];
+        let mut padded = data[36..].to_vec();
+        padded.resize(12, 0);
+        for stream_size in &[StreamSize::Known(40), StreamSize::Unknown] {
+            for read_buffer in &[None, Some(5)] {
+                let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+                let mut hasher =
+                    ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, *stream_size, 12)?;
+                if let Some(size) = read_buffer {
+                    hasher = hasher.with_read_buffer(*size);
+                }
+                let chunks: Vec<Chunk> = hasher.collect();
+                assert_eq!(chunks.len(), 4);
+                assert_eq!(chunks[0].hash, Sha256Hasher::hash_bytes(&data[..12]));
+                assert_eq!((chunks[3].offset, chunks[3].size), (36, 4));
+                assert_eq!(chunks[3].hash, Sha256Hasher::hash_bytes(&padded));
+            }
+        }
+        // Streams shorter than a chunk and explicit boundaries aren't padded
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, StreamSize::Unknown, 64)?
+                .collect();
+        assert_eq!(chunks[0].hash, Sha256Hasher::hash_bytes(data));
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 30])?.collect();
+        assert_eq!(chunks[1].hash, Sha256Hasher::hash_bytes(&data[30..]));
+        Ok(())
+    }
+
+    #[test]
+    fn from_boundaries_hashes_the_given_ranges() -> Result<()> {
+        use hashers::Hasher;
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let hasher =
+            ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 10, 25, 26])?;
+        assert_eq!(hasher.chunk_count(), Some(4));
+        assert_eq!(hasher.chunk_size(), 15);
+        let chunks: Vec<Chunk> = hasher.collect();
+        let ranges: Vec<(u64, u64)> = chunks
+            .iter()
+            .map(|chunk| (chunk.offset, chunk.size))
+            .collect();
+        assert_eq!(ranges, vec![(0, 10), (10, 15), (25, 1), (26, 14)]);
+        assert_eq!(
+            chunks[1].hash,
+            Sha256Hasher::hash_bytes(&WORDSTRING.as_bytes()[10..25])
+        );
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(
+            ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 10, 10]).is_err()
+        );
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 40]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn range_hashes_overlapping_chunks() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let all: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, 40, 10)?.collect();
+        for stream_size in [StreamSize::Known(40), StreamSize::Unknown] {
+            let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+            let ranged: Vec<Chunk> =
+                ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, stream_size, 10)?
+                    .with_range(12, 25)?
+                    .collect();
+            assert_eq!(ranged, all[1..3].to_vec());
+        }
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let windows: Vec<u64> =
+            ChunkedHasher::<Sha256Hasher>::overlapping_windows(&mut buffer, 40, 10, 5)?
+                .with_range(12, 13)?
+                .map(|chunk| chunk.index)
+                .collect();
+        assert_eq!(windows, vec![1, 2]);
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let explicit: Vec<u64> =
+            ChunkedHasher::<Sha256Hasher>::from_boundaries(&mut buffer, 40, &[0, 10, 25, 26])?
+                .with_range(25, 27)?
+                .map(|chunk| chunk.offset)
+                .collect();
+        assert_eq!(explicit, vec![25, 26]);
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        assert!(
+            ChunkedHasher::<Sha256Hasher>::cdc_chunks(&mut buffer, 40, 4, 8, 16)?
+                .with_range(12, 25)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_file_chunks_match_fixed_chunks() -> Result<()> {
+        use std::io::Write;
+        let path =
+            std::env::temp_dir().join(format!("chunked-hasher-holes-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        file.set_len(1024 * 1024)?;
+        file.seek(SeekFrom::Start(300 * 1024))?;
+        file.write_all(WORDSTRING.as_bytes())?;
+        let sparse: Result<Vec<Chunk>> =
+            ChunkedHasher::<Sha256Hasher>::sparse_file_chunks(&mut file, 64 * 1024)
+                .map(|chunks| chunks.collect());
+        let fixed: Result<Vec<Chunk>> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut file, 1024 * 1024, 64 * 1024)
+                .map(|chunks| chunks.collect());
+        std::fs::remove_file(&path)?;
+        assert_eq!(sparse?, fixed?);
+        Ok(())
+    }
+
+    #[test]
+    fn subscribers_see_every_chunk_in_order() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 40)?
+                .on_chunk(|chunk| first.push(chunk.clone()))
+                .on_chunk(|chunk| second.push(chunk.index))
+                .collect();
+        assert_eq!(first, chunks);
+        assert_eq!(second, (0..chunks.len() as u64).collect::<Vec<u64>>());
+        Ok(())
+    }
+
+    #[test]
+    fn stream_digests() -> Result<()> {
+        let mut buff_one: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+        let mut buff_two: Cursor<&[u8]> = Cursor::new(WORDSTRING_DIFF.as_bytes());
+        let mut original_chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(
+            &mut buff_one,
+            WORDSTRING.len() as u64,
+            40,
+        )?
+        .collect();
+        let different_chunks: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(
+            &mut buff_two,
+            WORDSTRING_DIFF.len() as u64,
+            40,
+        )?
+        .collect();
+        let digest = stream_digest::<Sha256Hasher>(&original_chunks);
+        assert_ne!(digest, stream_digest::<Sha256Hasher>(&different_chunks));
+        original_chunks.reverse();
+        assert_eq!(digest, stream_digest::<Sha256Hasher>(&original_chunks));
+        Ok(())
+    }
 }
@@ -0,0 +1,475 @@
+//! Append-only manifest log for long running jobs
+//!
+//! Chunk records are appended to the log as chunks are produced, with a
+//! [`ChainState`](../append_only/struct.ChainState.html) checkpoint every few
+//! chunks. After a crash at most the records written since the last flush are
+//! lost, and [`replay`](fn.replay.html) rebuilds the manifest from whatever
+//! made it to disk, dropping a torn final record and verifying every
+//! checkpoint along the way. [`ManifestLogWriter::resume`](struct.ManifestLogWriter.html#method.resume)
+//! then continues appending to the recovered log.
+//!
+//! Every record is a line, chunks as `c index/offset/size/hash`, followed by
+//! `/similarity` in hex for chunks carrying a similarity digest, and
+//! checkpoints as `k` followed by the chain state. Logs of truncated digests
+//! start with `t` followed by the amount of digest bytes kept, and logs of
+//! extendable-output digests with `o` followed by the output length.
+
+use crate::{append_only::ChainState, hashers::Hasher, Chunk};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
+    marker::PhantomData,
+};
+
+/// Writer appending chunk records and checkpoints to a manifest log
+pub struct ManifestLogWriter<W, H> {
+    writer: W,
+    /// Amount of chunks between two checkpoints
+    checkpoint_interval: u64,
+    /// Commitment over all chunks appended so far
+    state: ChainState,
+    /// Amount of chunks appended since the last checkpoint
+    pending: u64,
+    _marker: PhantomData<H>,
+}
+
+impl<W: Write, H: Hasher> ManifestLogWriter<W, H> {
+    /// Instantiate a manifest log writer
+    /// # Arguments
+    /// * `writer` - log to append to, usually a file opened for appending
+    /// * `chunk_size` - fixed chunk size the stream is chunked with
+    /// * `checkpoint_interval` - amount of chunks between two checkpoints
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, manifest_log::{replay, ManifestLogWriter}, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let mut log = ManifestLogWriter::<_, Sha256Hasher>::new(Vec::new(), 10, 2)?;
+    /// for chunk in ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)? {
+    ///     log.append(&chunk)?;
+    /// }
+    /// let log = log.finish()?;
+    /// assert_eq!(replay::<Sha256Hasher>(&log[..])?.chunks.len(), 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(writer: W, chunk_size: u64, checkpoint_interval: u64) -> Result<Self> {
+        ensure!(
+            checkpoint_interval > 0,
+            "Checkpoint interval must be greater than zero"
+        );
+        Ok(Self {
+            writer,
+            checkpoint_interval,
+            state: ChainState::new(chunk_size),
+            pending: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Record that chunk digests are truncated, must be called before the
+    /// first chunk is appended
+    /// # Arguments
+    /// * `length` - amount of leading digest bytes kept, see
+    ///   [`ChunkedHasher::with_truncation`](../struct.ChunkedHasher.html#method.with_truncation)
+    pub fn with_truncation(mut self, length: usize) -> Result<Self> {
+        ensure!(
+            self.state.chunk_count == 0,
+            "Truncation must be recorded before the first chunk"
+        );
+        ensure!(length > 0, "Truncation length must be greater than zero");
+        writeln!(self.writer, "t {}", length)?;
+        self.writer.flush()?;
+        Ok(self)
+    }
+
+    /// Record the output length of an extendable-output hasher, must be
+    /// called before the first chunk is appended
+    /// # Arguments
+    /// * `length` - amount of digest bytes of every chunk, see
+    ///   [`Xof`](../hashers/xof/struct.Xof.html)
+    pub fn with_output_length(mut self, length: usize) -> Result<Self> {
+        ensure!(
+            self.state.chunk_count == 0,
+            "Output length must be recorded before the first chunk"
+        );
+        ensure!(length > 0, "Output length must be greater than zero");
+        writeln!(self.writer, "o {}", length)?;
+        self.writer.flush()?;
+        Ok(self)
+    }
+
+    /// Append a chunk record, and a checkpoint if one is due
+    /// # Arguments
+    /// * `chunk` - the next chunk of the stream
+    pub fn append(&mut self, chunk: &Chunk) -> Result<()> {
+        write!(
+            self.writer,
+            "c {}/{}/{}/{}",
+            chunk.index,
+            chunk.offset,
+            chunk.size,
+            hex::encode(&chunk.hash)
+        )?;
+        match chunk.similarity {
+            Some(similarity) => writeln!(self.writer, "/{:016x}", similarity)?,
+            None => writeln!(self.writer)?,
+        }
+        self.state.update::<H>(chunk);
+        self.pending += 1;
+        if self.pending == self.checkpoint_interval {
+            self.checkpoint()?;
+        } else {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Append a checkpoint covering all chunks appended so far
+    pub fn checkpoint(&mut self) -> Result<()> {
+        writeln!(self.writer, "k {}", self.state)?;
+        self.writer.flush()?;
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Append a final checkpoint, returning the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        if self.pending > 0 {
+            self.checkpoint()?;
+        }
+        Ok(self.writer)
+    }
+}
+
+impl<H: Hasher> ManifestLogWriter<File, H> {
+    /// Continue a log after a crash, dropping a torn final record so new
+    /// records start on a line of their own, returning the writer along with
+    /// what was recovered from the log
+    /// # Arguments
+    /// * `file` - log to continue, opened for reading and writing
+    /// * `chunk_size` - fixed chunk size the stream is chunked with
+    /// * `checkpoint_interval` - amount of chunks between two checkpoints
+    pub fn resume(
+        mut file: File,
+        chunk_size: u64,
+        checkpoint_interval: u64,
+    ) -> Result<(Self, Replay)> {
+        file.seek(SeekFrom::Start(0))?;
+        let replay = replay::<H>(BufReader::new(&mut file))?;
+        if let Some(checkpoint) = &replay.checkpoint {
+            ensure!(
+                checkpoint.chunk_size == chunk_size,
+                "Log was written with a chunk size of {}",
+                checkpoint.chunk_size
+            );
+        }
+        file.set_len(replay.length)?;
+        file.seek(SeekFrom::End(0))?;
+        let mut writer = Self::new(file, chunk_size, checkpoint_interval)?;
+        for chunk in &replay.chunks {
+            writer.state.update::<H>(chunk);
+        }
+        writer.pending = writer.state.chunk_count
+            - replay
+                .checkpoint
+                .as_ref()
+                .map_or(0, |checkpoint| checkpoint.chunk_count);
+        Ok((writer, replay))
+    }
+}
+
+/// Manifest recovered from a log
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    /// All complete chunk records, in log order
+    pub chunks: Vec<Chunk>,
+    /// Last checkpoint found in the log
+    pub checkpoint: Option<ChainState>,
+    /// Whether the log ended in a partially written record, which was dropped
+    pub torn: bool,
+    /// Amount of log bytes up to the end of the last complete record
+    pub length: u64,
+    /// Amount of leading digest bytes kept, if digests are truncated
+    pub truncation: Option<usize>,
+    /// Amount of digest bytes of every chunk, if produced by an
+    /// extendable-output hasher
+    pub output_length: Option<usize>,
+}
+
+/// Rebuild a manifest from a log written by
+/// [`ManifestLogWriter`](struct.ManifestLogWriter.html)
+///
+/// Fails if a complete record is malformed or a checkpoint doesn't match the
+/// chunk records before it, as that means the log was corrupted rather than
+/// cut short.
+/// # Arguments
+/// * `reader` - the log to replay
+pub fn replay<H: Hasher>(mut reader: impl BufRead) -> Result<Replay> {
+    let mut replay = Replay {
+        chunks: Vec::new(),
+        checkpoint: None,
+        torn: false,
+        length: 0,
+        truncation: None,
+        output_length: None,
+    };
+    let mut state = ChainState::new(0);
+    let mut line = String::new();
+    for number in 1.. {
+        line.clear();
+        let read_bytes = reader.read_line(&mut line)?;
+        if read_bytes == 0 {
+            break;
+        }
+        if !line.ends_with('\n') {
+            replay.torn = true;
+            break;
+        }
+        replay.length += read_bytes as u64;
+        let record = line.trim_end();
+        if let Some(chunk) = record.strip_prefix("c ") {
+            let chunk =
+                parse_chunk(chunk).with_context(|| format!("Invalid chunk record {}", number))?;
+            if let Some(length) = replay.truncation {
+                ensure!(
+                    chunk.hash.len() <= length,
+                    "Chunk record {} has a digest longer than the truncation",
+                    number
+                );
+            }
+            if let Some(length) = replay.output_length {
+                ensure!(
+                    chunk.hash.len() == length,
+                    "Chunk record {} has a digest not matching the output length",
+                    number
+                );
+            }
+            state.update::<H>(&chunk);
+            replay.chunks.push(chunk);
+        } else if let Some(checkpoint) = record.strip_prefix("k ") {
+            let checkpoint: ChainState = checkpoint
+                .parse()
+                .with_context(|| format!("Invalid checkpoint record {}", number))?;
+            state.chunk_size = checkpoint.chunk_size;
+            ensure!(
+                checkpoint == state,
+                "Checkpoint record {} doesn't match the chunk records before it",
+                number
+            );
+            replay.checkpoint = Some(checkpoint);
+        } else if let Some(length) = record.strip_prefix("t ") {
+            ensure!(
+                replay.chunks.is_empty(),
+                "Truncation record {} follows chunk records",
+                number
+            );
+            let length: usize = length
+                .parse()
+                .with_context(|| format!("Invalid truncation record {}", number))?;
+            ensure!(length > 0, "Truncation record {} keeps no bytes", number);
+            replay.truncation = Some(length);
+        } else if let Some(length) = record.strip_prefix("o ") {
+            ensure!(
+                replay.chunks.is_empty(),
+                "Output length record {} follows chunk records",
+                number
+            );
+            replay.output_length = Some(
+                length
+                    .parse()
+                    .with_context(|| format!("Invalid output length record {}", number))?,
+            );
+        } else {
+            bail!("Unknown record {}", number);
+        }
+    }
+    Ok(replay)
+}
+
+fn parse_chunk(record: &str) -> Result<Chunk> {
+    let mut fields = record.split('/');
+    let mut next_field = |name: &str| {
+        fields
+            .next()
+            .ok_or_else(|| anyhow!("Missing {} in chunk record", name))
+    };
+    let index = next_field("index")?.parse()?;
+    let offset = next_field("offset")?.parse()?;
+    let size = next_field("size")?.parse()?;
+    let hash = hex::decode(next_field("hash")?)?;
+    let similarity = match fields.next() {
+        Some(similarity) => Some(u64::from_str_radix(similarity, 16)?),
+        None => None,
+    };
+    ensure!(fields.next().is_none(), "Trailing data in chunk record");
+    Ok(Chunk {
+        index,
+        offset,
+        size,
+        hash,
+        similarity,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    fn write_log() -> Result<(Vec<Chunk>, Vec<u8>)> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 10)?
+                .collect();
+        let mut log = ManifestLogWriter::<_, Sha256Hasher>::new(Vec::new(), 10, 2)?;
+        for chunk in &chunks {
+            log.append(chunk)?;
+        }
+        Ok((chunks, log.finish()?))
+    }
+
+    #[test]
+    fn replay_complete_log() -> Result<()> {
+        let (chunks, log) = write_log()?;
+        let replay = replay::<Sha256Hasher>(&log[..])?;
+        assert_eq!(replay.chunks, chunks);
+        assert!(!replay.torn);
+        assert_eq!(replay.checkpoint.map(|state| state.chunk_count), Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn replay_drops_torn_record() -> Result<()> {
+        let (chunks, log) = write_log()?;
+        // Cut the log in the middle of the record of the last chunk
+        let start = String::from_utf8(log.clone())?.find("c 4/").unwrap();
+        let replay = replay::<Sha256Hasher>(&log[..start + 10])?;
+        assert!(replay.torn);
+        assert_eq!(replay.chunks, chunks[..4]);
+        assert_eq!(replay.checkpoint.map(|state| state.chunk_count), Some(4));
+        Ok(())
+    }
+
+    #[test]
+    fn replay_records_truncation() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 10)?
+                .with_truncation(8)?
+                .collect();
+        let mut log =
+            ManifestLogWriter::<_, Sha256Hasher>::new(Vec::new(), 10, 2)?.with_truncation(8)?;
+        for chunk in &chunks {
+            log.append(chunk)?;
+        }
+        let log = log.finish()?;
+        let replayed = replay::<Sha256Hasher>(&log[..])?;
+        assert_eq!(replayed.truncation, Some(8));
+        assert_eq!(replayed.chunks, chunks);
+        let (_, full) = write_log()?;
+        let mut mislabeled = b"t 8\n".to_vec();
+        mislabeled.extend(full);
+        assert!(replay::<Sha256Hasher>(&mislabeled[..]).is_err());
+        assert!(replay::<Sha256Hasher>(&b"t 0\n"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn replay_records_output_length() -> Result<()> {
+        use crate::hashers::{shake::Shake128Hasher, xof::Xof};
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Shake128Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 10)?
+                .with_keyed_hasher(Xof::<Shake128Hasher>::new(16)?)
+                .collect();
+        let mut log = ManifestLogWriter::<_, Shake128Hasher>::new(Vec::new(), 10, 2)?
+            .with_output_length(16)?;
+        for chunk in &chunks {
+            log.append(chunk)?;
+        }
+        let log = log.finish()?;
+        let replayed = replay::<Shake128Hasher>(&log[..])?;
+        assert_eq!(replayed.output_length, Some(16));
+        assert_eq!(replayed.chunks, chunks);
+        let (_, full) = write_log()?;
+        let mut mislabeled = b"o 16\n".to_vec();
+        mislabeled.extend(full);
+        assert!(replay::<Sha256Hasher>(&mislabeled[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn replay_keeps_similarity_digests() -> Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let chunks: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 10)?
+                .with_similarity()
+                .collect();
+        let mut log = ManifestLogWriter::<_, Sha256Hasher>::new(Vec::new(), 10, 2)?;
+        for chunk in &chunks {
+            log.append(chunk)?;
+        }
+        let replayed = replay::<Sha256Hasher>(&log.finish()?[..])?;
+        let similarities = |chunks: &[Chunk]| -> Vec<Option<u64>> {
+            chunks.iter().map(|chunk| chunk.similarity).collect()
+        };
+        assert_eq!(similarities(&replayed.chunks), similarities(&chunks));
+        assert!(replayed
+            .chunks
+            .iter()
+            .all(|chunk| chunk.similarity.is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn resume_after_crash() -> Result<()> {
+        use std::{fs::OpenOptions, io::Read};
+        let (chunks, log) = write_log()?;
+        // Crash in the middle of the record of the fourth chunk
+        let torn = String::from_utf8(log.clone())?.find("c 3/").unwrap() + 10;
+        let path = std::env::temp_dir().join(format!(
+            "chunked-hasher-manifest-log-{}",
+            std::process::id()
+        ));
+        std::fs::write(&path, &log[..torn])?;
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let (mut writer, recovered) = ManifestLogWriter::<_, Sha256Hasher>::resume(file, 10, 2)?;
+        assert!(recovered.torn);
+        assert_eq!(recovered.chunks, chunks[..3]);
+        for chunk in &chunks[3..] {
+            writer.append(chunk)?;
+        }
+        writer.finish()?;
+        let mut resumed = Vec::new();
+        File::open(&path)?.read_to_end(&mut resumed)?;
+        let replayed = replay::<Sha256Hasher>(&resumed[..])?;
+        assert!(!replayed.torn);
+        assert_eq!(replayed.chunks, chunks);
+        assert_eq!(replayed.checkpoint.map(|state| state.chunk_count), Some(5));
+        // The chunk size of the log must match
+        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mismatch = ManifestLogWriter::<_, Sha256Hasher>::resume(file, 20, 2);
+        std::fs::remove_file(&path)?;
+        assert!(mismatch.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn replay_rejects_corrupted_records() -> Result<()> {
+        let (_, log) = write_log()?;
+        let mut corrupted = String::from_utf8(log)?;
+        corrupted = corrupted.replacen("c 1/10/10/", "c 1/10/11/", 1);
+        assert!(replay::<Sha256Hasher>(corrupted.as_bytes()).is_err());
+        Ok(())
+    }
+}
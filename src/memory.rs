@@ -0,0 +1,201 @@
+//! Page granular hashing of memory snapshots
+//!
+//! Process core dumps and VM memory images are hashed page by page, with the
+//! page number as chunk index. Memory snapshots are mostly zero pages, which
+//! [`PageMap`](struct.PageMap.html) stores sparsely: zero pages are detected
+//! without hashing and only recorded as absent. Comparing two page maps
+//! yields the dirty pages, as needed for live migration style transfers.
+
+use crate::{hashers::Hasher, layout, read_full, Chunk, ReadAndSeek};
+use anyhow::{ensure, Result};
+use std::{collections::BTreeMap, io::SeekFrom};
+
+/// Size of a memory page
+pub const PAGE_SIZE: u64 = 4096;
+
+/// Page hashes of a memory snapshot, without entries for zero pages
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageMap {
+    /// Size of the snapshot
+    size: u64,
+    /// Hashes of the pages containing non-zero bytes, by page number
+    pages: BTreeMap<u64, Vec<u8>>,
+}
+
+impl PageMap {
+    /// Amount of pages in the snapshot, the last one may be partial
+    pub fn page_count(&self) -> u64 {
+        layout::chunk_count(self.size, PAGE_SIZE, PAGE_SIZE)
+    }
+
+    /// Amount of pages containing only zero bytes
+    pub fn zero_page_count(&self) -> u64 {
+        self.page_count() - self.pages.len() as u64
+    }
+
+    /// Hash of a page, `None` for zero pages and pages past the end
+    /// # Arguments
+    /// * `page` - page number
+    pub fn page_hash(&self, page: u64) -> Option<&[u8]> {
+        self.pages.get(&page).map(Vec::as_slice)
+    }
+
+    /// Pages differing between this snapshot and a newer one, including
+    /// pages present in only one of them
+    /// # Arguments
+    /// * `newer` - page map of the newer snapshot, hashed with the same hasher
+    pub fn dirty_pages(&self, newer: &PageMap) -> Vec<u64> {
+        let page_count = self.page_count().max(newer.page_count());
+        let common = self.page_count().min(newer.page_count());
+        // Only non-zero pages can differ within the common range
+        let mut candidates: Vec<u64> = self
+            .pages
+            .keys()
+            .chain(newer.pages.keys())
+            .cloned()
+            .filter(|page| *page < common)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        let mut dirty: Vec<u64> = candidates
+            .into_iter()
+            .filter(|page| self.pages.get(page) != newer.pages.get(page))
+            .collect();
+        // The last common page is partial in at least one snapshot if the
+        // snapshot sizes differ
+        if common > 0 && self.page_size(common - 1) != newer.page_size(common - 1) {
+            dirty.push(common - 1);
+            dirty.dedup();
+        }
+        dirty.extend(common..page_count);
+        dirty
+    }
+
+    fn page_size(&self, page: u64) -> u64 {
+        PAGE_SIZE.min(self.size - page * PAGE_SIZE)
+    }
+
+    /// Expand into regular chunks, one per page, with zero pages hashed
+    ///
+    /// As with [`ChunkedHasher::fixed_chunks`](../struct.ChunkedHasher.html#method.fixed_chunks),
+    /// a partial last page is hashed zero-padded to the page size.
+    /// # Arguments
+    /// * `H` - hasher the page map was produced with
+    pub fn chunks<H: Hasher>(&self) -> Vec<Chunk> {
+        let zero_page = H::hash_bytes(&[0; PAGE_SIZE as usize]);
+        (0..self.page_count())
+            .map(|page| {
+                let offset = page * PAGE_SIZE;
+                let size = self.page_size(page);
+                let hash = match self.pages.get(&page) {
+                    Some(hash) => hash.clone(),
+                    None if size + layout::padding(offset, size, PAGE_SIZE) == PAGE_SIZE => {
+                        zero_page.clone()
+                    }
+                    None => H::hash_bytes(&vec![0; size as usize]),
+                };
+                Chunk {
+                    index: page,
+                    offset,
+                    size,
+                    hash,
+                    similarity: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Hash a memory snapshot page by page
+/// # Arguments
+/// * `snapshot` - the snapshot to hash
+/// * `size` - size of the snapshot
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::xxhash::Xxh3Hasher, memory::{hash_pages, PAGE_SIZE}};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// let mut memory = vec![0u8; 4 * PAGE_SIZE as usize];
+/// memory[PAGE_SIZE as usize] = 1;
+/// let before = hash_pages::<Xxh3Hasher>(&mut Cursor::new(&memory), memory.len() as u64)?;
+/// assert_eq!(before.zero_page_count(), 3);
+/// memory[3 * PAGE_SIZE as usize + 10] = 1;
+/// let after = hash_pages::<Xxh3Hasher>(&mut Cursor::new(&memory), memory.len() as u64)?;
+/// assert_eq!(before.dirty_pages(&after), vec![3]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn hash_pages<H: Hasher>(snapshot: &mut dyn ReadAndSeek, size: u64) -> Result<PageMap> {
+    snapshot.seek(SeekFrom::Start(0))?;
+    let mut pages = BTreeMap::new();
+    let mut buf = [0u8; PAGE_SIZE as usize];
+    let mut offset = 0;
+    while offset < size {
+        let length = PAGE_SIZE.min(size - offset) as usize;
+        let read_bytes = read_full(snapshot, &mut buf[..length])?;
+        ensure!(
+            read_bytes == length,
+            "Snapshot ended at {} before its size of {}",
+            offset + read_bytes as u64,
+            size
+        );
+        if buf[..length].iter().any(|byte| *byte != 0) {
+            let padding = layout::padding(offset, length as u64, PAGE_SIZE) as usize;
+            buf[length..length + padding]
+                .iter_mut()
+                .for_each(|byte| *byte = 0);
+            pages.insert(offset / PAGE_SIZE, H::hash_bytes(&buf[..length + padding]));
+        }
+        offset += length as u64;
+    }
+    Ok(PageMap { size, pages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    fn snapshot(dirty: &[usize], size: usize) -> Vec<u8> {
+        let mut memory = vec![0u8; size];
+        for offset in dirty {
+            memory[*offset] = 0xaa;
+        }
+        memory
+    }
+
+    #[test]
+    fn chunks_match_chunked_hasher() -> Result<()> {
+        let memory = snapshot(&[5, 9000], 3 * PAGE_SIZE as usize + 100);
+        let map = hash_pages::<Sha256Hasher>(&mut Cursor::new(&memory), memory.len() as u64)?;
+        assert_eq!(map.page_count(), 4);
+        assert_eq!(map.zero_page_count(), 2);
+        assert!(map.page_hash(1).is_none());
+        let mut buffer = Cursor::new(&memory);
+        let expected: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(
+            &mut buffer,
+            memory.len() as u64,
+            PAGE_SIZE,
+        )?
+        .collect();
+        assert_eq!(map.chunks::<Sha256Hasher>(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn dirty_pages_cover_changes_and_growth() -> Result<()> {
+        let size = 4 * PAGE_SIZE as usize;
+        let before = snapshot(&[10, 2 * PAGE_SIZE as usize], size);
+        let after = snapshot(&[10, PAGE_SIZE as usize + 1], size + 10);
+        let before = hash_pages::<Sha256Hasher>(&mut Cursor::new(&before), size as u64)?;
+        let after = hash_pages::<Sha256Hasher>(&mut Cursor::new(&after), size as u64 + 10)?;
+        assert_eq!(before.dirty_pages(&after), vec![1, 2, 4]);
+        assert_eq!(after.dirty_pages(&before), vec![1, 2, 4]);
+        assert!(before.dirty_pages(&before).is_empty());
+        Ok(())
+    }
+}
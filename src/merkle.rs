@@ -0,0 +1,100 @@
+//! Merkle roots over the chunk hashes of a stream
+//!
+//! The tree is built from stored chunk hashes alone, so existing manifests can
+//! be upgraded to root based verification without reading the data again.
+//! Leaves and inner nodes are domain separated as in RFC 6962,
+//! `leaf = H(0x00 || chunk hash)` and `node = H(0x01 || left || right)`, with
+//! the left subtree holding the largest power of two of leaves.
+
+use crate::{hashers::Hasher, Chunk};
+
+/// Merkle root over the chunks of a stream
+///
+/// Zero-length streams produce no chunks, their root is defined as the hash
+/// of empty input.
+/// # Arguments
+/// * `chunks` - all chunks of the stream, in any order
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, merkle::merkle_root, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// assert_eq!(merkle_root::<Sha256Hasher>(&chunks).len(), 32);
+/// # Ok(())
+/// # }
+/// ```
+pub fn merkle_root<H: Hasher>(chunks: &[Chunk]) -> Vec<u8> {
+    let mut sorted: Vec<&Chunk> = chunks.iter().collect();
+    sorted.sort_by_key(|chunk| chunk.index);
+    if sorted.is_empty() {
+        return H::hash_bytes(&[]);
+    }
+    let leaves: Vec<Vec<u8>> = sorted
+        .iter()
+        .map(|chunk| prefixed::<H>(0x00, &[&chunk.hash]))
+        .collect();
+    subtree_root::<H>(&leaves)
+}
+
+fn subtree_root<H: Hasher>(leaves: &[Vec<u8>]) -> Vec<u8> {
+    if leaves.len() == 1 {
+        return leaves[0].clone();
+    }
+    let split = 1 << (63 - (leaves.len() as u64 - 1).leading_zeros());
+    let (left, right) = leaves.split_at(split);
+    prefixed::<H>(0x01, &[&subtree_root::<H>(left), &subtree_root::<H>(right)])
+}
+
+pub(crate) fn prefixed<H: Hasher>(prefix: u8, parts: &[&[u8]]) -> Vec<u8> {
+    let mut input = vec![prefix];
+    for part in parts {
+        input.extend_from_slice(part);
+    }
+    H::hash_bytes(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::Sha256Hasher;
+
+    fn chunk(index: u64, hash: &[u8]) -> Chunk {
+        Chunk {
+            index,
+            offset: index,
+            size: 1,
+            hash: hash.to_vec(),
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn tree_shape() {
+        let chunks: Vec<Chunk> = (0..3u8).map(|i| chunk(u64::from(i), &[i])).collect();
+        let leaf = |i: u8| prefixed::<Sha256Hasher>(0x00, &[&[i]]);
+        let node = |left: &[u8], right: &[u8]| prefixed::<Sha256Hasher>(0x01, &[left, right]);
+        assert_eq!(
+            merkle_root::<Sha256Hasher>(&chunks),
+            node(&node(&leaf(0), &leaf(1)), &leaf(2))
+        );
+        assert_eq!(merkle_root::<Sha256Hasher>(&chunks[..1]), leaf(0));
+        let mut reversed = chunks.clone();
+        reversed.reverse();
+        assert_eq!(
+            merkle_root::<Sha256Hasher>(&reversed),
+            merkle_root::<Sha256Hasher>(&chunks)
+        );
+        assert_eq!(
+            merkle_root::<Sha256Hasher>(&[]),
+            Sha256Hasher::hash_bytes(&[])
+        );
+    }
+}
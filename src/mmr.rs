@@ -0,0 +1,234 @@
+//! Merkle mountain range accumulator over chunk hashes
+//!
+//! Appending a chunk to a [`MerkleMountainRange`](struct.MerkleMountainRange.html)
+//! only merges the perfect subtrees ("peaks") it completes, so roots and
+//! inclusion proofs of append-only streams such as logs stay cheap while they
+//! grow. Nodes are domain separated like [`merkle_root`](../merkle/fn.merkle_root.html)
+//! and the peaks are bagged right to left, which makes the root equal to the
+//! Merkle root over the same chunks.
+
+use crate::{hashers::Hasher, merkle::prefixed};
+use anyhow::{ensure, Result};
+use std::marker::PhantomData;
+
+/// Merkle mountain range over chunk hashes, hashed with `H`
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, merkle::merkle_root, mmr::MerkleMountainRange, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let mut mmr = MerkleMountainRange::<Sha256Hasher>::new();
+/// for chunk in &chunks {
+///     mmr.append(&chunk.hash);
+/// }
+/// assert_eq!(mmr.root(), merkle_root::<Sha256Hasher>(&chunks));
+/// let proof = mmr.proof(2)?;
+/// assert!(proof.verify::<Sha256Hasher>(&chunks[2].hash, &mmr.root()));
+/// # Ok(())
+/// # }
+/// ```
+pub struct MerkleMountainRange<H> {
+    /// Nodes by height, `levels[h][i]` covers leaves `i * 2^h..(i + 1) * 2^h`
+    levels: Vec<Vec<Vec<u8>>>,
+    _marker: PhantomData<H>,
+}
+
+/// Inclusion proof of a chunk hash in a
+/// [`MerkleMountainRange`](struct.MerkleMountainRange.html) root
+#[derive(Clone, Debug, PartialEq)]
+pub struct MmrProof {
+    /// Index of the proven leaf
+    pub leaf_index: u64,
+    /// Sibling nodes from the leaf up to its peak
+    pub siblings: Vec<Vec<u8>>,
+    /// Peaks left of the leaf's peak, highest first
+    pub left_peaks: Vec<Vec<u8>>,
+    /// Bagged peaks right of the leaf's peak, if any
+    pub right_peaks: Option<Vec<u8>>,
+}
+
+impl<H: Hasher> MerkleMountainRange<H> {
+    /// Instantiate an empty accumulator
+    pub fn new() -> Self {
+        Self {
+            levels: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Amount of appended chunk hashes
+    pub fn leaf_count(&self) -> u64 {
+        self.levels.first().map_or(0, |leaves| leaves.len() as u64)
+    }
+
+    /// Append the hash of the next chunk
+    /// # Arguments
+    /// * `chunk_hash` - hash of the chunk
+    pub fn append(&mut self, chunk_hash: &[u8]) {
+        let mut node = prefixed::<H>(0x00, &[chunk_hash]);
+        let mut height = 0;
+        loop {
+            if self.levels.len() == height {
+                self.levels.push(Vec::new());
+            }
+            let level = &mut self.levels[height];
+            level.push(node);
+            if level.len() % 2 == 1 {
+                break;
+            }
+            node = prefixed::<H>(0x01, &[&level[level.len() - 2], &level[level.len() - 1]]);
+            height += 1;
+        }
+    }
+
+    /// Root over all appended chunk hashes, the hash of empty input if
+    /// nothing was appended
+    pub fn root(&self) -> Vec<u8> {
+        let peaks = self.peaks();
+        match bag::<H>(&peaks) {
+            Some(root) => root,
+            None => H::hash_bytes(&[]),
+        }
+    }
+
+    /// Inclusion proof of an appended chunk hash
+    /// # Arguments
+    /// * `leaf_index` - index of the chunk hash, in append order
+    pub fn proof(&self, leaf_index: u64) -> Result<MmrProof> {
+        let leaf_count = self.leaf_count();
+        ensure!(
+            leaf_index < leaf_count,
+            "Leaf {} is out of bounds of {} leaves",
+            leaf_index,
+            leaf_count
+        );
+        // Peaks cover the set bits of the leaf count, highest first
+        let mut peak_start = 0;
+        let mut peak_height = 0;
+        let mut peak_index = 0;
+        for (index, height) in peak_heights(leaf_count).enumerate() {
+            if leaf_index < peak_start + (1 << height) {
+                peak_height = height;
+                peak_index = index;
+                break;
+            }
+            peak_start += 1 << height;
+        }
+        let siblings = (0..peak_height)
+            .map(|height| self.levels[height][((leaf_index >> height) ^ 1) as usize].clone())
+            .collect();
+        let peaks = self.peaks();
+        Ok(MmrProof {
+            leaf_index,
+            siblings,
+            left_peaks: peaks[..peak_index].to_vec(),
+            right_peaks: bag::<H>(&peaks[peak_index + 1..]),
+        })
+    }
+
+    fn peaks(&self) -> Vec<Vec<u8>> {
+        let leaf_count = self.leaf_count();
+        peak_heights(leaf_count)
+            .map(|height| self.levels[height][((leaf_count >> height) - 1) as usize].clone())
+            .collect()
+    }
+}
+
+impl<H: Hasher> Default for MerkleMountainRange<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MmrProof {
+    /// Whether the proof shows the chunk hash is included in the root
+    /// # Arguments
+    /// * `chunk_hash` - hash of the proven chunk
+    /// * `root` - root of the merkle mountain range, hashed with `H`
+    pub fn verify<H: Hasher>(&self, chunk_hash: &[u8], root: &[u8]) -> bool {
+        let mut node = prefixed::<H>(0x00, &[chunk_hash]);
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            node = if (self.leaf_index >> height) & 1 == 0 {
+                prefixed::<H>(0x01, &[&node, sibling])
+            } else {
+                prefixed::<H>(0x01, &[sibling, &node])
+            };
+        }
+        if let Some(right_peaks) = &self.right_peaks {
+            node = prefixed::<H>(0x01, &[&node, right_peaks]);
+        }
+        for peak in self.left_peaks.iter().rev() {
+            node = prefixed::<H>(0x01, &[peak, &node]);
+        }
+        node == root
+    }
+}
+
+/// Heights of the peaks of a range with the given amount of leaves, highest
+/// first
+fn peak_heights(leaf_count: u64) -> impl Iterator<Item = usize> {
+    (0..64)
+        .rev()
+        .filter(move |height| leaf_count & (1 << height) != 0)
+}
+
+/// Bag peaks right to left, `None` if there are none
+fn bag<H: Hasher>(peaks: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let (last, rest) = peaks.split_last()?;
+    Some(rest.iter().rev().fold(last.clone(), |bagged, peak| {
+        prefixed::<H>(0x01, &[peak, &bagged])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, merkle::merkle_root, Chunk};
+
+    fn chunk(index: u64) -> Chunk {
+        Chunk {
+            index,
+            offset: index,
+            size: 1,
+            hash: vec![index as u8],
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn roots_match_merkle_roots() {
+        let mut mmr = MerkleMountainRange::<Sha256Hasher>::new();
+        assert_eq!(mmr.root(), merkle_root::<Sha256Hasher>(&[]));
+        let mut chunks = Vec::new();
+        for index in 0..13 {
+            chunks.push(chunk(index));
+            mmr.append(&chunks[index as usize].hash);
+            assert_eq!(mmr.root(), merkle_root::<Sha256Hasher>(&chunks));
+        }
+        assert_eq!(mmr.leaf_count(), 13);
+    }
+
+    #[test]
+    fn proofs_verify_every_leaf() -> Result<()> {
+        let mut mmr = MerkleMountainRange::<Sha256Hasher>::new();
+        for index in 0..11 {
+            mmr.append(&[index as u8]);
+        }
+        let root = mmr.root();
+        for index in 0..11 {
+            let proof = mmr.proof(index)?;
+            assert!(proof.verify::<Sha256Hasher>(&[index as u8], &root));
+            assert!(!proof.verify::<Sha256Hasher>(&[0xff], &root));
+        }
+        assert!(mmr.proof(11).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Self-describing chunk hashes in the multihash format
+//!
+//! A multihash is the unsigned varint multicodec code of the algorithm,
+//! followed by the unsigned varint digest length and the digest itself, as
+//! used by IPFS and adjacent tooling. Truncated digests keep their shorter
+//! length.
+
+use crate::{hashers::algorithm::Algorithm, Chunk};
+use anyhow::{anyhow, ensure, Result};
+use std::fmt;
+
+/// Encode a digest as multihash
+/// # Arguments
+/// * `algorithm` - algorithm that produced the digest
+/// * `digest` - the digest
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::{algorithm::Algorithm, sha2::Sha256Hasher, Hasher}, multihash};
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// let encoded = multihash::encode(Algorithm::Sha256, &Sha256Hasher::hash_bytes(b"brainstorm"))?;
+/// assert_eq!(&encoded[..2], &[0x12, 0x20]);
+/// assert_eq!(multihash::decode(&encoded)?.0, Algorithm::Sha256);
+/// # Ok(())
+/// # }
+/// ```
+pub fn encode(algorithm: Algorithm, digest: &[u8]) -> Result<Vec<u8>> {
+    let code = algorithm
+        .multihash_code()
+        .ok_or_else(|| anyhow!("{} has no multihash code", algorithm))?;
+    let mut encoded = Vec::with_capacity(digest.len() + 4);
+    write_varint(&mut encoded, code);
+    write_varint(&mut encoded, digest.len() as u64);
+    encoded.extend_from_slice(digest);
+    Ok(encoded)
+}
+
+/// Decode a multihash into the algorithm and digest
+/// # Arguments
+/// * `encoded` - the multihash
+pub fn decode(encoded: &[u8]) -> Result<(Algorithm, Vec<u8>)> {
+    let (code, rest) = read_varint(encoded)?;
+    let (length, digest) = read_varint(rest)?;
+    let algorithm = Algorithm::ALL
+        .iter()
+        .find(|algorithm| algorithm.multihash_code() == Some(code))
+        .copied()
+        .ok_or_else(|| anyhow!("Unknown or disabled multihash code {:#x}", code))?;
+    ensure!(
+        digest.len() as u64 == length,
+        "Multihash digest is {} bytes, {} were declared",
+        digest.len(),
+        length
+    );
+    Ok((algorithm, digest.to_vec()))
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn read_varint(input: &[u8]) -> Result<(u64, &[u8])> {
+    let mut value = 0u64;
+    // The multiformats varint is at most 9 bytes long
+    for (index, byte) in input.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok((value, &input[index + 1..]));
+        }
+    }
+    Err(anyhow!("Truncated or overlong varint in multihash"))
+}
+
+/// Display of a chunk with its hash in multihash format, as
+/// `index/size/multihash` with the multihash hex encoded
+pub struct MultihashDisplay<'a, O> {
+    chunk: &'a Chunk<O>,
+    multihash: Vec<u8>,
+}
+
+impl<O: AsRef<[u8]>> Chunk<O> {
+    /// Hash of the chunk in multihash format
+    /// # Arguments
+    /// * `algorithm` - algorithm the chunk was hashed with, see
+    ///   [`ChunkedHasher::algorithm`](struct.ChunkedHasher.html#method.algorithm)
+    pub fn multihash(&self, algorithm: Algorithm) -> Result<Vec<u8>> {
+        encode(algorithm, self.hash.as_ref())
+    }
+
+    /// Display the chunk with its hash in multihash format
+    /// # Arguments
+    /// * `algorithm` - algorithm the chunk was hashed with
+    pub fn display_multihash(&self, algorithm: Algorithm) -> Result<MultihashDisplay<'_, O>> {
+        Ok(MultihashDisplay {
+            chunk: self,
+            multihash: self.multihash(algorithm)?,
+        })
+    }
+}
+
+impl<O> fmt::Display for MultihashDisplay<'_, O> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}",
+            self.chunk.index,
+            self.chunk.size,
+            hex::encode(&self.multihash)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::xxhash::Xxh64Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    #[test]
+    fn varints_round_trip() -> Result<()> {
+        for value in [0, 0x7f, 0x80, 0xb3e2, u64::from(u32::MAX), (1 << 63) - 1] {
+            let mut encoded = Vec::new();
+            write_varint(&mut encoded, value);
+            assert_eq!(read_varint(&encoded)?, (value, &[][..]));
+        }
+        let mut encoded = Vec::new();
+        write_varint(&mut encoded, 0xb3e2);
+        assert_eq!(encoded, vec![0xe2, 0xe7, 0x02]);
+        assert!(read_varint(&[0x80]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn chunks_are_tagged() -> Result<()> {
+        let data = b"brainstormremuneratedisabilityexperiment";
+        let mut buffer: Cursor<&[u8]> = Cursor::new(data);
+        let hasher = ChunkedHasher::<Xxh64Hasher>::fixed_chunks(&mut buffer, 40, 20)?;
+        let algorithm = hasher.algorithm().unwrap();
+        assert_eq!(algorithm, Algorithm::Xxh64);
+        let chunks: Vec<Chunk> = hasher.collect();
+        let display = chunks[1].display_multihash(algorithm)?.to_string();
+        assert_eq!(
+            display,
+            format!("1/20/e2e70208{}", hex::encode(&chunks[1].hash))
+        );
+        assert_eq!(
+            decode(&chunks[1].multihash(algorithm)?)?,
+            (algorithm, chunks[1].hash.clone())
+        );
+        assert!(encode(Algorithm::Crc64, &[0; 8]).is_err());
+        assert!(decode(&[0x12, 0x20, 0x00]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Runtime chunk accounting invariant checks, enabled with the `paranoid`
+//! feature
+//!
+//! Every engine feeds the chunks it produces through
+//! [`Invariants`](struct.Invariants.html), which panics as soon as indices or
+//! offsets go backwards, chunks of a contiguous layout leave gaps, sizes don't
+//! add up to the stream size or digest lengths change, catching integration
+//! bugs where they happen instead of in a corrupted manifest.
+
+use crate::ChunkRef;
+
+/// Chunk accounting state of one engine run
+#[derive(Debug, Default)]
+pub(crate) struct Invariants {
+    /// First chunk produced
+    first: Option<ChunkRef>,
+    /// Last chunk produced
+    previous: Option<ChunkRef>,
+    /// Sum of all chunk sizes
+    covered: u64,
+    /// Digest length of the first chunk
+    digest_length: Option<usize>,
+    /// Whether the stream ended before its size hint
+    short_read: bool,
+}
+
+impl Invariants {
+    /// Check the next chunk produced
+    /// # Arguments
+    /// * `chunk` - the chunk
+    /// * `contiguous` - whether every chunk starts where the previous ended
+    pub(crate) fn chunk(&mut self, chunk: ChunkRef, contiguous: bool) {
+        if let Some(previous) = self.previous {
+            assert!(
+                chunk.index > previous.index,
+                "Chunk index {} follows index {}",
+                chunk.index,
+                previous.index
+            );
+            assert!(
+                chunk.offset > previous.offset,
+                "Chunk {} at offset {} follows offset {}",
+                chunk.index,
+                chunk.offset,
+                previous.offset
+            );
+            if contiguous {
+                assert_eq!(
+                    chunk.offset,
+                    previous.offset + previous.size,
+                    "Chunk {} doesn't start where chunk {} ended",
+                    chunk.index,
+                    previous.index
+                );
+            }
+        }
+        assert!(chunk.size > 0, "Chunk {} is empty", chunk.index);
+        self.covered += chunk.size;
+        self.first.get_or_insert(chunk);
+        self.previous = Some(chunk);
+    }
+
+    /// Check the digest of the last chunk produced
+    /// # Arguments
+    /// * `length` - digest length
+    /// * `expected` - digest length of the algorithm, if known
+    pub(crate) fn digest(&mut self, length: usize, expected: Option<usize>) {
+        if let Some(expected) = expected {
+            assert_eq!(
+                length, expected,
+                "Digest length doesn't match the algorithm"
+            );
+        }
+        let first = *self.digest_length.get_or_insert(length);
+        assert_eq!(length, first, "Digest length changed between chunks");
+    }
+
+    /// Record that the stream ended before its size hint
+    pub(crate) fn short_read(&mut self) {
+        self.short_read = true;
+    }
+
+    /// Check the chunks produced once the engine is exhausted
+    /// # Arguments
+    /// * `stream_size` - stream size, if known
+    /// * `read_data` - amount of data the engine accounted for
+    /// * `contiguous` - whether every chunk starts where the previous ended
+    pub(crate) fn complete(&self, stream_size: Option<u64>, read_data: u64, contiguous: bool) {
+        assert_eq!(
+            self.covered, read_data,
+            "Chunk sizes don't add up to the data read"
+        );
+        let (first, last) = match (self.first, self.previous) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return,
+        };
+        if !contiguous {
+            return;
+        }
+        assert_eq!(
+            self.covered,
+            last.offset + last.size - first.offset,
+            "Chunk sizes don't add up to the range they cover"
+        );
+        if let Some(stream_size) = stream_size {
+            if !self.short_read {
+                assert_eq!(
+                    last.offset + last.size,
+                    stream_size,
+                    "Chunks don't end at the end of the stream"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(index: u64, offset: u64, size: u64) -> ChunkRef {
+        ChunkRef {
+            index,
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn accepts_consistent_chunks() {
+        let mut invariants = Invariants::default();
+        invariants.chunk(chunk(0, 0, 10), true);
+        invariants.digest(32, Some(32));
+        invariants.chunk(chunk(1, 10, 5), true);
+        invariants.digest(32, Some(32));
+        invariants.complete(Some(15), 15, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't start where")]
+    fn rejects_gaps() {
+        let mut invariants = Invariants::default();
+        invariants.chunk(chunk(0, 0, 10), true);
+        invariants.chunk(chunk(1, 11, 5), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Chunk index")]
+    fn rejects_duplicate_indices() {
+        let mut invariants = Invariants::default();
+        invariants.chunk(chunk(0, 0, 10), false);
+        invariants.chunk(chunk(0, 10, 5), false);
+    }
+
+    #[test]
+    #[should_panic(expected = "end of the stream")]
+    fn rejects_missing_tail() {
+        let mut invariants = Invariants::default();
+        invariants.chunk(chunk(0, 0, 10), true);
+        invariants.complete(Some(15), 10, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Digest length")]
+    fn rejects_changing_digest_lengths() {
+        let mut invariants = Invariants::default();
+        invariants.digest(32, None);
+        invariants.digest(16, None);
+    }
+}
@@ -0,0 +1,221 @@
+//! Content-defined chunking with Rabin fingerprints
+//!
+//! A Rabin fingerprint, the remainder of the last `window_size` bytes read as
+//! a polynomial over GF(2) divided by an irreducible polynomial, is rolled
+//! over the stream and a boundary is placed after every byte where the
+//! fingerprint masked with the boundary mask is zero. This is the scheme of
+//! LBFS and venti style stores, so boundaries computed by them can be
+//! reproduced given their polynomial, window size and mask.
+
+use crate::strategy::ChunkingStrategy;
+use anyhow::{ensure, Result};
+
+/// Irreducible polynomial of degree 53
+pub const DEFAULT_POLYNOMIAL: u64 = 0x3d_a335_8b4d_c173;
+
+/// Rabin fingerprint chunk boundary finder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RabinChunker {
+    window_size: usize,
+    mask: u64,
+    min_size: u64,
+    max_size: u64,
+    /// Shift moving the top byte of a fingerprint to the lowest byte
+    shift: u32,
+    /// Fingerprint of every byte value followed by `window_size - 1` zero
+    /// bytes, to slide bytes out of the window
+    out_table: Vec<u64>,
+    /// Reduction of every top byte value, to append bytes
+    mod_table: Vec<u64>,
+}
+
+impl RabinChunker {
+    /// Instantiate a boundary finder
+    ///
+    /// The polynomial has to be irreducible for boundaries to be well
+    /// distributed, this isn't checked.
+    /// # Arguments
+    /// * `polynomial` - polynomial of degree 9 to 56, such as
+    ///   [`DEFAULT_POLYNOMIAL`](constant.DEFAULT_POLYNOMIAL.html)
+    /// * `window_size` - amount of bytes the fingerprint covers
+    /// * `mask` - boundary mask, a boundary follows every byte where the
+    ///   masked fingerprint is zero, `2^n - 1` for an average chunk size of
+    ///   about `2^n` bytes
+    /// * `min_size` - minimum chunk size, except for the last chunk, at least
+    ///   the window size
+    /// * `max_size` - maximum chunk size
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, rabin::{RabinChunker, DEFAULT_POLYNOMIAL}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunker = RabinChunker::new(DEFAULT_POLYNOMIAL, 4, 0x7, 4, 16)?;
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::rabin_chunks(&mut buffer, WORDSTRING.len() as u64, chunker)?
+    ///         .collect();
+    /// assert_eq!(chunks.iter().map(|chunk| chunk.size).sum::<u64>(), 40);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(
+        polynomial: u64,
+        window_size: usize,
+        mask: u64,
+        min_size: u64,
+        max_size: u64,
+    ) -> Result<Self> {
+        let degree = 63 - polynomial.leading_zeros();
+        ensure!(
+            polynomial != 0 && (9..=56).contains(&degree),
+            "Polynomial degree must be between 9 and 56"
+        );
+        ensure!(window_size > 0, "Window size must be greater than zero");
+        ensure!(
+            min_size >= window_size as u64,
+            "Minimum size must not be less than the window size"
+        );
+        ensure!(
+            min_size <= max_size,
+            "Minimum size must not be greater than the maximum size"
+        );
+        let mod_table = (0..256u64)
+            .map(|byte| reduce(byte << degree, polynomial) | (byte << degree))
+            .collect();
+        let out_table = (0..256u64)
+            .map(|byte| {
+                let mut fingerprint = append(0, byte as u8, polynomial);
+                for _ in 1..window_size {
+                    fingerprint = append(fingerprint, 0, polynomial);
+                }
+                fingerprint
+            })
+            .collect();
+        Ok(Self {
+            window_size,
+            mask,
+            min_size,
+            max_size,
+            shift: degree - 8,
+            out_table,
+            mod_table,
+        })
+    }
+
+    fn append(&self, fingerprint: u64, byte: u8) -> u64 {
+        let top = (fingerprint >> self.shift) as usize;
+        ((fingerprint << 8) | u64::from(byte)) ^ self.mod_table[top]
+    }
+}
+
+impl ChunkingStrategy for RabinChunker {
+    fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let length = data.len() as u64;
+        if length <= self.min_size {
+            return data.len();
+        }
+        let end = length.min(self.max_size) as usize;
+        // The fingerprint only depends on the window, so rolling can start a
+        // window before the first possible boundary
+        let start = self.min_size as usize - self.window_size;
+        let mut window = vec![0u8; self.window_size];
+        let mut fingerprint = 0;
+        for (index, byte) in data.iter().enumerate().take(end).skip(start) {
+            let slot = (index - start) % self.window_size;
+            fingerprint ^= self.out_table[window[slot] as usize];
+            window[slot] = *byte;
+            fingerprint = self.append(fingerprint, *byte);
+            if index + 1 >= self.min_size as usize && fingerprint & self.mask == 0 {
+                return index + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Remainder of the polynomial division of `value` by `polynomial`
+fn reduce(mut value: u64, polynomial: u64) -> u64 {
+    let degree = 63 - polynomial.leading_zeros();
+    while value != 0 && 63 - value.leading_zeros() >= degree {
+        value ^= polynomial << (63 - value.leading_zeros() - degree);
+    }
+    value
+}
+
+/// Fingerprint after appending a byte, without lookup tables
+fn append(fingerprint: u64, byte: u8, polynomial: u64) -> u64 {
+    reduce((fingerprint << 8) | u64::from(byte), polynomial)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(length: usize) -> Vec<u8> {
+        let mut state = 7u32;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rolling_matches_window_fingerprint() -> Result<()> {
+        let chunker = RabinChunker::new(DEFAULT_POLYNOMIAL, 16, 0, 16, 1024)?;
+        let data = data(200);
+        let mut window = [0u8; 16];
+        let mut fingerprint = 0;
+        for (index, byte) in data.iter().enumerate() {
+            fingerprint ^= chunker.out_table[window[index % 16] as usize];
+            window[index % 16] = *byte;
+            fingerprint = chunker.append(fingerprint, *byte);
+            if index >= 15 {
+                let direct = data[index - 15..=index]
+                    .iter()
+                    .fold(0, |fingerprint, byte| {
+                        append(fingerprint, *byte, DEFAULT_POLYNOMIAL)
+                    });
+                assert_eq!(fingerprint, direct);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn boundaries_are_content_defined() -> Result<()> {
+        let chunker = RabinChunker::new(DEFAULT_POLYNOMIAL, 48, 0x1ff, 64, 4096)?;
+        let original = data(32 * 1024);
+        let boundaries = |data: &[u8]| {
+            let mut boundaries = Vec::new();
+            let mut start = 0;
+            while start < data.len() {
+                let size = chunker.cut_point(&data[start..]);
+                assert!(size <= 4096);
+                start += size;
+                boundaries.push(start);
+            }
+            boundaries
+        };
+        let before = boundaries(&original);
+        let average = original.len() / before.len();
+        assert!((256..=1024).contains(&average), "average {}", average);
+        let mut edited = original.clone();
+        edited.drain(10..20);
+        let after: Vec<usize> = boundaries(&edited).iter().map(|b| b + 10).collect();
+        let shared = before.iter().filter(|b| after.contains(b)).count();
+        assert!(shared >= before.len() - 2);
+        assert!(RabinChunker::new(0xff, 48, 0x1ff, 64, 4096).is_err());
+        assert!(RabinChunker::new(DEFAULT_POLYNOMIAL, 48, 0x1ff, 32, 4096).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Byte range helpers for transfer planning
+//!
+//! Chunk selections, e.g. the result of
+//! [`missing_chunks`](../verify/fn.missing_chunks.html), are coalesced into
+//! as few byte ranges as possible and rendered as HTTP `Range` header values
+//! or `curl --range` arguments.
+
+use crate::ChunkRef;
+
+/// Contiguous byte range, `end` being exclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    /// First byte of the range
+    pub start: u64,
+    /// Byte right after the end of the range
+    pub end: u64,
+}
+
+/// Coalesce chunks into sorted byte ranges, merging adjacent and overlapping
+/// chunks and dropping empty ones
+/// # Arguments
+/// * `chunks` - selected chunks, in any order
+pub fn coalesce<I: IntoIterator<Item = ChunkRef>>(chunks: I) -> Vec<ByteRange> {
+    let mut sorted: Vec<ChunkRef> = chunks.into_iter().filter(|chunk| chunk.size > 0).collect();
+    sorted.sort_by_key(|chunk| chunk.offset);
+    let mut ranges: Vec<ByteRange> = Vec::with_capacity(sorted.len());
+    for chunk in sorted {
+        match ranges.last_mut() {
+            Some(last) if chunk.offset <= last.end => last.end = last.end.max(chunk.end()),
+            _ => ranges.push(ByteRange {
+                start: chunk.offset,
+                end: chunk.end(),
+            }),
+        }
+    }
+    ranges
+}
+
+/// Render byte ranges as an HTTP `Range` header value, e.g. `bytes=0-9,20-29`,
+/// `None` if there is nothing to request
+/// # Arguments
+/// * `ranges` - ranges to request
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{ranges::{coalesce, http_range_header}, ChunkRef};
+/// let selection = vec![
+///     ChunkRef { index: 2, offset: 20, size: 10 },
+///     ChunkRef { index: 0, offset: 0, size: 10 },
+///     ChunkRef { index: 1, offset: 10, size: 10 },
+///     ChunkRef { index: 5, offset: 50, size: 4 },
+/// ];
+/// let header = http_range_header(&coalesce(selection));
+/// assert_eq!(header.as_deref(), Some("bytes=0-29,50-53"));
+/// ```
+pub fn http_range_header(ranges: &[ByteRange]) -> Option<String> {
+    range_list(ranges).map(|list| format!("bytes={}", list))
+}
+
+/// Render byte ranges as a curl compatible range list, e.g. `0-9,20-29`,
+/// `None` if there is nothing to request
+/// # Arguments
+/// * `ranges` - ranges to request
+pub fn curl_range(ranges: &[ByteRange]) -> Option<String> {
+    range_list(ranges)
+}
+
+fn range_list(ranges: &[ByteRange]) -> Option<String> {
+    let list = ranges
+        .iter()
+        .filter(|range| range.end > range.start)
+        .map(|range| format!("{}-{}", range.start, range.end - 1))
+        .collect::<Vec<String>>();
+    if list.is_empty() {
+        None
+    } else {
+        Some(list.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(index: u64, offset: u64, size: u64) -> ChunkRef {
+        ChunkRef {
+            index,
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn coalesces_adjacent_and_overlapping_chunks() {
+        let ranges = coalesce(vec![
+            chunk(4, 40, 10),
+            chunk(0, 0, 10),
+            chunk(1, 5, 10),
+            chunk(3, 30, 10),
+            chunk(7, 70, 0),
+        ]);
+        assert_eq!(
+            ranges,
+            vec![
+                ByteRange { start: 0, end: 15 },
+                ByteRange { start: 30, end: 50 }
+            ]
+        );
+        assert_eq!(curl_range(&ranges).as_deref(), Some("0-14,30-49"));
+        assert_eq!(
+            http_range_header(&ranges).as_deref(),
+            Some("bytes=0-14,30-49")
+        );
+    }
+
+    #[test]
+    fn nothing_to_request() {
+        assert!(coalesce(vec![]).is_empty());
+        assert_eq!(http_range_header(&[]), None);
+        assert_eq!(curl_range(&[ByteRange { start: 3, end: 3 }]), None);
+    }
+}
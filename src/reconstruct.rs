@@ -0,0 +1,315 @@
+//! Healing data from several replicas
+//!
+//! Every chunk is taken from whichever replica holds a good copy of it: the
+//! one matching the manifest when there is one, otherwise the copy a strict
+//! majority of the replicas agree on.
+
+use crate::{hashers::Hasher, layout, Chunk, ChunkRef, ReadAndSeek};
+use anyhow::{ensure, Result};
+use std::io::{self, Read, SeekFrom, Write};
+
+/// Outcome of a reconstruction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Reconstruction {
+    /// Chunks where the first replica was bad and the chunk was taken from
+    /// another replica
+    pub repaired: Vec<ChunkRef>,
+    /// Chunks no good copy was found for, the first replica's copy was
+    /// written for these, zero-padded where the replica is truncated
+    pub unrecoverable: Vec<ChunkRef>,
+}
+
+impl Reconstruction {
+    /// Whether every chunk of the output is known to be good
+    pub fn is_complete(&self) -> bool {
+        self.unrecoverable.is_empty()
+    }
+}
+
+/// Write a healed copy of the data described by a manifest, taking each
+/// chunk from the first replica whose copy matches the manifest
+/// # Arguments
+/// * `replicas` - readers of the replicas, in order of preference
+/// * `manifest` - contiguous chunks of the data from its start, hashed
+///   with `H`
+/// * `output` - where the healed data is written to
+pub fn reconstruct<H: Hasher>(
+    replicas: &mut [&mut dyn ReadAndSeek],
+    manifest: &[Chunk],
+    output: &mut dyn Write,
+) -> Result<Reconstruction> {
+    ensure!(!replicas.is_empty(), "At least one replica is required");
+    // Chunks are written back to back, any gap or overlap would shift the
+    // data following it
+    let mut end = Some(0);
+    for chunk in manifest {
+        ensure!(
+            end == Some(chunk.offset),
+            "Chunk {} doesn't follow the previous chunk",
+            chunk.index
+        );
+        end = chunk.offset.checked_add(chunk.size);
+    }
+    let mut reconstruction = Reconstruction::default();
+    for chunk in manifest {
+        let chunk_ref = ChunkRef::from(chunk);
+        let padding = chunk.padding_in(manifest);
+        let mut first_copy = None;
+        let mut good_copy = None;
+        for (replica_index, replica) in replicas.iter_mut().enumerate() {
+            let copy = read_chunk(*replica, &chunk_ref)?;
+            if copy.len() as u64 == chunk.size && chunk.matches_data::<H>(&copy, padding) {
+                good_copy = Some((replica_index, copy));
+                break;
+            }
+            if first_copy.is_none() {
+                first_copy = Some(copy);
+            }
+        }
+        match good_copy {
+            Some((replica_index, copy)) => {
+                if replica_index > 0 {
+                    reconstruction.repaired.push(chunk_ref);
+                }
+                output.write_all(&copy)?;
+            }
+            None => {
+                reconstruction.unrecoverable.push(chunk_ref);
+                write_padded(output, &first_copy.unwrap_or_default(), chunk.size)?;
+            }
+        }
+    }
+    Ok(reconstruction)
+}
+
+/// Write a healed copy of data without a manifest, taking each fixed size
+/// chunk from the copy a strict majority of the replicas agree on
+/// # Arguments
+/// * `replicas` - readers of the replicas, at least three are needed to
+///   outvote a bad copy
+/// * `stream_size` - size of the data
+/// * `chunk_size` - size of the chunks to vote on
+/// * `output` - where the healed data is written to
+pub fn reconstruct_by_majority<H: Hasher>(
+    replicas: &mut [&mut dyn ReadAndSeek],
+    stream_size: u64,
+    chunk_size: u64,
+    output: &mut dyn Write,
+) -> Result<Reconstruction> {
+    ensure!(!replicas.is_empty(), "At least one replica is required");
+    ensure!(chunk_size > 0, "Chunk size must be greater than zero");
+    let chunk_size = layout::clamp_chunk_size(chunk_size, stream_size);
+    let mut reconstruction = Reconstruction::default();
+    for index in 0..layout::chunk_count(stream_size, chunk_size, chunk_size) {
+        let offset = index * chunk_size;
+        let chunk_ref = ChunkRef {
+            index,
+            offset,
+            size: layout::read_length(offset, chunk_size, stream_size),
+        };
+        let mut copies = Vec::with_capacity(replicas.len());
+        for replica in replicas.iter_mut() {
+            let copy = read_chunk(*replica, &chunk_ref)?;
+            let hash = H::hash_bytes(&copy);
+            copies.push((hash, copy));
+        }
+        let winner = (0..copies.len()).find(|candidate| {
+            let votes = copies
+                .iter()
+                .filter(|(hash, copy)| {
+                    copy.len() as u64 == chunk_ref.size && *hash == copies[*candidate].0
+                })
+                .count();
+            votes * 2 > copies.len()
+        });
+        match winner {
+            Some(winner) => {
+                if copies[0].0 != copies[winner].0 {
+                    reconstruction.repaired.push(chunk_ref);
+                }
+                output.write_all(&copies[winner].1)?;
+            }
+            None => {
+                reconstruction.unrecoverable.push(chunk_ref);
+                write_padded(output, &copies[0].1, chunk_ref.size)?;
+            }
+        }
+    }
+    Ok(reconstruction)
+}
+
+/// Read a chunk from a replica, the copy is shorter than the chunk if the
+/// replica is truncated
+///
+/// At most the data actually present is read into memory, so a manifest
+/// claiming a huge chunk can't exhaust memory.
+fn read_chunk(replica: &mut dyn ReadAndSeek, chunk: &ChunkRef) -> Result<Vec<u8>> {
+    replica.seek(SeekFrom::Start(chunk.offset))?;
+    let mut copy = Vec::new();
+    replica.take(chunk.size).read_to_end(&mut copy)?;
+    Ok(copy)
+}
+
+/// Write a copy of a chunk, followed by zeros up to the chunk's size if the
+/// copy is short, without buffering the padding
+fn write_padded(output: &mut dyn Write, copy: &[u8], size: u64) -> Result<()> {
+    output.write_all(copy)?;
+    let padding = size.saturating_sub(copy.len() as u64);
+    io::copy(&mut io::repeat(0).take(padding), output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    fn damaged(positions: &[usize]) -> Vec<u8> {
+        let mut data = DATA.to_vec();
+        for position in positions {
+            data[*position] = b'x';
+        }
+        data
+    }
+
+    #[test]
+    fn heals_from_manifest() -> Result<()> {
+        let mut original: Cursor<&[u8]> = Cursor::new(DATA);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut original, DATA.len() as u64, 10)?
+                .collect();
+        let first = damaged(&[3, 25]);
+        let second = damaged(&[12, 25]);
+        let third = damaged(&[3]);
+        let mut first: Cursor<&[u8]> = Cursor::new(&first);
+        let mut second: Cursor<&[u8]> = Cursor::new(&second);
+        let mut third: Cursor<&[u8]> = Cursor::new(&third[..45]);
+        let mut output = Vec::new();
+        let reconstruction = reconstruct::<Sha256Hasher>(
+            &mut [&mut first, &mut second, &mut third],
+            &manifest,
+            &mut output,
+        )?;
+        assert!(reconstruction.is_complete());
+        assert_eq!(
+            reconstruction
+                .repaired
+                .iter()
+                .map(|chunk| chunk.index)
+                .collect::<Vec<u64>>(),
+            vec![0, 2]
+        );
+        assert_eq!(output, DATA);
+        Ok(())
+    }
+
+    #[test]
+    fn heals_padded_last_chunks() -> Result<()> {
+        let data = &DATA[..45];
+        let mut original: Cursor<&[u8]> = Cursor::new(data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut original, data.len() as u64, 10)?
+                .collect();
+        let damaged = damaged(&[42]);
+        let mut first: Cursor<&[u8]> = Cursor::new(&damaged[..45]);
+        let mut second: Cursor<&[u8]> = Cursor::new(data);
+        let mut output = Vec::new();
+        let reconstruction =
+            reconstruct::<Sha256Hasher>(&mut [&mut first, &mut second], &manifest, &mut output)?;
+        assert!(reconstruction.is_complete());
+        assert_eq!(reconstruction.repaired.len(), 1);
+        assert_eq!(output, data);
+        Ok(())
+    }
+
+    #[test]
+    fn heals_by_majority() -> Result<()> {
+        let first = damaged(&[3, 45]);
+        let second = damaged(&[41]);
+        let third = damaged(&[47]);
+        let mut first: Cursor<&[u8]> = Cursor::new(&first);
+        let mut second: Cursor<&[u8]> = Cursor::new(&second);
+        let mut third: Cursor<&[u8]> = Cursor::new(&third);
+        let mut output = Vec::new();
+        let reconstruction = reconstruct_by_majority::<Sha256Hasher>(
+            &mut [&mut first, &mut second, &mut third],
+            DATA.len() as u64,
+            10,
+            &mut output,
+        )?;
+        // The damaged first chunk of the first replica is outvoted, the last
+        // chunk has three different copies
+        assert_eq!(
+            reconstruction
+                .repaired
+                .iter()
+                .map(|chunk| chunk.index)
+                .collect::<Vec<u64>>(),
+            vec![0]
+        );
+        assert_eq!(
+            reconstruction
+                .unrecoverable
+                .iter()
+                .map(|chunk| chunk.index)
+                .collect::<Vec<u64>>(),
+            vec![4]
+        );
+        assert_eq!(&output[..40], &DATA[..40]);
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_replicas_keep_the_output_aligned() -> Result<()> {
+        let mut original: Cursor<&[u8]> = Cursor::new(DATA);
+        let mut manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut original, DATA.len() as u64, 10)?
+                .collect();
+        // Only the first replica has the damaged third chunk, and it ends
+        // within it
+        let mut first: Cursor<&[u8]> = Cursor::new(&DATA[..25]);
+        let second = damaged(&[22]);
+        let mut second: Cursor<&[u8]> = Cursor::new(&second[..20]);
+        let mut output = Vec::new();
+        let reconstruction = reconstruct::<Sha256Hasher>(
+            &mut [&mut first, &mut second],
+            &manifest[..3],
+            &mut output,
+        )?;
+        assert_eq!(reconstruction.unrecoverable.len(), 1);
+        assert_eq!(output.len(), 30);
+        assert_eq!(&output[20..], b"disab\0\0\0\0\0");
+
+        let mut output = Vec::new();
+        let mut short: Cursor<&[u8]> = Cursor::new(&DATA[..45]);
+        let mut other: Cursor<&[u8]> = Cursor::new(&DATA[..2]);
+        let mut third: Cursor<&[u8]> = Cursor::new(&DATA[..2]);
+        reconstruct_by_majority::<Sha256Hasher>(
+            &mut [&mut short, &mut other, &mut third],
+            DATA.len() as u64,
+            10,
+            &mut output,
+        )?;
+        assert_eq!(output.len(), DATA.len());
+
+        // Claimed sizes don't decide how much is read into memory
+        let mut replica: Cursor<&[u8]> = Cursor::new(DATA);
+        let huge = ChunkRef {
+            index: 4,
+            offset: 40,
+            size: u64::MAX / 2,
+        };
+        assert_eq!(read_chunk(&mut replica, &huge)?, &DATA[40..]);
+
+        // Gaps in the manifest are rejected
+        manifest.remove(1);
+        let mut replica: Cursor<&[u8]> = Cursor::new(DATA);
+        assert!(
+            reconstruct::<Sha256Hasher>(&mut [&mut replica], &manifest, &mut Vec::new()).is_err()
+        );
+        Ok(())
+    }
+}
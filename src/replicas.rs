@@ -0,0 +1,381 @@
+//! Verification of a manifest against several replicas of the same data
+//!
+//! [`verify_replicas`](fn.verify_replicas.html) checks every replica on its
+//! own thread, and the threads share the chunks between them. A thread takes
+//! a chunk that failed on the replicas that checked it so far, or else the
+//! next chunk no replica checked yet. A chunk is checked until one replica
+//! holds it intact and no further, so a healthy set of replicas reads every
+//! chunk about once, while a chunk that is bad on one replica is still
+//! recovered from another. The per-replica health tells quorum reads which
+//! replicas to trust.
+
+use crate::{
+    hashers::Hasher,
+    verify::{MissingChunk, MissingReason},
+    Chunk, ChunkRef,
+};
+use anyhow::{anyhow, ensure, Result};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Condvar, Mutex, PoisonError},
+    thread,
+};
+
+/// How a replica fared in [`verify_replicas`](fn.verify_replicas.html)
+#[derive(Debug, Default)]
+pub struct ReplicaHealth {
+    /// Chunks checked against this replica
+    pub checked: u64,
+    /// Chunks this replica holds intact
+    pub matched: u64,
+    /// Chunks checked against this replica that it doesn't hold intact
+    pub missing: Vec<MissingChunk>,
+    /// Error that stopped this replica from being checked further
+    pub error: Option<anyhow::Error>,
+}
+
+impl ReplicaHealth {
+    /// Whether every chunk checked against this replica was intact
+    pub fn healthy(&self) -> bool {
+        self.missing.is_empty() && self.error.is_none()
+    }
+}
+
+/// Outcome of [`verify_replicas`](fn.verify_replicas.html)
+#[derive(Debug)]
+pub struct ReplicaReport {
+    /// Health of every replica, in the order the replicas were given
+    pub replicas: Vec<ReplicaHealth>,
+    /// Chunks no replica holds intact, in manifest order
+    pub unverified: Vec<ChunkRef>,
+}
+
+/// Chunks still to be checked, shared by the threads of all replicas
+struct Schedule {
+    /// Chunks from this index on weren't checked against any replica yet
+    next_chunk: usize,
+    /// Chunks that failed on every replica they were checked against so far,
+    /// with the replicas they were checked against
+    retries: Vec<(usize, Vec<bool>)>,
+    /// Amount of chunks being checked right now
+    in_flight: usize,
+    /// Whether a replica holds the chunk intact
+    matched: Vec<bool>,
+}
+
+/// What a replica's thread does next
+enum Next {
+    /// Check the chunk, which was checked against the flagged replicas before
+    Check(usize, Vec<bool>),
+    /// Wait for a chunk being checked against another replica, it may fail
+    Wait,
+    /// Stop, nothing is left this replica could check
+    Done,
+}
+
+impl Schedule {
+    fn next(&mut self, replica: usize, replicas: usize) -> Next {
+        if let Some(position) = self.retries.iter().position(|(_, tried)| !tried[replica]) {
+            let (index, tried) = self.retries.swap_remove(position);
+            self.in_flight += 1;
+            Next::Check(index, tried)
+        } else if self.next_chunk < self.matched.len() {
+            self.next_chunk += 1;
+            self.in_flight += 1;
+            Next::Check(self.next_chunk - 1, vec![false; replicas])
+        } else if self.in_flight > 0 {
+            Next::Wait
+        } else {
+            Next::Done
+        }
+    }
+}
+
+/// Verify a manifest against several replicas of the data concurrently,
+/// with one thread per replica, reporting the health of every replica and
+/// the chunks no replica holds intact
+///
+/// See the [module documentation](index.html) for how the chunks are shared
+/// between the replicas. A replica failing with an I/O error, or a hasher
+/// panicking on it, stops that replica only; its chunks are checked against
+/// the remaining replicas.
+/// # Arguments
+/// * `replicas` - readers of the replicas to check
+/// * `manifest` - chunks of the complete data, hashed with `H`
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, replicas::verify_replicas, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let manifest: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let truncated = Cursor::new(&WORDSTRING.as_bytes()[..25]);
+/// let complete = Cursor::new(WORDSTRING.as_bytes());
+/// let report = verify_replicas::<Sha256Hasher, _>(vec![truncated, complete], &manifest)?;
+/// assert!(report.unverified.is_empty());
+/// assert!(report.replicas[1].healthy());
+/// # Ok(())
+/// # }
+/// ```
+pub fn verify_replicas<H: Hasher, R: Read + Seek + Send>(
+    replicas: Vec<R>,
+    manifest: &[Chunk],
+) -> Result<ReplicaReport> {
+    ensure!(!replicas.is_empty(), "At least one replica is required");
+    let shared = Shared {
+        manifest,
+        replicas: replicas.len(),
+        schedule: Mutex::new(Schedule {
+            next_chunk: 0,
+            retries: Vec::new(),
+            in_flight: 0,
+            matched: vec![false; manifest.len()],
+        }),
+        changed: Condvar::new(),
+    };
+    let health = thread::scope(|scope| {
+        let workers: Vec<_> = replicas
+            .into_iter()
+            .enumerate()
+            .map(|(replica, reader)| {
+                let shared = &shared;
+                scope.spawn(move || check_replica::<H, R>(shared, replica, reader))
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap_or_default())
+            .collect::<Vec<ReplicaHealth>>()
+    });
+    let matched = shared
+        .schedule
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .matched;
+    Ok(ReplicaReport {
+        replicas: health,
+        unverified: manifest
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(chunk, _)| ChunkRef::from(chunk))
+            .collect(),
+    })
+}
+
+/// State shared by the threads of all replicas
+struct Shared<'m> {
+    manifest: &'m [Chunk],
+    /// Amount of replicas
+    replicas: usize,
+    schedule: Mutex<Schedule>,
+    /// Signalled whenever a chunk was checked
+    changed: Condvar,
+}
+
+/// Check chunks against one replica until nothing is left it could check
+fn check_replica<H: Hasher, R: Read + Seek>(
+    shared: &Shared,
+    replica: usize,
+    mut reader: R,
+) -> ReplicaHealth {
+    let mut health = ReplicaHealth::default();
+    loop {
+        let mut schedule = shared
+            .schedule
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (index, mut tried) = loop {
+            match schedule.next(replica, shared.replicas) {
+                Next::Check(index, tried) => break (index, tried),
+                Next::Wait => {
+                    schedule = shared
+                        .changed
+                        .wait(schedule)
+                        .unwrap_or_else(PoisonError::into_inner)
+                }
+                Next::Done => return health,
+            }
+        };
+        drop(schedule);
+        let chunk = &shared.manifest[index];
+        let padding = chunk.padding_in(shared.manifest);
+        let outcome = catch_unwind(AssertUnwindSafe(|| {
+            check_chunk::<H, R>(&mut reader, chunk, padding)
+        }))
+        .unwrap_or_else(|_| Err(anyhow!("Checking replica {} panicked", replica)));
+        let mut schedule = shared
+            .schedule
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        schedule.in_flight -= 1;
+        match outcome {
+            Ok(None) => {
+                schedule.matched[index] = true;
+                health.checked += 1;
+                health.matched += 1;
+            }
+            Ok(Some(reason)) => {
+                health.checked += 1;
+                health.missing.push(MissingChunk {
+                    chunk: ChunkRef::from(chunk),
+                    reason,
+                });
+            }
+            Err(error) => health.error = Some(error),
+        }
+        tried[replica] = true;
+        if !schedule.matched[index] && tried.iter().any(|tried| !tried) {
+            schedule.retries.push((index, tried));
+        }
+        drop(schedule);
+        shared.changed.notify_all();
+        if health.error.is_some() {
+            return health;
+        }
+    }
+}
+
+/// Check a chunk against a replica, `None` when the replica holds it intact,
+/// possibly hashed followed by `padding` zeros
+///
+/// At most the data actually present is read into memory, so a manifest
+/// claiming a huge chunk can't exhaust memory.
+fn check_chunk<H: Hasher, R: Read + Seek>(
+    reader: &mut R,
+    chunk: &Chunk,
+    padding: u64,
+) -> Result<Option<MissingReason>> {
+    reader.seek(SeekFrom::Start(chunk.offset))?;
+    let mut buf = Vec::new();
+    reader.take(chunk.size).read_to_end(&mut buf)?;
+    Ok(if (buf.len() as u64) < chunk.size {
+        Some(MissingReason::Absent)
+    } else if !chunk.matches_data::<H>(&buf, padding) {
+        Some(MissingReason::Corrupt)
+    } else {
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    fn manifest() -> Result<Vec<Chunk>> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let manifest =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .collect();
+        Ok(manifest)
+    }
+
+    fn corrupted(positions: &[usize]) -> Cursor<Vec<u8>> {
+        let mut data = DATA.to_vec();
+        for position in positions {
+            data[*position] = b'x';
+        }
+        Cursor::new(data)
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::Other.into())
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn chunks_are_recovered_from_any_replica() -> Result<()> {
+        let manifest = manifest()?;
+        // Every chunk is intact on at least one replica
+        let replicas = vec![corrupted(&[5, 25]), corrupted(&[15, 45]), corrupted(&[35])];
+        let report = verify_replicas::<Sha256Hasher, _>(replicas, &manifest)?;
+        assert!(report.unverified.is_empty());
+        assert_eq!(
+            report
+                .replicas
+                .iter()
+                .map(|health| health.matched)
+                .sum::<u64>(),
+            manifest.len() as u64
+        );
+        for health in &report.replicas {
+            assert!(health.error.is_none());
+            assert_eq!(health.checked, health.matched + health.missing.len() as u64);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn padded_last_chunks_are_verified() -> Result<()> {
+        let data = &DATA[..45];
+        let mut complete: Cursor<&[u8]> = Cursor::new(data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, data.len() as u64, 10)?
+                .collect();
+        let report = verify_replicas::<Sha256Hasher, _>(vec![Cursor::new(data)], &manifest)?;
+        assert!(report.unverified.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn chunks_bad_on_every_replica_are_unverified() -> Result<()> {
+        let manifest = manifest()?;
+        let replicas = vec![
+            corrupted(&[12]),
+            Cursor::new(DATA[..15].to_vec()),
+            corrupted(&[18, 40]),
+        ];
+        let report = verify_replicas::<Sha256Hasher, _>(replicas, &manifest)?;
+        assert_eq!(report.unverified, vec![ChunkRef::from(&manifest[1])]);
+        for health in &report.replicas {
+            assert!(health
+                .missing
+                .iter()
+                .any(|missing| missing.chunk.index == 1));
+        }
+        assert_eq!(
+            report.replicas[1]
+                .missing
+                .iter()
+                .find(|missing| missing.chunk.index == 1)
+                .map(|missing| missing.reason),
+            Some(MissingReason::Absent)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn failing_replica_is_stopped_and_others_take_over() -> Result<()> {
+        let manifest = manifest()?;
+        let replicas: Vec<Box<dyn crate::ReadAndSeek + Send>> =
+            vec![Box::new(FailingReader), Box::new(Cursor::new(DATA))];
+        let report = verify_replicas::<Sha256Hasher, _>(replicas, &manifest)?;
+        assert!(report.unverified.is_empty());
+        assert_eq!(report.replicas[0].checked, 0);
+        assert!(report.replicas[0].error.is_some());
+        assert!(!report.replicas[0].healthy());
+        assert_eq!(report.replicas[1].matched, manifest.len() as u64);
+        assert!(verify_replicas::<Sha256Hasher, Cursor<&[u8]>>(vec![], &manifest).is_err());
+        Ok(())
+    }
+}
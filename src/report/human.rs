@@ -0,0 +1,263 @@
+//! Human readable formatting of sizes, durations and throughput
+//!
+//! Sizes use binary units (KiB, MiB, GiB, ...) and are rendered with at most
+//! two decimals. The free functions are locale-independent, a
+//! [`Locale`](struct.Locale.html) formats with the decimal and digit grouping
+//! separators of the reader's locale instead. The system locale isn't
+//! queried, callers pass the separators in.
+
+use std::time::Duration;
+
+/// Binary size units, each 1024 times the previous one
+const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// Separators numbers are formatted with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Locale {
+    decimal_separator: char,
+    grouping_separator: Option<char>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::C
+    }
+}
+
+impl Locale {
+    /// Locale-independent formatting with a `.` decimal separator and no
+    /// digit grouping
+    pub const C: Locale = Locale {
+        decimal_separator: '.',
+        grouping_separator: None,
+    };
+
+    /// Instantiate a locale without digit grouping
+    /// # Arguments
+    /// * `decimal_separator` - separator between the integer and fractional
+    ///   part, such as `,` in most of Europe
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::report::human::Locale;
+    /// # use std::time::Duration;
+    /// let german = Locale::new(',').with_grouping('.');
+    /// assert_eq!(german.format_bytes(3 * 1024 * 1024 * 1024 / 2), "1,50 GiB");
+    /// assert_eq!(
+    ///     german.summary(10, 1500, Duration::from_millis(1250)),
+    ///     "hashed 10 B in 1.500 chunks in 1,25s (8 B/s)"
+    /// );
+    /// ```
+    pub fn new(decimal_separator: char) -> Self {
+        Self {
+            decimal_separator,
+            grouping_separator: None,
+        }
+    }
+
+    /// Group the digits of integer parts in thousands
+    /// # Arguments
+    /// * `separator` - separator between groups, such as `,` in English
+    pub fn with_grouping(mut self, separator: char) -> Self {
+        self.grouping_separator = Some(separator);
+        self
+    }
+
+    /// Format an integer, grouping its digits
+    fn integer(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let separator = match self.grouping_separator {
+            Some(separator) => separator,
+            None => return digits,
+        };
+        let mut grouped = String::with_capacity(digits.len() * 4 / 3);
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index) % 3 == 0 {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// Format a non-negative value with two decimals
+    fn decimal(&self, value: f64) -> String {
+        let formatted = format!("{:.2}", value);
+        let (integer, fraction) = formatted.split_at(formatted.len() - 3);
+        format!(
+            "{}{}{}",
+            self.integer(integer.parse().unwrap_or(u64::MAX)),
+            self.decimal_separator,
+            &fraction[1..]
+        )
+    }
+
+    /// Format a byte count, see [`format_bytes`](fn.format_bytes.html)
+    /// # Arguments
+    /// * `bytes` - amount of bytes
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        let mut unit = 0;
+        let mut value = bytes as f64;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", self.integer(bytes), UNITS[0])
+        } else {
+            format!("{} {}", self.decimal(value), UNITS[unit])
+        }
+    }
+
+    /// Format a duration, see [`format_duration`](fn.format_duration.html)
+    /// # Arguments
+    /// * `duration` - duration to format
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        if seconds >= 3600 {
+            format!(
+                "{}h {:02}m {:02}s",
+                self.integer(seconds / 3600),
+                (seconds % 3600) / 60,
+                seconds % 60
+            )
+        } else if seconds >= 60 {
+            format!("{}m {:02}s", seconds / 60, seconds % 60)
+        } else if seconds >= 1 {
+            format!("{}s", self.decimal(duration.as_secs_f64()))
+        } else {
+            format!("{}ms", duration.as_millis())
+        }
+    }
+
+    /// Format a throughput, see
+    /// [`format_throughput`](fn.format_throughput.html)
+    /// # Arguments
+    /// * `bytes` - amount of bytes processed
+    /// * `duration` - time it took to process them
+    pub fn format_throughput(&self, bytes: u64, duration: Duration) -> String {
+        let seconds = duration.as_secs_f64();
+        if seconds == 0.0 {
+            return "n/a".to_owned();
+        }
+        let per_second = (bytes as f64 / seconds).min(u64::MAX as f64) as u64;
+        format!("{}/s", self.format_bytes(per_second))
+    }
+
+    /// Format a one line summary of a hashing run, see
+    /// [`summary`](fn.summary.html)
+    /// # Arguments
+    /// * `bytes` - amount of bytes hashed
+    /// * `chunks` - amount of chunks produced
+    /// * `duration` - time the run took
+    pub fn summary(&self, bytes: u64, chunks: u64, duration: Duration) -> String {
+        format!(
+            "hashed {} in {} chunk{} in {} ({})",
+            self.format_bytes(bytes),
+            self.integer(chunks),
+            if chunks == 1 { "" } else { "s" },
+            self.format_duration(duration),
+            self.format_throughput(bytes, duration)
+        )
+    }
+}
+
+/// Format a byte count, e.g. `1.50 GiB`
+/// # Arguments
+/// * `bytes` - amount of bytes
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::report::human::format_bytes;
+/// assert_eq!(format_bytes(512), "512 B");
+/// assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.50 GiB");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    Locale::C.format_bytes(bytes)
+}
+
+/// Format a duration, e.g. `1h 02m 03s` or `1.25s`
+/// # Arguments
+/// * `duration` - duration to format
+pub fn format_duration(duration: Duration) -> String {
+    Locale::C.format_duration(duration)
+}
+
+/// Format the throughput of processing `bytes` in `duration`, e.g.
+/// `512.00 MiB/s`
+/// # Arguments
+/// * `bytes` - amount of bytes processed
+/// * `duration` - time it took to process them
+pub fn format_throughput(bytes: u64, duration: Duration) -> String {
+    Locale::C.format_throughput(bytes, duration)
+}
+
+/// Format a one line summary of a hashing run, e.g.
+/// `hashed 1.00 GiB in 1024 chunks in 2.00s (512.00 MiB/s)`
+/// # Arguments
+/// * `bytes` - amount of bytes hashed
+/// * `chunks` - amount of chunks produced
+/// * `duration` - time the run took
+pub fn summary(bytes: u64, chunks: u64, duration: Duration) -> String {
+    Locale::C.summary(bytes, chunks, duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1024), "1.00 KiB");
+        assert_eq!(format_bytes(40 * 1024 * 1024 * 1024), "40.00 GiB");
+        assert_eq!(format_bytes(u64::MAX), "16.00 EiB");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(format_duration(Duration::from_millis(250)), "250ms");
+        assert_eq!(format_duration(Duration::from_millis(1250)), "1.25s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 02m 03s");
+    }
+
+    #[test]
+    fn throughput_and_summary() {
+        assert_eq!(
+            format_throughput(1024 * 1024 * 1024, Duration::from_secs(2)),
+            "512.00 MiB/s"
+        );
+        assert_eq!(format_throughput(1024, Duration::from_secs(0)), "n/a");
+        assert_eq!(
+            summary(1024 * 1024 * 1024, 1024, Duration::from_secs(2)),
+            "hashed 1.00 GiB in 1024 chunks in 2.00s (512.00 MiB/s)"
+        );
+        assert_eq!(
+            summary(10, 1, Duration::from_millis(5)),
+            "hashed 10 B in 1 chunk in 5ms (1.95 KiB/s)"
+        );
+    }
+
+    #[test]
+    fn locale_separators() {
+        let english = Locale::new('.').with_grouping(',');
+        assert_eq!(english.format_bytes(1023), "1,023 B");
+        assert_eq!(english.format_bytes(u64::MAX), "16.00 EiB");
+        assert_eq!(
+            english.format_duration(Duration::from_secs(3600 * 1234)),
+            "1,234h 00m 00s"
+        );
+        let french = Locale::new(',').with_grouping('\u{202f}');
+        assert_eq!(french.format_bytes(1536), "1,50 KiB");
+        assert_eq!(
+            french.format_throughput(1024 * 1024 * 1024, Duration::from_secs(2)),
+            "512,00 MiB/s"
+        );
+        assert_eq!(french.integer(1_234_567), "1\u{202f}234\u{202f}567");
+        assert_eq!(Locale::default(), Locale::C);
+    }
+}
@@ -0,0 +1,3 @@
+//! Reporting helpers for presenting hashing results
+pub mod human;
+pub mod regions;
@@ -0,0 +1,96 @@
+//! Largest changed regions between two manifests of a stream
+//!
+//! Changed chunks are coalesced into contiguous byte ranges, so a report can
+//! point at the few regions where most of the change happened rather than
+//! listing thousands of chunks.
+
+use crate::{
+    ranges::{coalesce, ByteRange},
+    Chunk, ChunkRef,
+};
+use std::collections::HashMap;
+
+/// The `count` largest changed byte ranges between two manifests, largest
+/// first
+///
+/// Chunks are matched by index. Chunks only present in the old manifest, e.g.
+/// after the stream was truncated, are reported at their old position.
+/// # Arguments
+/// * `old` - chunks of the stream before the change
+/// * `new` - chunks of the stream after the change
+/// * `count` - maximum amount of regions to return
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, report::regions::largest_changed_regions, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # let mut before: Cursor<&[u8]> = Cursor::new(b"brainstormremuneratedisabilityexperiment");
+/// # let mut after: Cursor<&[u8]> = Cursor::new(b"brainstormREMUNERATEDISAbilityexperimenT");
+/// let old: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut before, 40, 5)?.collect();
+/// let new: Vec<Chunk> = ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut after, 40, 5)?.collect();
+/// let regions = largest_changed_regions(&old, &new, 1);
+/// assert_eq!((regions[0].start, regions[0].end), (10, 25));
+/// # Ok(())
+/// # }
+/// ```
+pub fn largest_changed_regions(old: &[Chunk], new: &[Chunk], count: usize) -> Vec<ByteRange> {
+    let old_chunks: HashMap<u64, &Chunk> = old.iter().map(|chunk| (chunk.index, chunk)).collect();
+    let new_chunks: HashMap<u64, &Chunk> = new.iter().map(|chunk| (chunk.index, chunk)).collect();
+    let changed = new
+        .iter()
+        .filter(|chunk| old_chunks.get(&chunk.index) != Some(chunk))
+        .chain(
+            old.iter()
+                .filter(|chunk| !new_chunks.contains_key(&chunk.index)),
+        )
+        .map(ChunkRef::from);
+    let mut regions = coalesce(changed);
+    regions.sort_by(|a, b| {
+        (b.end - b.start)
+            .cmp(&(a.end - a.start))
+            .then(a.start.cmp(&b.start))
+    });
+    regions.truncate(count);
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(index: u64, hash: u8) -> Chunk {
+        Chunk {
+            index,
+            offset: index * 10,
+            size: 10,
+            hash: vec![hash],
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn regions_are_ordered_by_size() {
+        let old: Vec<Chunk> = (0..10).map(|index| chunk(index, 0)).collect();
+        let mut new = old.clone();
+        for index in &[1, 4, 5, 6, 8, 9] {
+            new[*index].hash = vec![1];
+        }
+        new.truncate(9);
+        let regions = largest_changed_regions(&old, &new, 2);
+        assert_eq!(
+            regions,
+            vec![
+                ByteRange { start: 40, end: 70 },
+                ByteRange {
+                    start: 80,
+                    end: 100
+                },
+            ]
+        );
+        assert_eq!(largest_changed_regions(&old, &new, 5).len(), 3);
+        assert!(largest_changed_regions(&old, &old, 5).is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+//! Structured concurrency for hashing several streams at once
+//!
+//! [`ChunkScope::run`](struct.ChunkScope.html#method.run) hashes every
+//! stream submitted to the scope on its own worker thread. All workers are
+//! joined before `run` returns, even when one of them fails or panics, so
+//! streams may borrow from the surrounding environment.
+//!
+//! Streams finish in any order, but the results are returned in submission
+//! order, and the chunks of every stream in index order, as each stream is
+//! hashed sequentially by a single worker.
+
+use crate::{hashers::Hasher, Chunk, ChunkedHasher, StreamSize};
+use anyhow::{anyhow, Result};
+use std::{
+    cell::RefCell,
+    io::{Read, Seek},
+    marker::PhantomData,
+    thread::{self, Scope, ScopedJoinHandle},
+};
+
+/// Scope streams are submitted to for hashing
+pub struct ChunkScope<'scope, 'env: 'scope, H> {
+    /// Thread scope the workers run in
+    scope: &'scope Scope<'scope, 'env>,
+    /// Workers in submission order
+    jobs: RefCell<Vec<ScopedJoinHandle<'scope, Result<Vec<Chunk>>>>>,
+    _marker: PhantomData<H>,
+}
+
+impl<'scope, 'env, H: Hasher> ChunkScope<'scope, 'env, H> {
+    /// Run a scope, returning the chunks of every submitted stream in
+    /// submission order, or the error of the first failed stream in
+    /// submission order
+    /// # Arguments
+    /// * `f` - submits streams to the scope
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, scope::ChunkScope};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # let first: Cursor<&[u8]> = Cursor::new(b"brainstormremuneratedisabilityexperiment");
+    /// # let second: Cursor<&[u8]> = Cursor::new(b"goalkeepervegetarian");
+    /// let results = ChunkScope::<Sha256Hasher>::run(|scope| {
+    ///     scope.hash(first, 40, 10);
+    ///     scope.hash(second, 20, 10);
+    /// })?;
+    /// assert_eq!(results[0].len(), 4);
+    /// assert_eq!(results[1].len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run<F>(f: F) -> Result<Vec<Vec<Chunk>>>
+    where
+        F: for<'s> FnOnce(&ChunkScope<'s, 'env, H>),
+    {
+        thread::scope(|scope| {
+            let chunk_scope = ChunkScope {
+                scope,
+                jobs: RefCell::new(Vec::new()),
+                _marker: PhantomData,
+            };
+            f(&chunk_scope);
+            let mut results = Vec::new();
+            let mut first_error = None;
+            for (index, job) in chunk_scope.jobs.into_inner().into_iter().enumerate() {
+                let result = job
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("Hashing stream {} panicked", index)));
+                match result {
+                    Ok(chunks) => results.push(chunks),
+                    Err(e) => {
+                        if first_error.is_none() {
+                            first_error = Some(e);
+                        }
+                    }
+                }
+            }
+            match first_error {
+                Some(e) => Err(e),
+                None => Ok(results),
+            }
+        })
+    }
+
+    /// Hash a stream in fixed size chunks on a worker thread
+    /// # Arguments
+    /// * `buffer` - the buffer to hash, it's moved to the worker
+    /// * `stream_size` - as neither Read nor Seek implements the ability to get
+    ///   the full size, we need to give this hint
+    /// * `fixed_size` - fixed chunk size, the last chunk will contain the
+    ///   remainder
+    pub fn hash<R>(&self, buffer: R, stream_size: impl Into<StreamSize>, fixed_size: u64)
+    where
+        R: Read + Seek + Send + 'env,
+    {
+        let stream_size = stream_size.into();
+        let job = self.scope.spawn(move || {
+            let mut buffer = buffer;
+            let chunks =
+                ChunkedHasher::<H>::fixed_chunks(&mut buffer, stream_size, fixed_size)?.collect();
+            Ok(chunks)
+        });
+        self.jobs.borrow_mut().push(job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashers::sha2::Sha256Hasher;
+    use std::io::{Cursor, SeekFrom};
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    struct PanickingReader;
+
+    impl Read for PanickingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            panic!("read failed");
+        }
+    }
+
+    impl Seek for PanickingReader {
+        fn seek(&mut self, _pos: SeekFrom) -> std::io::Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn hashes_in_submission_order() -> Result<()> {
+        let results = ChunkScope::<Sha256Hasher>::run(|scope| {
+            for size in &[50u64, 20, 35] {
+                scope.hash(Cursor::new(&DATA[..*size as usize]), *size, 10);
+            }
+        })?;
+        assert_eq!(
+            results
+                .iter()
+                .map(|chunks| chunks.len())
+                .collect::<Vec<usize>>(),
+            vec![5, 2, 4]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn propagates_the_first_error() {
+        let error = ChunkScope::<Sha256Hasher>::run(|scope| {
+            scope.hash(Cursor::new(DATA), 50, 10);
+            scope.hash(Cursor::new(DATA), 50, 0);
+            scope.hash(PanickingReader, StreamSize::Unknown, 10);
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.to_string(), "Fixed size must be greater than zero");
+
+        let error = ChunkScope::<Sha256Hasher>::run(|scope| {
+            scope.hash(PanickingReader, StreamSize::Unknown, 10);
+        })
+        .err()
+        .unwrap();
+        assert_eq!(error.to_string(), "Hashing stream 0 panicked");
+    }
+
+    /// Reader that stalls before its first read, so it finishes last
+    struct SlowReader<R>(R, bool);
+
+    impl<R: Read> Read for SlowReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if !self.1 {
+                self.1 = true;
+                thread::sleep(std::time::Duration::from_millis(50));
+            }
+            self.0.read(buf)
+        }
+    }
+
+    impl<R: Seek> Seek for SlowReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    #[test]
+    fn order_doesnt_depend_on_completion() -> Result<()> {
+        let results = ChunkScope::<Sha256Hasher>::run(|scope| {
+            scope.hash(SlowReader(Cursor::new(DATA), false), 50, 10);
+            scope.hash(Cursor::new(&DATA[..20]), 20, 10);
+            scope.hash(Cursor::new(DATA), 50, 7);
+        })?;
+        let expected: Vec<Vec<Chunk>> = vec![
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut Cursor::new(DATA), 50, 10)?.collect(),
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut Cursor::new(&DATA[..20]), 20, 10)?
+                .collect(),
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut Cursor::new(DATA), 50, 7)?.collect(),
+        ];
+        assert_eq!(results, expected);
+        for chunks in &results {
+            assert!(chunks
+                .iter()
+                .enumerate()
+                .all(|(index, chunk)| chunk.index == index as u64));
+        }
+        Ok(())
+    }
+}
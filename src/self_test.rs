@@ -0,0 +1,134 @@
+//! Known-answer tests of the compiled-in hashers and chunkers
+//!
+//! [`self_test`](fn.self_test.html) is meant to run at startup, before the
+//! output of a binary is trusted, and fails on the first hasher or chunker
+//! producing an unexpected result.
+
+use crate::{hashers::algorithm::Algorithm, Chunk, ChunkedHasher, Chunking};
+use anyhow::{anyhow, ensure, Result};
+use std::io::Cursor;
+
+/// Input and digest of every known-answer test, CRCs use their catalogue
+/// check input
+const KNOWN_ANSWERS: &[(Algorithm, &[u8], &str)] = &[
+    (Algorithm::Sha256, b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+    (Algorithm::Sha512, b"abc", "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+    (Algorithm::Blake3, b"abc", "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+    (Algorithm::Shake128, b"abc", "5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8"),
+    (Algorithm::Shake256, b"abc", "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4"),
+    (Algorithm::Xxh64, b"abc", "44bc2cf5ad770999"),
+    (Algorithm::Xxh3, b"abc", "78af5f94892f3950"),
+    (Algorithm::Crc32, b"123456789", "cbf43926"),
+    (Algorithm::Crc32c, b"123456789", "e3069283"),
+    (Algorithm::Crc64, b"123456789", "ae8b14860a799888"),
+    #[cfg(feature = "legacy-hashes")]
+    (Algorithm::Md5, b"abc", "900150983cd24fb0d6963f7d28e17f72"),
+    #[cfg(feature = "legacy-hashes")]
+    (Algorithm::Sha1, b"abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+    #[cfg(feature = "ripemd")]
+    (Algorithm::Ripemd160, b"abc", "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc"),
+    #[cfg(feature = "sm3")]
+    (Algorithm::Sm3, b"abc", "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0"),
+    #[cfg(feature = "streebog")]
+    (Algorithm::Streebog256, b"abc", "4e2919cf137ed41ec4fb6270c61826cc4fffb660341e0af3688cd0626d23b481"),
+    #[cfg(feature = "streebog")]
+    (Algorithm::Streebog512, b"abc", "28156e28317da7c98f4fe2bed6b542d0dab85bb224445fcedaf75d46e26d7eb8d5997f3e0915dd6b7f0aab08d9c8beb0d8c64bae2ab8b3c8c6bc53b3bf0db728"),
+    #[cfg(feature = "whirlpool")]
+    (Algorithm::Whirlpool, b"abc", "4e2448a4c6f486bb16b6562c73b4020bf3043e3a731bce721ae1b303d97e6d4c7181eebdb6c57e277d0e34957114cbd6c797fc9d95d8b582d225292076d4eef5"),
+];
+
+/// Stream the chunkers are tested with
+const STREAM: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+/// Chunk layouts of the stream, as `(offset, size)` of every chunk
+const KNOWN_LAYOUTS: &[(Chunking, &[(u64, u64)])] = &[
+    (Chunking::Fixed(16), &[(0, 16), (16, 16), (32, 16), (48, 2)]),
+    (
+        Chunking::Dynamic(4),
+        &[(0, 12), (12, 12), (24, 12), (36, 12), (48, 2)],
+    ),
+    (
+        Chunking::Overlapping {
+            window_size: 20,
+            stride: 15,
+        },
+        &[(0, 20), (15, 20), (30, 20)],
+    ),
+];
+
+/// Run known-answer tests for every compiled-in hasher and chunker,
+/// returning the names of the passed tests
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::self_test::self_test;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// let passed = self_test()?;
+/// assert!(passed.contains(&"sha256".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+pub fn self_test() -> Result<Vec<String>> {
+    let mut passed = Vec::new();
+    for algorithm in Algorithm::ALL {
+        let (_, input, expected) = KNOWN_ANSWERS
+            .iter()
+            .find(|(known, _, _)| known == algorithm)
+            .ok_or_else(|| anyhow!("No known answer for hasher {}", algorithm))?;
+        ensure!(
+            hex::encode(algorithm.hash_bytes(input)) == *expected,
+            "Hasher {} failed its known-answer test",
+            algorithm
+        );
+        passed.push(algorithm.to_string());
+    }
+    for (chunking, layout) in KNOWN_LAYOUTS {
+        let mut buffer = Cursor::new(STREAM);
+        let chunks: Vec<Chunk> = ChunkedHasher::with_algorithm(
+            &mut buffer,
+            STREAM.len() as u64,
+            *chunking,
+            Algorithm::Sha256,
+        )?
+        .collect();
+        let produced: Vec<(u64, u64)> = chunks
+            .iter()
+            .map(|chunk| (chunk.offset, chunk.size))
+            .collect();
+        ensure!(
+            produced == *layout,
+            "Chunker {:?} produced an unexpected layout",
+            chunking
+        );
+        for chunk in &chunks {
+            let mut data =
+                STREAM[chunk.offset as usize..(chunk.offset + chunk.size) as usize].to_vec();
+            // A short last chunk is hashed zero-padded to the chunk size
+            if chunk.offset > 0 {
+                data.resize(chunks[0].size as usize, 0);
+            }
+            ensure!(
+                chunk.hash == Algorithm::Sha256.hash_bytes(&data),
+                "Chunker {:?} hashed chunk {} incorrectly",
+                chunking,
+                chunk.index
+            );
+        }
+        passed.push(format!("{:?}", chunking));
+    }
+    Ok(passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_every_algorithm() -> Result<()> {
+        let passed = self_test()?;
+        assert_eq!(passed.len(), Algorithm::ALL.len() + KNOWN_LAYOUTS.len());
+        Ok(())
+    }
+}
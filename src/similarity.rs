@@ -0,0 +1,62 @@
+//! Locality-sensitive similarity digests
+//!
+//! Unlike the cryptographic chunk hashes, a similarity digest of two chunks
+//! with mostly the same content will only differ in a few bits. The digest is
+//! a 64-bit SimHash over the byte trigrams of the chunk, and two digests are
+//! compared by their Hamming [`distance`](fn.distance.html).
+
+/// FNV-1a offset basis
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// FNV-1a prime
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Returns the SimHash digest of the given bytes
+/// # Arguments
+/// * `bytes` - byte slice to digest
+pub fn simhash(bytes: &[u8]) -> u64 {
+    let mut weights = [0i64; 64];
+    for feature in bytes.windows(3.min(bytes.len()).max(1)) {
+        let hash = feature.iter().fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        });
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |digest, (bit, _)| digest | (1 << bit))
+}
+
+/// Returns the amount of differing bits between two similarity digests
+/// # Arguments
+/// * `a` - first digest
+/// * `b` - second digest
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_content_is_closer_than_unrelated_content() {
+        let original = simhash(b"brainstormremuneratedisabilityexperimentgoalkeeper");
+        let similar = simhash(b"brainstormremuneratedisabilityexperimentgoalkeepxx");
+        let unrelated = simhash(b"curriculumhypnothizestereotypefederationattraction");
+        assert!(distance(original, similar) < distance(original, unrelated));
+    }
+
+    #[test]
+    fn empty_and_short_input() {
+        assert_eq!(simhash(b""), 0);
+        assert_eq!(simhash(b"ab"), simhash(b"ab"));
+    }
+}
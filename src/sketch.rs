@@ -0,0 +1,144 @@
+//! MinHash sketches of chunk sets
+//!
+//! A [`Sketch`](struct.Sketch.html) condenses the set of chunk hashes of a
+//! file or dataset into a fixed size signature. Comparing two signatures
+//! estimates the Jaccard similarity of the underlying chunk sets, without
+//! having either the data or the full chunk lists at hand.
+
+use crate::Chunk;
+
+/// Default amount of hash functions in a signature
+pub const DEFAULT_SKETCH_SIZE: usize = 128;
+
+/// MinHash signature of a set of chunk hashes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sketch {
+    /// Minimum value seen per hash function
+    signature: Vec<u64>,
+}
+
+impl Sketch {
+    /// Build a sketch with [`DEFAULT_SKETCH_SIZE`](constant.DEFAULT_SKETCH_SIZE.html)
+    /// hash functions
+    /// # Arguments
+    /// * `chunks` - chunks to build the sketch from
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, sketch::Sketch, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+    ///         .collect();
+    /// let sketch = Sketch::from_chunks(&chunks);
+    /// assert_eq!(sketch.similarity(&sketch), 1.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_chunks<'c, I: IntoIterator<Item = &'c Chunk>>(chunks: I) -> Self {
+        Self::with_size(chunks, DEFAULT_SKETCH_SIZE)
+    }
+
+    /// Build a sketch with a given amount of hash functions, more functions
+    /// give a more precise estimate
+    /// # Arguments
+    /// * `chunks` - chunks to build the sketch from
+    /// * `size` - amount of hash functions in the signature
+    pub fn with_size<'c, I: IntoIterator<Item = &'c Chunk>>(chunks: I, size: usize) -> Self {
+        let mut signature = vec![u64::MAX; size];
+        for chunk in chunks {
+            let base = fold_hash(&chunk.hash);
+            for (seed, minimum) in signature.iter_mut().enumerate() {
+                let value = mix(base ^ mix(seed as u64));
+                if value < *minimum {
+                    *minimum = value;
+                }
+            }
+        }
+        Self { signature }
+    }
+
+    /// Raw MinHash signature
+    pub fn signature(&self) -> &[u64] {
+        &self.signature
+    }
+
+    /// Estimated Jaccard similarity between the chunk sets of two sketches,
+    /// ranging from 0.0 (nothing shared) to 1.0 (identical sets)
+    /// # Arguments
+    /// * `other` - sketch to compare against, only the common prefix of the
+    ///   signatures is compared if they differ in size
+    pub fn similarity(&self, other: &Sketch) -> f64 {
+        let compared = self.signature.len().min(other.signature.len());
+        if compared == 0 {
+            return 0.0;
+        }
+        let equal = self
+            .signature
+            .iter()
+            .zip(other.signature.iter())
+            .filter(|(ours, theirs)| ours == theirs && **ours != u64::MAX)
+            .count();
+        equal as f64 / compared as f64
+    }
+}
+
+/// Fold an arbitrary length digest into 64 bits
+fn fold_hash(hash: &[u8]) -> u64 {
+    hash.chunks(8).fold(0, |folded, part| {
+        let mut word = [0u8; 8];
+        word[..part.len()].copy_from_slice(part);
+        mix(folded ^ u64::from_le_bytes(word))
+    })
+}
+
+/// SplitMix64 finalizer
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(hashes: &[u8]) -> Vec<Chunk> {
+        hashes
+            .iter()
+            .enumerate()
+            .map(|(index, hash)| Chunk {
+                index: index as u64,
+                offset: index as u64,
+                size: 1,
+                hash: vec![*hash; 32],
+                similarity: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn similarity_estimates() {
+        let original = Sketch::from_chunks(&chunks(&(0..100).collect::<Vec<u8>>()));
+        let half = Sketch::from_chunks(&chunks(&(50..150).collect::<Vec<u8>>()));
+        let disjoint = Sketch::from_chunks(&chunks(&(150..250).collect::<Vec<u8>>()));
+        assert_eq!(original.similarity(&original), 1.0);
+        // Jaccard similarity of the two sets is 50/150
+        let estimate = original.similarity(&half);
+        assert!(estimate > 0.2 && estimate < 0.5);
+        assert!(original.similarity(&disjoint) < 0.05);
+    }
+
+    #[test]
+    fn empty_sketches_are_not_similar() {
+        let empty = Sketch::from_chunks(&[]);
+        assert_eq!(empty.similarity(&empty), 0.0);
+        assert_eq!(Sketch::with_size(&[], 0).similarity(&empty), 0.0);
+    }
+}
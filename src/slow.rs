@@ -0,0 +1,130 @@
+//! Detection of chunks that take unusually long to read or hash
+//!
+//! A chunk is reported when reading or hashing it takes longer than a given
+//! multiple of the median time of the recent chunks, which pinpoints failing
+//! disk sectors or stalled network sources during long jobs.
+
+use crate::ChunkRef;
+use std::{collections::VecDeque, time::Duration};
+
+/// Amount of recent chunks the median is computed over
+const WINDOW: usize = 64;
+/// Amount of chunks needed before any chunk is reported
+const WARMUP: usize = 8;
+
+/// Phase of processing a chunk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Seeking to and reading the chunk
+    Read,
+    /// Hashing the chunk
+    Hash,
+}
+
+/// Chunk that took unusually long to process
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SlowChunk {
+    /// Position of the slow chunk
+    pub chunk: ChunkRef,
+    /// Phase that was slow
+    pub phase: Phase,
+    /// Time the phase took for this chunk
+    pub duration: Duration,
+    /// Median time the phase took for recent chunks
+    pub median: Duration,
+}
+
+/// Tracks chunk timings and reports slow chunks to a callback
+pub(crate) struct SlowChunkDetector<'a> {
+    /// Multiple of the median a chunk must exceed to be reported
+    multiple: f64,
+    /// Recent read timings
+    reads: VecDeque<Duration>,
+    /// Recent hash timings
+    hashes: VecDeque<Duration>,
+    /// Receiver of slow chunk reports
+    callback: Box<dyn FnMut(&SlowChunk) + 'a>,
+}
+
+impl<'a> SlowChunkDetector<'a> {
+    pub(crate) fn new(multiple: f64, callback: Box<dyn FnMut(&SlowChunk) + 'a>) -> Self {
+        Self {
+            multiple,
+            reads: VecDeque::with_capacity(WINDOW),
+            hashes: VecDeque::with_capacity(WINDOW),
+            callback,
+        }
+    }
+
+    /// Record the timings of a chunk, reporting each slow phase
+    pub(crate) fn record(&mut self, chunk: ChunkRef, read: Duration, hash: Duration) {
+        for (phase, duration) in [(Phase::Read, read), (Phase::Hash, hash)].iter().cloned() {
+            let samples = match phase {
+                Phase::Read => &mut self.reads,
+                Phase::Hash => &mut self.hashes,
+            };
+            if samples.len() >= WARMUP {
+                let median = median(samples);
+                if duration.as_secs_f64() > median.as_secs_f64() * self.multiple {
+                    (self.callback)(&SlowChunk {
+                        chunk,
+                        phase,
+                        duration,
+                        median,
+                    });
+                }
+            }
+            if samples.len() == WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back(duration);
+        }
+    }
+}
+
+fn median(samples: &VecDeque<Duration>) -> Duration {
+    let mut sorted: Vec<Duration> = samples.iter().cloned().collect();
+    sorted.sort();
+    sorted[sorted.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn chunk(index: u64) -> ChunkRef {
+        ChunkRef {
+            index,
+            offset: index * 10,
+            size: 10,
+        }
+    }
+
+    #[test]
+    fn reports_outliers_after_warmup() {
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let sink = reported.clone();
+        let mut detector = SlowChunkDetector::new(
+            4.0,
+            Box::new(move |slow: &SlowChunk| sink.borrow_mut().push(*slow)),
+        );
+        let normal = Duration::from_millis(10);
+        let slow = Duration::from_millis(100);
+        // Slow during warmup, not reported
+        detector.record(chunk(0), slow, normal);
+        for index in 1..10 {
+            detector.record(chunk(index), normal, normal);
+        }
+        detector.record(chunk(10), slow, normal);
+        detector.record(chunk(11), normal, slow);
+        detector.record(chunk(12), Duration::from_millis(30), normal);
+        let reported = reported.borrow();
+        assert_eq!(reported.len(), 2);
+        assert_eq!(reported[0].chunk.index, 10);
+        assert_eq!(reported[0].phase, Phase::Read);
+        assert_eq!(reported[0].median, normal);
+        assert_eq!(reported[1].chunk.index, 11);
+        assert_eq!(reported[1].phase, Phase::Hash);
+    }
+}
@@ -0,0 +1,13 @@
+//! Readers to hash from besides files and in-memory buffers
+pub mod scatter;
+pub mod zero;
+
+/// Apply a relative seek to `base`, `None` if the result is negative or
+/// overflows
+fn offset_by(base: u64, delta: i64) -> Option<u64> {
+    if delta >= 0 {
+        base.checked_add(delta as u64)
+    } else {
+        base.checked_sub(delta.unsigned_abs())
+    }
+}
@@ -0,0 +1,129 @@
+use super::offset_by;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+/// Seekable reader presenting a list of non-contiguous memory regions, such
+/// as an iovec list taken from a ring buffer, as one logical stream, so they
+/// can be hashed without linearizing them first
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, sources::scatter::ScatterReader, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// let (head, tail) = WORDSTRING.as_bytes().split_at(13);
+/// let mut source = ScatterReader::new(vec![head, tail]);
+/// let scattered: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut source, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let contiguous: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// assert_eq!(scattered, contiguous);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScatterReader<'a> {
+    /// Memory regions, in stream order
+    regions: Vec<&'a [u8]>,
+    /// Stream offset of every region
+    starts: Vec<u64>,
+    /// Total size of all regions
+    size: u64,
+    /// Current position
+    position: u64,
+}
+
+impl<'a> ScatterReader<'a> {
+    /// Instantiate a reader over memory regions
+    /// # Arguments
+    /// * `regions` - memory regions making up the stream, in order
+    pub fn new<I: IntoIterator<Item = &'a [u8]>>(regions: I) -> Self {
+        let regions: Vec<&'a [u8]> = regions.into_iter().collect();
+        let mut starts = Vec::with_capacity(regions.len());
+        let mut size = 0;
+        for region in &regions {
+            starts.push(size);
+            size += region.len() as u64;
+        }
+        Self {
+            regions,
+            starts,
+            size,
+            position: 0,
+        }
+    }
+
+    /// Total size of the stream
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl Read for ScatterReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if self.position >= self.size {
+            return Ok(0);
+        }
+        // Last region starting at or before the position, skipping empty ones
+        let mut region = self.starts.partition_point(|&start| start <= self.position) - 1;
+        let mut written = 0;
+        while written < buf.len() && region < self.regions.len() {
+            let skip = (self.position - self.starts[region]) as usize;
+            let available = &self.regions[region][skip..];
+            let length = available.len().min(buf.len() - written);
+            buf[written..written + length].copy_from_slice(&available[..length]);
+            written += length;
+            self.position += length as u64;
+            region += 1;
+        }
+        Ok(written)
+    }
+}
+
+impl Seek for ScatterReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => offset_by(self.size, delta),
+            SeekFrom::Current(delta) => offset_by(self.position, delta),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_across_regions() -> Result<()> {
+        let regions: [&[u8]; 4] = [b"brain", b"", b"storm", b"remunerate"];
+        let mut reader = ScatterReader::new(regions.iter().cloned());
+        assert_eq!(reader.size(), 20);
+        let mut buf = [0u8; 8];
+        assert_eq!(reader.read(&mut buf)?, 8);
+        assert_eq!(&buf, b"brainsto");
+        assert_eq!(reader.seek(SeekFrom::Start(5))?, 5);
+        let mut buf = [0u8; 12];
+        assert_eq!(reader.read(&mut buf)?, 12);
+        assert_eq!(&buf, b"stormremuner");
+        assert_eq!(reader.read(&mut buf)?, 3);
+        assert_eq!(&buf[..3], b"ate");
+        assert_eq!(reader.read(&mut buf)?, 0);
+        assert!(reader.seek(SeekFrom::Current(-21)).is_err());
+        Ok(())
+    }
+}
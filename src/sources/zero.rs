@@ -0,0 +1,87 @@
+use super::offset_by;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+/// Seekable reader producing a given amount of zero bytes without touching
+/// any storage, to measure hashing throughput without I/O
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::null::NullHasher, sources::zero::ZeroReader, Chunk, ChunkedHasher};
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// let mut source = ZeroReader::new(1024 * 1024);
+/// let chunks: Vec<Chunk> =
+///     ChunkedHasher::<NullHasher>::fixed_chunks(&mut source, 1024 * 1024, 64 * 1024)?.collect();
+/// assert_eq!(chunks.len(), 16);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ZeroReader {
+    /// Amount of zero bytes to produce
+    size: u64,
+    /// Current position
+    position: u64,
+}
+
+impl ZeroReader {
+    /// Instantiate a zero reader
+    /// # Arguments
+    /// * `size` - amount of zero bytes to produce
+    pub fn new(size: u64) -> Self {
+        Self { size, position: 0 }
+    }
+}
+
+impl Read for ZeroReader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let remaining = self.size.saturating_sub(self.position);
+        let length = (buf.len() as u64).min(remaining) as usize;
+        for byte in &mut buf[..length] {
+            *byte = 0;
+        }
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for ZeroReader {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => offset_by(self.size, delta),
+            SeekFrom::Current(delta) => offset_by(self.position, delta),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_and_seeks() -> Result<()> {
+        let mut reader = ZeroReader::new(10);
+        let mut buf = [1u8; 8];
+        assert_eq!(reader.read(&mut buf)?, 8);
+        assert_eq!(buf, [0u8; 8]);
+        assert_eq!(reader.read(&mut buf)?, 2);
+        assert_eq!(reader.read(&mut buf)?, 0);
+        assert_eq!(reader.seek(SeekFrom::End(-4))?, 6);
+        assert_eq!(reader.seek(SeekFrom::Current(2))?, 8);
+        assert!(reader.seek(SeekFrom::Current(-9)).is_err());
+        assert_eq!(reader.seek(SeekFrom::Start(20))?, 20);
+        assert_eq!(reader.read(&mut buf)?, 0);
+        Ok(())
+    }
+}
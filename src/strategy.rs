@@ -0,0 +1,487 @@
+//! Pluggable chunk boundary selection
+//!
+//! A [`ChunkingStrategy`](trait.ChunkingStrategy.html) decides where the next
+//! chunk ends given the data following the previous boundary.
+//! [`ChunkedHasher::strategy_chunks`](../struct.ChunkedHasher.html#method.strategy_chunks)
+//! reads the stream sequentially and hashes the chunks it selects, so custom
+//! boundary logic doesn't require a separate iterator. Besides the strategies
+//! of this module, [`FastCdc`](../cdc/struct.FastCdc.html) and
+//! [`RabinChunker`](../rabin/struct.RabinChunker.html) implement it.
+
+use crate::{
+    cdc::{random_table, GEAR},
+    layout,
+};
+use anyhow::{ensure, Result};
+
+/// Buzhash table, one random value per byte value
+const BUZHASH: [u64; 256] = random_table(0x6275_7a68_6173_6821);
+
+/// Chunk boundary selection
+pub trait ChunkingStrategy {
+    /// Maximum chunk size, the amount of data handed to
+    /// [`cut_point`](#tymethod.cut_point) unless the stream ends earlier
+    fn max_size(&self) -> u64;
+
+    /// Length of the first chunk of the data, between 1 and the data length
+    ///
+    /// The data holds at least `max_size` bytes unless it's the end of the
+    /// stream.
+    /// # Arguments
+    /// * `data` - data starting at a chunk boundary
+    fn cut_point(&self, data: &[u8]) -> usize;
+}
+
+impl<S: ChunkingStrategy + ?Sized> ChunkingStrategy for Box<S> {
+    fn max_size(&self) -> u64 {
+        (**self).max_size()
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        (**self).cut_point(data)
+    }
+}
+
+/// Chunks of a fixed size, the last chunk holding the remainder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fixed {
+    size: u64,
+}
+
+impl Fixed {
+    /// Instantiate a fixed size strategy
+    /// # Arguments
+    /// * `size` - chunk size
+    pub fn new(size: u64) -> Result<Self> {
+        ensure!(size > 0, "Chunk size must be greater than zero");
+        Ok(Self { size })
+    }
+}
+
+impl ChunkingStrategy for Fixed {
+    fn max_size(&self) -> u64 {
+        self.size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        (data.len() as u64).min(self.size) as usize
+    }
+}
+
+/// A given amount of equally sized chunks, plus a remainder chunk if the
+/// stream size isn't divisible by it, the same layout as
+/// [`ChunkedHasher::dynamic_chunks`](../struct.ChunkedHasher.html#method.dynamic_chunks)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Count {
+    size: u64,
+}
+
+impl Count {
+    /// Instantiate a count-based strategy
+    /// # Arguments
+    /// * `stream_size` - total stream size, the chunk size is derived from it
+    /// * `amount` - amount of chunks to split into
+    pub fn new(stream_size: u64, amount: u64) -> Result<Self> {
+        ensure!(amount > 0, "Dynamic amount must be greater than zero");
+        Ok(Self {
+            size: layout::dynamic_chunk_size(stream_size, amount).max(1),
+        })
+    }
+}
+
+impl ChunkingStrategy for Count {
+    fn max_size(&self) -> u64 {
+        self.size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        (data.len() as u64).min(self.size) as usize
+    }
+}
+
+/// Content-defined chunks using a plain gear hash, a boundary following
+/// every byte where the top bits of the hash are zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gear {
+    min_size: u64,
+    max_size: u64,
+    mask: u64,
+}
+
+impl Gear {
+    /// Instantiate a gear hash strategy
+    /// # Arguments
+    /// * `min_size` - minimum chunk size, except for the last chunk
+    /// * `avg_size` - desired average chunk size, rounded down to a power of
+    ///   two
+    /// * `max_size` - maximum chunk size
+    pub fn new(min_size: u64, avg_size: u64, max_size: u64) -> Result<Self> {
+        ensure!(min_size > 0, "Minimum size must be greater than zero");
+        ensure!(
+            min_size <= avg_size && avg_size <= max_size,
+            "Chunk sizes must satisfy minimum <= average <= maximum"
+        );
+        ensure!(avg_size >= 2, "Average size must be at least 2");
+        let bits = 63 - avg_size.leading_zeros();
+        Ok(Self {
+            min_size,
+            max_size,
+            mask: !0u64 << (64 - bits),
+        })
+    }
+}
+
+impl ChunkingStrategy for Gear {
+    fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let end = (data.len() as u64).min(self.max_size) as usize;
+        if end as u64 <= self.min_size {
+            return end;
+        }
+        let mut hash = 0u64;
+        for (index, byte) in data
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(self.min_size as usize)
+        {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            if hash & self.mask == 0 {
+                return index + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Content-defined chunks using buzhash, a cyclic polynomial rolling hash of
+/// the last `window_size` bytes, a boundary following every byte where the
+/// masked hash is zero
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Buzhash {
+    window_size: usize,
+    mask: u64,
+    min_size: u64,
+    max_size: u64,
+}
+
+impl Buzhash {
+    /// Instantiate a buzhash strategy
+    /// # Arguments
+    /// * `window_size` - amount of bytes the hash covers
+    /// * `mask` - boundary mask, `2^n - 1` for an average chunk size of about
+    ///   `2^n` bytes
+    /// * `min_size` - minimum chunk size, except for the last chunk, at least
+    ///   the window size
+    /// * `max_size` - maximum chunk size
+    pub fn new(window_size: usize, mask: u64, min_size: u64, max_size: u64) -> Result<Self> {
+        ensure!(window_size > 0, "Window size must be greater than zero");
+        ensure!(
+            min_size >= window_size as u64,
+            "Minimum size must not be less than the window size"
+        );
+        ensure!(
+            min_size <= max_size,
+            "Minimum size must not be greater than the maximum size"
+        );
+        Ok(Self {
+            window_size,
+            mask,
+            min_size,
+            max_size,
+        })
+    }
+}
+
+impl ChunkingStrategy for Buzhash {
+    fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let end = (data.len() as u64).min(self.max_size) as usize;
+        if end as u64 <= self.min_size {
+            return end;
+        }
+        // The hash only depends on the window, so rolling can start a window
+        // before the first possible boundary
+        let start = self.min_size as usize - self.window_size;
+        let rotation = (self.window_size % 64) as u32;
+        let mut hash = data[start..self.min_size as usize]
+            .iter()
+            .fold(0u64, |hash, byte| {
+                hash.rotate_left(1) ^ BUZHASH[*byte as usize]
+            });
+        for index in self.min_size as usize..end {
+            let outgoing = BUZHASH[data[index - self.window_size] as usize];
+            hash = hash.rotate_left(1)
+                ^ outgoing.rotate_left(rotation)
+                ^ BUZHASH[data[index] as usize];
+            if hash & self.mask == 0 {
+                return index + 1;
+            }
+        }
+        end
+    }
+}
+
+/// Minimum and maximum chunk size constraints on top of another strategy
+///
+/// Boundaries the wrapped strategy selects before the minimum size are
+/// skipped, and chunks are cut at the maximum size if the wrapped strategy
+/// selects no boundary before it. Optionally, a trailing chunk smaller than a
+/// threshold is merged into the previous chunk, which may then exceed the
+/// maximum size by less than the threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constrained<S> {
+    strategy: S,
+    min_size: u64,
+    max_size: u64,
+    tail_merge: u64,
+}
+
+impl<S: ChunkingStrategy> Constrained<S> {
+    /// Wrap a strategy, without constraining it until configured
+    /// # Arguments
+    /// * `strategy` - strategy selecting the boundaries
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use chunked_hasher::{hashers::sha2::Sha256Hasher, strategy::{Constrained, Fixed}, Chunk, ChunkedHasher};
+    /// # use std::io::Cursor;
+    /// # use anyhow::Result;
+    /// # pub fn main() -> Result<()> {
+    /// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+    /// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+    /// let strategy = Constrained::new(Fixed::new(12)?).with_tail_merge(6);
+    /// let chunks: Vec<Chunk> =
+    ///     ChunkedHasher::<Sha256Hasher>::strategy_chunks(&mut buffer, WORDSTRING.len() as u64, strategy)?
+    ///         .collect();
+    /// // The trailing 4 bytes are merged into the third chunk
+    /// assert_eq!(chunks.iter().map(|chunk| chunk.size).collect::<Vec<_>>(), vec![12, 12, 16]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(strategy: S) -> Self {
+        let max_size = strategy.max_size();
+        Self {
+            strategy,
+            min_size: 1,
+            max_size,
+            tail_merge: 0,
+        }
+    }
+
+    /// Skip boundaries before a minimum chunk size, except for the last
+    /// chunk, capped by the maximum size
+    /// # Arguments
+    /// * `size` - minimum chunk size
+    pub fn with_min_size(mut self, size: u64) -> Self {
+        self.min_size = size.max(1);
+        self
+    }
+
+    /// Cut chunks at a maximum size
+    /// # Arguments
+    /// * `size` - maximum chunk size
+    pub fn with_max_size(mut self, size: u64) -> Self {
+        self.max_size = size.max(1);
+        self
+    }
+
+    /// Merge a trailing chunk smaller than a threshold into the previous
+    /// chunk
+    /// # Arguments
+    /// * `threshold` - size below which the trailing chunk is merged, zero to
+    ///   never merge it
+    pub fn with_tail_merge(mut self, threshold: u64) -> Self {
+        self.tail_merge = threshold;
+        self
+    }
+}
+
+impl<S: ChunkingStrategy> ChunkingStrategy for Constrained<S> {
+    fn max_size(&self) -> u64 {
+        // Reading past the maximum size tells a trailing chunk apart
+        self.max_size + self.tail_merge
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let end = (data.len() as u64).min(self.max_size) as usize;
+        let min_size = self.min_size.min(self.max_size) as usize;
+        let mut cut = 0;
+        while cut < min_size && cut < end {
+            cut += self.strategy.cut_point(&data[cut..]).max(1);
+        }
+        let cut = cut.min(end);
+        // Less data than requested means the stream ends within it
+        let at_end = (data.len() as u64) < self.max_size();
+        if at_end && ((data.len() - cut) as u64) < self.tail_merge {
+            data.len()
+        } else {
+            cut
+        }
+    }
+}
+
+/// Boundaries of another strategy moved forward to the next multiple of a
+/// block size, such as the sector size of a device written with `O_DIRECT`
+///
+/// Only the last chunk of the stream may end unaligned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Aligned<S> {
+    strategy: S,
+    alignment: u64,
+}
+
+impl<S: ChunkingStrategy> Aligned<S> {
+    /// Align the boundaries of a strategy
+    /// # Arguments
+    /// * `strategy` - strategy selecting the boundaries
+    /// * `alignment` - block size the boundaries are aligned to
+    pub fn new(strategy: S, alignment: u64) -> Result<Self> {
+        ensure!(alignment > 0, "Alignment must be greater than zero");
+        Ok(Self {
+            strategy,
+            alignment,
+        })
+    }
+}
+
+impl<S: ChunkingStrategy> ChunkingStrategy for Aligned<S> {
+    fn max_size(&self) -> u64 {
+        layout::align_up(self.strategy.max_size(), self.alignment)
+    }
+
+    fn cut_point(&self, data: &[u8]) -> usize {
+        let cut = self.strategy.cut_point(data) as u64;
+        // Less data than an aligned cut needs only happens at the end of the
+        // stream
+        layout::align_up(cut, self.alignment).min(data.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(length: usize) -> Vec<u8> {
+        let mut state = 3u32;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn boundaries(strategy: &dyn ChunkingStrategy, data: &[u8]) -> Vec<usize> {
+        let mut boundaries = Vec::new();
+        let mut start = 0;
+        while start < data.len() {
+            let size = strategy.cut_point(&data[start..]);
+            assert!(size > 0 && size as u64 <= strategy.max_size());
+            start += size;
+            boundaries.push(start);
+        }
+        boundaries
+    }
+
+    #[test]
+    fn fixed_cuts_at_the_chunk_size() -> Result<()> {
+        let fixed = Fixed::new(10)?;
+        assert_eq!(boundaries(&fixed, &data(35)), vec![10, 20, 30, 35]);
+        assert!(Fixed::new(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn count_matches_dynamic_chunks() -> Result<()> {
+        assert_eq!(
+            boundaries(&Count::new(45, 4)?, &data(45)),
+            vec![11, 22, 33, 44, 45]
+        );
+        assert_eq!(boundaries(&Count::new(3, 4)?, &data(3)), vec![3]);
+        assert!(Count::new(45, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn constraints_bound_chunk_sizes() -> Result<()> {
+        let data = data(4096);
+        let gear = Gear::new(2, 16, 1024)?;
+        let constrained = Constrained::new(gear).with_min_size(32).with_max_size(48);
+        let sizes: Vec<usize> = boundaries(&constrained, &data)
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        assert!(sizes[..sizes.len() - 1]
+            .iter()
+            .all(|size| (32..=48).contains(size)));
+        let capped = Constrained::new(Fixed::new(1000)?).with_max_size(48);
+        assert_eq!(boundaries(&capped, &data[..100]), vec![48, 96, 100]);
+        Ok(())
+    }
+
+    #[test]
+    fn trailing_chunk_is_merged_below_threshold() -> Result<()> {
+        let merged = Constrained::new(Fixed::new(10)?).with_tail_merge(5);
+        assert_eq!(boundaries(&merged, &data(43)), vec![10, 20, 30, 43]);
+        assert_eq!(boundaries(&merged, &data(45)), vec![10, 20, 30, 40, 45]);
+        assert_eq!(boundaries(&merged, &data(40)), vec![10, 20, 30, 40]);
+        Ok(())
+    }
+
+    #[test]
+    fn aligned_boundaries_are_block_multiples() -> Result<()> {
+        let data = data(64 * 1024);
+        let aligned = Aligned::new(Gear::new(64, 512, 4096)?, 1000)?;
+        assert_eq!(aligned.max_size(), 5000);
+        let boundaries = boundaries(&aligned, &data);
+        let (last, rest) = boundaries.split_last().unwrap();
+        assert!(rest.iter().all(|boundary| boundary % 1000 == 0));
+        assert_eq!(*last, data.len());
+        assert!(Aligned::new(Fixed::new(10)?, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn buzhash_matches_hash_of_the_window() -> Result<()> {
+        // A boundary depends on the window only, wherever the chunk started
+        let buzhash = Buzhash::new(16, 0x3f, 16, 4096)?;
+        let data = data(8 * 1024);
+        for boundary in boundaries(&buzhash, &data) {
+            if boundary >= 17 && boundary < data.len() {
+                assert_eq!(buzhash.cut_point(&data[boundary - 17..]), 17);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rolling_strategies_resync_after_insertion() -> Result<()> {
+        let strategies: Vec<Box<dyn ChunkingStrategy>> = vec![
+            Box::new(Gear::new(64, 512, 4096)?),
+            Box::new(Buzhash::new(32, 0x1ff, 64, 4096)?),
+        ];
+        let original = data(64 * 1024);
+        let mut edited = original.clone();
+        edited.splice(100..100, b"inserted".iter().cloned());
+        for strategy in &strategies {
+            let before = boundaries(strategy, &original);
+            let average = original.len() / before.len();
+            assert!((256..=1024).contains(&average), "average {}", average);
+            let after: Vec<usize> = boundaries(strategy, &edited)
+                .into_iter()
+                .map(|boundary| boundary - 8)
+                .collect();
+            let shared = before.iter().filter(|b| after.contains(b)).count();
+            assert!(shared >= before.len() - 2);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Whole-stream summary emitted after the last chunk
+//!
+//! [`ChunkedHasher::summarized`](../struct.ChunkedHasher.html#method.summarized)
+//! yields every chunk followed by a final
+//! [`StreamSummary`](struct.StreamSummary.html), so consumers don't have to
+//! track totals themselves.
+
+use crate::{
+    hashers::{algorithm::DynHasher, Hasher},
+    Chunk, ChunkedHasher,
+};
+use std::time::{Duration, Instant};
+
+/// Totals of a hashed stream
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSummary {
+    /// Sum of all chunk sizes
+    pub total_bytes: u64,
+    /// Amount of chunks produced
+    pub chunk_count: u64,
+    /// Whole-stream digest, the hash of the concatenated chunk hashes like
+    /// [`stream_digest`](../fn.stream_digest.html), if enabled
+    pub digest: Option<Vec<u8>>,
+    /// Time from creating the summarizing iterator to producing the summary
+    pub elapsed: Duration,
+}
+
+/// Item of a summarizing iterator
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamItem {
+    /// The next chunk of the stream
+    Chunk(Chunk),
+    /// Totals of the stream, always the last item
+    Summary(StreamSummary),
+}
+
+/// Chunked hasher producing its chunks followed by a summary
+pub struct SummarizedChunks<'a, H: Hasher> {
+    inner: ChunkedHasher<'a, H>,
+    start: Instant,
+    total_bytes: u64,
+    chunk_count: u64,
+    /// Concatenated chunk hashes, when the whole-stream digest is enabled
+    hashes: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl<'a, H: Hasher> SummarizedChunks<'a, H> {
+    pub(crate) fn new(inner: ChunkedHasher<'a, H>) -> Self {
+        Self {
+            inner,
+            start: Instant::now(),
+            total_bytes: 0,
+            chunk_count: 0,
+            hashes: None,
+            finished: false,
+        }
+    }
+
+    /// Also compute the whole-stream digest, hashed with the algorithm the
+    /// chunks are hashed with
+    pub fn with_stream_digest(mut self) -> Self {
+        self.hashes = Some(Vec::new());
+        self
+    }
+}
+
+impl<H: Hasher> Iterator for SummarizedChunks<'_, H> {
+    type Item = StreamItem;
+
+    fn next(&mut self) -> Option<StreamItem> {
+        if self.finished {
+            return None;
+        }
+        if let Some(chunk) = self.inner.next() {
+            self.total_bytes += chunk.size;
+            self.chunk_count += 1;
+            if let Some(hashes) = self.hashes.as_mut() {
+                hashes.extend_from_slice(&chunk.hash);
+            }
+            return Some(StreamItem::Chunk(chunk));
+        }
+        self.finished = true;
+        let algorithm = self.inner.hashing.algorithm;
+        let digest = self.hashes.as_ref().map(|hashes| match algorithm {
+            Some(DynHasher(algorithm)) => algorithm.hash_bytes(hashes),
+            None => H::hash_bytes(hashes),
+        });
+        Some(StreamItem::Summary(StreamSummary {
+            total_bytes: self.total_bytes,
+            chunk_count: self.chunk_count,
+            digest,
+            elapsed: self.start.elapsed(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, stream_digest};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    #[test]
+    fn summary_follows_the_chunks() -> anyhow::Result<()> {
+        let mut buffer: Cursor<&[u8]> = Cursor::new(DATA);
+        let items: Vec<StreamItem> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, DATA.len() as u64, 16)?
+                .summarized()
+                .with_stream_digest()
+                .collect();
+        let chunks: Vec<Chunk> = items
+            .iter()
+            .filter_map(|item| match item {
+                StreamItem::Chunk(chunk) => Some(chunk.clone()),
+                StreamItem::Summary(_) => None,
+            })
+            .collect();
+        match items.last() {
+            Some(StreamItem::Summary(summary)) => {
+                assert_eq!(summary.total_bytes, 50);
+                assert_eq!(summary.chunk_count, 4);
+                assert_eq!(summary.digest, Some(stream_digest::<Sha256Hasher>(&chunks)));
+            }
+            other => panic!("Expected a summary, got {:?}", other),
+        }
+        assert_eq!(items.len(), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+//! Verification of local data against previously produced chunks
+
+use crate::{hashers::Hasher, read_full, Chunk, ChunkRef, ReadAndSeek};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek, SeekFrom},
+    sync::mpsc::{channel, Receiver},
+    thread::{self, JoinHandle},
+};
+
+/// Size of the reads when scanning a chunk for zero bytes
+const SCAN_SIZE: u64 = 64 * 1024;
+
+/// Why a chunk of a manifest is missing from local data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingReason {
+    /// The local data ends before the end of the chunk
+    Absent,
+    /// The chunk consists only of zero bytes, as in a pre-allocated region
+    /// that was not written yet
+    NotWritten,
+    /// The chunk was written but doesn't match the manifest
+    Corrupt,
+}
+
+/// Chunk that is missing from local data, with the reason it's missing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MissingChunk {
+    /// Position of the missing chunk
+    pub chunk: ChunkRef,
+    /// Why the chunk is missing
+    pub reason: MissingReason,
+}
+
+/// Find the chunks of a manifest that are absent or corrupt in local data,
+/// e.g. a partially downloaded file, returning the byte ranges to re-fetch
+///
+/// A chunk is reported when its byte range can't be read in full or when the
+/// hash of the local bytes doesn't match the manifest.
+/// # Arguments
+/// * `buffer` - the local data to check
+/// * `manifest` - chunks of the complete data, hashed with `H`
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, verify::missing_chunks, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let manifest: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let mut partial: Cursor<&[u8]> = Cursor::new(&WORDSTRING.as_bytes()[..25]);
+/// let missing = missing_chunks::<Sha256Hasher>(&mut partial, &manifest)?;
+/// assert_eq!(missing.iter().map(|chunk| chunk.index).collect::<Vec<u64>>(), vec![2, 3]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn missing_chunks<H: Hasher>(
+    buffer: &mut dyn ReadAndSeek,
+    manifest: &[Chunk],
+) -> Result<Vec<ChunkRef>> {
+    Ok(check_chunks::<H>(buffer, manifest, false)?
+        .into_iter()
+        .map(|missing| missing.chunk)
+        .collect())
+}
+
+/// Like [`missing_chunks`](fn.missing_chunks.html), but also classify why each
+/// chunk is missing
+///
+/// Chunks are scanned for zero bytes before hashing, so regions of a
+/// pre-allocated file that were not written yet are reported as
+/// [`NotWritten`](enum.MissingReason.html#variant.NotWritten) rather than
+/// [`Corrupt`](enum.MissingReason.html#variant.Corrupt), without hashing them.
+/// Chunks that legitimately consist of zero bytes are still recognized as
+/// present.
+/// # Arguments
+/// * `buffer` - the local data to check
+/// * `manifest` - chunks of the complete data, hashed with `H`
+pub fn classify_missing_chunks<H: Hasher>(
+    buffer: &mut dyn ReadAndSeek,
+    manifest: &[Chunk],
+) -> Result<Vec<MissingChunk>> {
+    check_chunks::<H>(buffer, manifest, true)
+}
+
+/// Result of verifying a single chunk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkResult {
+    /// Position of the verified chunk
+    pub chunk: ChunkRef,
+    /// Why the chunk is missing, `None` if it passed verification
+    pub missing: Option<MissingReason>,
+}
+
+impl ChunkResult {
+    /// Whether the chunk passed verification
+    pub fn passed(&self) -> bool {
+        self.missing.is_none()
+    }
+}
+
+/// Verify local data against a manifest on a background thread, streaming
+/// the result of every chunk as soon as it's known
+///
+/// Chunks are classified like in
+/// [`classify_missing_chunks`](fn.classify_missing_chunks.html). A single
+/// thread checks the chunks one after another, so results arrive in manifest
+/// order, one per chunk, never reordered by how long a chunk took to check.
+/// Sort the manifest by index first to receive results in index order. The
+/// thread stops early when the receiver is dropped, and returns any I/O
+/// error that ended verification prematurely.
+/// # Arguments
+/// * `buffer` - the local data to check
+/// * `manifest` - chunks of the complete data, hashed with `H`
+///
+/// # Example
+///
+/// ```
+/// use chunked_hasher::{hashers::sha2::Sha256Hasher, verify::spawn_verify, Chunk, ChunkedHasher};
+/// # use std::io::Cursor;
+/// # use anyhow::Result;
+/// # pub fn main() -> Result<()> {
+/// # const WORDSTRING: &str = "brainstormremuneratedisabilityexperiment";
+/// # let mut buffer: Cursor<&[u8]> = Cursor::new(WORDSTRING.as_bytes());
+/// let manifest: Vec<Chunk> =
+///     ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut buffer, WORDSTRING.len() as u64, 10)?
+///         .collect();
+/// let local = Cursor::new(WORDSTRING.as_bytes().to_vec());
+/// let (handle, results) = spawn_verify::<Sha256Hasher, _>(local, manifest);
+/// for result in results {
+///     assert!(result.passed());
+/// }
+/// handle.join().unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn spawn_verify<H, R>(
+    mut buffer: R,
+    manifest: Vec<Chunk>,
+) -> (JoinHandle<Result<()>>, Receiver<ChunkResult>)
+where
+    H: Hasher + 'static,
+    R: Read + Seek + Send + 'static,
+{
+    let (sender, receiver) = channel();
+    let handle = thread::spawn(move || {
+        let mut zero_hashes = HashMap::new();
+        for chunk in &manifest {
+            let padding = chunk.padding_in(&manifest);
+            let missing = check_chunk::<H>(&mut buffer, chunk, padding, Some(&mut zero_hashes))?;
+            let result = ChunkResult {
+                chunk: ChunkRef::from(chunk),
+                missing,
+            };
+            if sender.send(result).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+    (handle, receiver)
+}
+
+fn check_chunks<H: Hasher>(
+    buffer: &mut dyn ReadAndSeek,
+    manifest: &[Chunk],
+    detect_zero: bool,
+) -> Result<Vec<MissingChunk>> {
+    let mut zero_hashes = HashMap::new();
+    let mut missing = Vec::new();
+    for chunk in manifest {
+        let zero_hashes = if detect_zero {
+            Some(&mut zero_hashes)
+        } else {
+            None
+        };
+        let padding = chunk.padding_in(manifest);
+        if let Some(reason) = check_chunk::<H>(buffer, chunk, padding, zero_hashes)? {
+            missing.push(MissingChunk {
+                chunk: ChunkRef::from(chunk),
+                reason,
+            });
+        }
+    }
+    Ok(missing)
+}
+
+/// Verify a single chunk, only scanning for zero regions when given a cache
+/// of the hash of an all-zero chunk per chunk size, so zero regions only need
+/// to be hashed once, and also accepting the chunk's data followed by
+/// `padding` zeros
+///
+/// Chunks are read in bounded increments, a manifest claiming a huge chunk
+/// can't make us allocate more than the data actually read.
+fn check_chunk<H: Hasher>(
+    buffer: &mut dyn ReadAndSeek,
+    chunk: &Chunk,
+    padding: u64,
+    zero_hashes: Option<&mut HashMap<u64, Vec<u8>>>,
+) -> Result<Option<MissingReason>> {
+    if let Some(zero_hashes) = zero_hashes {
+        buffer.seek(SeekFrom::Start(chunk.offset))?;
+        match scan_zeros(buffer, chunk.size)? {
+            None => return Ok(Some(MissingReason::Absent)),
+            Some(true) => {
+                let mut lengths = vec![chunk.size];
+                if padding > 0 {
+                    lengths.push(chunk.size.saturating_add(padding));
+                }
+                for length in lengths {
+                    let zero_hash = match zero_hashes.get(&length) {
+                        Some(zero_hash) => zero_hash,
+                        None => {
+                            let (_, zero_hash) = H::hash_reader(&mut io::repeat(0), length)?;
+                            zero_hashes
+                                .entry(length)
+                                .or_insert_with(|| zero_hash.as_ref().to_vec())
+                        }
+                    };
+                    if chunk.matches(zero_hash) {
+                        return Ok(None);
+                    }
+                }
+                return Ok(Some(MissingReason::NotWritten));
+            }
+            Some(false) => {}
+        }
+    }
+    buffer.seek(SeekFrom::Start(chunk.offset))?;
+    let (read_bytes, hash) = H::hash_reader(&mut &mut *buffer, chunk.size)?;
+    if read_bytes < chunk.size {
+        return Ok(Some(MissingReason::Absent));
+    }
+    if chunk.matches(hash.as_ref()) {
+        return Ok(None);
+    }
+    if padding > 0 {
+        // The last chunk of a manifest may have been hashed zero-padded
+        buffer.seek(SeekFrom::Start(chunk.offset))?;
+        let mut padded = (&mut *buffer)
+            .take(chunk.size)
+            .chain(io::repeat(0).take(padding));
+        let (_, hash) = H::hash_reader(&mut padded, chunk.size.saturating_add(padding))?;
+        if chunk.matches(hash.as_ref()) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(MissingReason::Corrupt))
+}
+
+/// Whether the next `length` bytes are all zero, stopping at the first other
+/// byte, `None` if the data ends before
+fn scan_zeros(buffer: &mut dyn ReadAndSeek, length: u64) -> Result<Option<bool>> {
+    let mut scanned = vec![0; length.min(SCAN_SIZE) as usize];
+    let mut remaining = length;
+    while remaining > 0 {
+        let wanted = remaining.min(SCAN_SIZE) as usize;
+        let read_bytes = read_full(buffer, &mut scanned[..wanted])?;
+        if scanned[..read_bytes].iter().any(|byte| *byte != 0) {
+            return Ok(Some(false));
+        }
+        if read_bytes < wanted {
+            return Ok(None);
+        }
+        remaining -= wanted as u64;
+    }
+    Ok(Some(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hashers::sha2::Sha256Hasher, ChunkedHasher};
+    use std::io::Cursor;
+
+    const DATA: &[u8] = b"brainstormremuneratedisabilityexperimentgoalkeeper";
+
+    #[test]
+    fn detects_absent_and_corrupt_chunks() -> Result<()> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .collect();
+
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        assert!(missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?.is_empty());
+
+        // Pre-allocated file with the second chunk never written and the
+        // last chunk cut short
+        let mut partial = DATA[..45].to_vec();
+        for byte in &mut partial[10..20] {
+            *byte = 0;
+        }
+        let mut partial: Cursor<&[u8]> = Cursor::new(&partial);
+        let missing = missing_chunks::<Sha256Hasher>(&mut partial, &manifest)?;
+        assert_eq!(
+            missing,
+            vec![
+                ChunkRef {
+                    index: 1,
+                    offset: 10,
+                    size: 10
+                },
+                ChunkRef {
+                    index: 4,
+                    offset: 40,
+                    size: 10
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn classifies_zero_regions() -> Result<()> {
+        let mut data = DATA.to_vec();
+        for byte in &mut data[20..30] {
+            *byte = 0;
+        }
+        let mut complete: Cursor<&[u8]> = Cursor::new(&data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, data.len() as u64, 10)?
+                .collect();
+
+        let mut partial = data[..45].to_vec();
+        for byte in &mut partial[0..10] {
+            *byte = 0;
+        }
+        partial[35] = b'x';
+        let mut partial: Cursor<&[u8]> = Cursor::new(&partial);
+        let missing = classify_missing_chunks::<Sha256Hasher>(&mut partial, &manifest)?;
+        assert_eq!(
+            missing
+                .iter()
+                .map(|missing| (missing.chunk.index, missing.reason))
+                .collect::<Vec<(u64, MissingReason)>>(),
+            vec![
+                (0, MissingReason::NotWritten),
+                (3, MissingReason::Corrupt),
+                (4, MissingReason::Absent)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_digests_are_compared_by_prefix() -> Result<()> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let mut manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .with_truncation(8)?
+                .collect();
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        assert!(missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?.is_empty());
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        assert!(
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .with_truncation(0)
+                .is_err()
+        );
+
+        // An empty hash matches nothing
+        manifest[2].hash.clear();
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let missing = classify_missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?;
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].reason, MissingReason::Corrupt);
+        Ok(())
+    }
+
+    #[test]
+    fn padded_last_chunks_are_verified() -> Result<()> {
+        let mut data = DATA[..45].to_vec();
+        let mut complete: Cursor<&[u8]> = Cursor::new(&data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, data.len() as u64, 10)?
+                .collect();
+        let mut complete: Cursor<&[u8]> = Cursor::new(&data);
+        assert!(missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?.is_empty());
+        let (handle, results) =
+            spawn_verify::<Sha256Hasher, _>(Cursor::new(data.clone()), manifest.clone());
+        assert!(results.iter().all(|result| result.passed()));
+        handle.join().unwrap()?;
+
+        // A padded last chunk of zeros is still recognized as present
+        for byte in &mut data[40..] {
+            *byte = 0;
+        }
+        let mut complete: Cursor<&[u8]> = Cursor::new(&data);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, data.len() as u64, 10)?
+                .collect();
+        let mut complete: Cursor<&[u8]> = Cursor::new(&data);
+        assert!(classify_missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn huge_claimed_chunks_are_absent() -> Result<()> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let mut manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .collect();
+        manifest[1].size = u64::MAX / 2;
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let missing = classify_missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?;
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].reason, MissingReason::Absent);
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        assert_eq!(
+            missing_chunks::<Sha256Hasher>(&mut complete, &manifest)?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn background_verification_streams_results() -> Result<()> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .collect();
+        let mut local = DATA[..45].to_vec();
+        local[15] = b'x';
+        let (handle, results) = spawn_verify::<Sha256Hasher, _>(Cursor::new(local), manifest);
+        let results: Vec<ChunkResult> = results.iter().collect();
+        handle.join().unwrap()?;
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.missing)
+                .collect::<Vec<Option<MissingReason>>>(),
+            vec![
+                None,
+                Some(MissingReason::Corrupt),
+                None,
+                None,
+                Some(MissingReason::Absent)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn background_results_follow_manifest_order() -> Result<()> {
+        let mut complete: Cursor<&[u8]> = Cursor::new(DATA);
+        let mut manifest: Vec<Chunk> =
+            ChunkedHasher::<Sha256Hasher>::fixed_chunks(&mut complete, DATA.len() as u64, 10)?
+                .collect();
+        manifest.reverse();
+        let mut local = DATA.to_vec();
+        local[25] = b'x';
+        let (handle, results) = spawn_verify::<Sha256Hasher, _>(Cursor::new(local), manifest);
+        let results: Vec<ChunkResult> = results.iter().collect();
+        handle.join().unwrap()?;
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| (result.chunk.index, result.passed()))
+                .collect::<Vec<(u64, bool)>>(),
+            vec![(4, true), (3, true), (2, false), (1, true), (0, true)]
+        );
+        Ok(())
+    }
+}